@@ -0,0 +1,383 @@
+use rag_core::config::ChunkingConfig;
+use rag_core::{AstContext, Chunk};
+use std::path::Path;
+
+/// Boundary-aware text splitter.
+///
+/// Splitting priority (no AST parsing yet, see SPEC.md "Chunking Strategy"):
+/// 1. Paragraph boundaries (blank lines) - separates top-level items in most code and docs
+/// 2. Line boundaries - for paragraphs larger than `max_chunk_size`
+/// 3. Char boundaries - for single lines larger than `max_chunk_size`
+///
+/// Chunks are contiguous slices of the input after their overlap prefix:
+/// `text[start_byte..end_byte] == content[context_prefix_bytes..]`. Whitespace-only
+/// slices are dropped. Markdown is first split at headings, see
+/// [`SemanticChunker::chunk_markdown`].
+pub struct SemanticChunker {
+    max_chunk_size: usize,
+    /// Bytes from the end of the previous chunk prepended to each chunk as context
+    overlap: usize,
+    language: Option<String>,
+}
+
+impl SemanticChunker {
+    pub fn new(max_chunk_size: usize, overlap: usize) -> Self {
+        Self {
+            max_chunk_size: max_chunk_size.max(1),
+            overlap,
+            language: None,
+        }
+    }
+
+    pub fn from_config(config: &ChunkingConfig) -> Self {
+        Self::new(config.max_chunk_size, config.chunk_overlap)
+    }
+
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn chunk(&self, text: &str) -> Vec<Chunk> {
+        if self.language.as_deref() == Some("markdown") {
+            return self.chunk_markdown(text);
+        }
+
+        let mut chunks = Vec::new();
+        for (start, end) in self.chunk_ranges(text, 0, text.len()) {
+            Self::push_chunk(&mut chunks, text, start, end, None);
+        }
+        self.add_overlap(&mut chunks);
+        chunks
+    }
+
+    /// Split at ATX headings (`#` to `######`, outside code fences) so every section
+    /// starts a new chunk; sections longer than `max_chunk_size` are split further like
+    /// plain text. Section chunks carry a `heading_section` [`AstContext`] whose `depth`
+    /// is the heading level and whose `parent_types` are the enclosing headings' titles,
+    /// outermost first. Text before the first heading has no context.
+    pub fn chunk_markdown(&self, text: &str) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+        let mut ancestors: Vec<(usize, String)> = Vec::new();
+
+        for (section_start, section_end, heading) in markdown_sections(text) {
+            let parent_types = heading.as_ref().map(|(level, title)| {
+                while ancestors.last().is_some_and(|(l, _)| l >= level) {
+                    ancestors.pop();
+                }
+                let parents = ancestors.iter().map(|(_, t)| t.clone()).collect();
+                ancestors.push((*level, title.clone()));
+                parents
+            });
+
+            for (start, end) in self.chunk_ranges(text, section_start, section_end) {
+                let context =
+                    heading
+                        .as_ref()
+                        .zip(parent_types.clone())
+                        .map(|((level, _), parent_types)| AstContext {
+                            node_type: "heading_section".to_string(),
+                            parent_types,
+                            depth: *level,
+                            // Only the chunk holding the heading line declares the section
+                            is_declaration: start == section_start,
+                        });
+                Self::push_chunk(&mut chunks, text, start, end, context);
+            }
+        }
+
+        self.add_overlap(&mut chunks);
+        chunks
+    }
+
+    /// Prepend up to `overlap` bytes from the end of the previous chunk's own content,
+    /// recording their length in `context_prefix_bytes`
+    fn add_overlap(&self, chunks: &mut [Chunk]) {
+        if self.overlap == 0 {
+            return;
+        }
+
+        let mut previous: Option<String> = None;
+        for chunk in chunks.iter_mut() {
+            let own = chunk.content.clone();
+            if let Some(previous) = previous {
+                let mut cut = previous.len().saturating_sub(self.overlap);
+                while !previous.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                let prefix = &previous[cut..];
+                chunk.content = format!("{}{}", prefix, own);
+                chunk.context_prefix_bytes = prefix.len();
+            }
+            previous = Some(own);
+        }
+    }
+
+    /// Atoms of `text[start..end]` merged greedily into ranges of at most
+    /// `max_chunk_size` bytes
+    fn chunk_ranges(&self, text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+
+        for (start, end) in self.atoms(text, start, end) {
+            current = match current {
+                Some((cur_start, cur_end)) if end - cur_start <= self.max_chunk_size => {
+                    debug_assert_eq!(cur_end, start);
+                    Some((cur_start, end))
+                }
+                Some(range) => {
+                    ranges.push(range);
+                    Some((start, end))
+                }
+                None => Some((start, end)),
+            };
+        }
+
+        ranges.extend(current);
+        ranges
+    }
+
+    /// Contiguous byte ranges covering `text[start..end]`, each at most
+    /// `max_chunk_size` long
+    fn atoms(&self, text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+        let mut atoms = Vec::new();
+
+        for (start, end) in paragraph_ranges(text, start, end) {
+            if end - start <= self.max_chunk_size {
+                atoms.push((start, end));
+                continue;
+            }
+
+            for (line_start, line_end) in line_ranges(text, start, end) {
+                if line_end - line_start <= self.max_chunk_size {
+                    atoms.push((line_start, line_end));
+                } else {
+                    atoms.extend(self.char_ranges(text, line_start, line_end));
+                }
+            }
+        }
+
+        atoms
+    }
+
+    fn char_ranges(&self, text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut pos = start;
+
+        while pos < end {
+            let mut split = (pos + self.max_chunk_size).min(end);
+            while !text.is_char_boundary(split) {
+                split -= 1;
+            }
+            if split == pos {
+                // Single char wider than max_chunk_size: take it whole
+                split = pos + text[pos..].chars().next().map_or(1, char::len_utf8);
+            }
+            ranges.push((pos, split));
+            pos = split;
+        }
+
+        ranges
+    }
+
+    fn push_chunk(
+        chunks: &mut Vec<Chunk>,
+        text: &str,
+        start: usize,
+        end: usize,
+        ast_context: Option<AstContext>,
+    ) {
+        let content = &text[start..end];
+        if content.trim().is_empty() {
+            return;
+        }
+
+        chunks.push(Chunk {
+            content: content.to_string(),
+            start_byte: start,
+            end_byte: end,
+            context_prefix_bytes: 0,
+            ast_context,
+        });
+    }
+}
+
+/// Paragraph ranges within `text[start..end]`: a new paragraph starts at the first
+/// non-blank line after a blank line
+fn paragraph_ranges(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = start;
+    let mut prev_blank = false;
+
+    for (line_start, line_end) in line_ranges(text, start, end) {
+        let blank = text[line_start..line_end].trim().is_empty();
+        if prev_blank && !blank && line_start > start {
+            ranges.push((start, line_start));
+            start = line_start;
+        }
+        prev_blank = blank;
+    }
+
+    if start < end {
+        ranges.push((start, end));
+    }
+
+    ranges
+}
+
+/// `(start, end, heading)` of a markdown section; `heading` is `(level, title)`
+type Section = (usize, usize, Option<(usize, String)>);
+
+/// Markdown sections: each heading line opens a section that runs to the next heading;
+/// text before the first heading has no heading. Lines inside ``` or ~~~ fences are
+/// never headings.
+fn markdown_sections(text: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut heading = None;
+    let mut in_fence = false;
+
+    for (line_start, line_end) in line_ranges(text, 0, text.len()) {
+        let line = text[line_start..line_end].trim_start();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(next) = atx_heading(&text[line_start..line_end]) {
+            if line_start > start {
+                sections.push((start, line_start, heading.take()));
+            }
+            start = line_start;
+            heading = Some(next);
+        }
+    }
+
+    if start < text.len() {
+        sections.push((start, text.len(), heading));
+    }
+
+    sections
+}
+
+/// `(level, title)` of an ATX heading line: up to three spaces of indent, one to six
+/// `#`, then whitespace or the end of the line; closing `#`s are not part of the title
+fn atx_heading(line: &str) -> Option<(usize, String)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = line[indent..].trim_end();
+    let level = line.len() - line.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let title = rest.trim().trim_end_matches('#').trim_end();
+    Some((level, title.to_string()))
+}
+
+/// Line ranges within `text[start..end]`, each including its trailing newline
+fn line_ranges(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut pos = start;
+
+    for line in text[start..end].split_inclusive('\n') {
+        ranges.push((pos, pos + line.len()));
+        pos += line.len();
+    }
+
+    ranges
+}
+
+/// Map a file extension to the language name stored in `MemoryMetadata::language`
+pub fn detect_language(path: &Path) -> Option<String> {
+    let language = match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "java" => "java",
+        "rb" => "ruby",
+        "sh" | "bash" => "shell",
+        "toml" => "toml",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "md" | "markdown" => "markdown",
+        "txt" => "text",
+        _ => return None,
+    };
+
+    Some(language.to_string())
+}
+
+/// Best-effort language for free-form content: the source file extension when known,
+/// then programming-language keywords at line starts, then natural-language detection
+/// (ISO 639-1 code such as `"en"`). None for blank content.
+pub fn detect_content_language(content: &str, source_file: Option<&Path>) -> Option<String> {
+    if let Some(language) = source_file.and_then(detect_language) {
+        return Some(language);
+    }
+    if content.trim().is_empty() {
+        return None;
+    }
+    if let Some(language) = detect_code_language(content) {
+        return Some(language.to_string());
+    }
+
+    Some(iso_639_1(whichlang::detect_language(content)).to_string())
+}
+
+fn detect_code_language(content: &str) -> Option<&'static str> {
+    for line in content.lines() {
+        let line = line.trim_start();
+        if ["fn ", "pub fn ", "async fn ", "impl "]
+            .iter()
+            .any(|kw| line.starts_with(kw))
+        {
+            return Some("rust");
+        }
+        if line.starts_with("def ") || (line.starts_with("class ") && line.ends_with(':')) {
+            return Some("python");
+        }
+        if line.starts_with("func ") {
+            return Some("go");
+        }
+    }
+    None
+}
+
+fn iso_639_1(lang: whichlang::Lang) -> &'static str {
+    use whichlang::Lang;
+    match lang {
+        Lang::Ara => "ar",
+        Lang::Cmn => "zh",
+        Lang::Deu => "de",
+        Lang::Eng => "en",
+        Lang::Fra => "fr",
+        Lang::Hin => "hi",
+        Lang::Ita => "it",
+        Lang::Jpn => "ja",
+        Lang::Kor => "ko",
+        Lang::Nld => "nl",
+        Lang::Por => "pt",
+        Lang::Rus => "ru",
+        Lang::Spa => "es",
+        Lang::Swe => "sv",
+        Lang::Tur => "tr",
+        Lang::Vie => "vi",
+    }
+}
@@ -0,0 +1,21 @@
+use rag_chunking::detect_content_language;
+use std::path::Path;
+
+#[test]
+fn rust_code_is_detected_by_keywords() {
+    let code = "use std::io;\n\npub fn main() {\n    println!(\"hi\");\n}\n";
+    assert_eq!(detect_content_language(code, None).as_deref(), Some("rust"));
+}
+
+#[test]
+fn english_prose_is_detected() {
+    let prose = "The deployment finished without errors and every service is healthy again.";
+    assert_eq!(detect_content_language(prose, None).as_deref(), Some("en"));
+}
+
+#[test]
+fn source_file_extension_takes_precedence() {
+    let language = detect_content_language("def helper(): pass", Some(Path::new("notes.md")));
+    assert_eq!(language.as_deref(), Some("markdown"));
+    assert_eq!(detect_content_language("   ", None), None);
+}
@@ -0,0 +1,58 @@
+use rag_chunking::SemanticChunker;
+
+fn markdown_chunker(max_chunk_size: usize) -> SemanticChunker {
+    SemanticChunker::new(max_chunk_size, 0).with_language(Some("markdown".to_string()))
+}
+
+#[test]
+fn each_heading_section_becomes_a_chunk() {
+    let text = "## Install\n\nRun the installer.\n\n## Configure\n\nEdit the file.\n\n## Usage\n\nStart the server.\n";
+    let chunks = markdown_chunker(1000).chunk(text);
+
+    assert_eq!(chunks.len(), 3);
+    for (chunk, heading) in chunks
+        .iter()
+        .zip(["## Install", "## Configure", "## Usage"])
+    {
+        assert!(chunk.content.starts_with(heading), "{:?}", chunk.content);
+        let context = chunk.ast_context.as_ref().expect("heading context");
+        assert_eq!(context.node_type, "heading_section");
+        assert_eq!(context.depth, 2);
+        assert!(context.is_declaration);
+    }
+}
+
+#[test]
+fn nested_headings_record_ancestors_and_fences_are_ignored() {
+    let text = "# Guide\n\nIntro.\n\n## Setup\n\n```sh\n# not a heading\n```\n\n### Linux\n\nUse apt.\n\n## Notes\n\nDone.\n";
+    let chunks = markdown_chunker(1000).chunk(text);
+
+    let parents: Vec<Vec<String>> = chunks
+        .iter()
+        .map(|c| c.ast_context.as_ref().unwrap().parent_types.clone())
+        .collect();
+    assert_eq!(
+        parents,
+        vec![
+            vec![],
+            vec!["Guide".to_string()],
+            vec!["Guide".to_string(), "Setup".to_string()],
+            vec!["Guide".to_string()],
+        ]
+    );
+    assert!(chunks[1].content.contains("# not a heading"));
+}
+
+#[test]
+fn oversized_sections_are_split_by_paragraph() {
+    let text = "## Long\n\nFirst paragraph of text.\n\nSecond paragraph of text.\n";
+    let chunks = markdown_chunker(30).chunk(text);
+
+    assert!(chunks.len() > 1);
+    assert!(chunks[0].content.starts_with("## Long"));
+    assert!(chunks[0].ast_context.as_ref().unwrap().is_declaration);
+    assert!(chunks.iter().skip(1).all(|c| {
+        let context = c.ast_context.as_ref().unwrap();
+        context.node_type == "heading_section" && !context.is_declaration
+    }));
+}
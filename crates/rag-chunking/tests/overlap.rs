@@ -0,0 +1,35 @@
+use rag_chunking::SemanticChunker;
+
+#[test]
+fn each_chunk_starts_with_the_tail_of_the_previous_one() {
+    let line = format!("{}\n", "x".repeat(39));
+    let text: String = (0..20)
+        .map(|i| line.replacen('x', &i.to_string(), 1))
+        .collect();
+    let chunks = SemanticChunker::new(200, 50).chunk(&text);
+
+    assert!(chunks.len() > 2);
+    assert_eq!(chunks[0].context_prefix_bytes, 0);
+    for pair in chunks.windows(2) {
+        let previous = &pair[0].content[pair[0].context_prefix_bytes..];
+        let next = &pair[1];
+        assert_eq!(next.context_prefix_bytes, 50);
+        assert!(next.content.starts_with(&previous[previous.len() - 50..]));
+        assert_eq!(
+            &text[next.start_byte..next.end_byte],
+            &next.content[next.context_prefix_bytes..]
+        );
+    }
+}
+
+#[test]
+fn zero_overlap_leaves_chunks_untouched() {
+    let text = "first paragraph\n\nsecond paragraph\n";
+    let chunks = SemanticChunker::new(20, 0).chunk(text);
+
+    assert_eq!(chunks.len(), 2);
+    for chunk in &chunks {
+        assert_eq!(chunk.context_prefix_bytes, 0);
+        assert_eq!(&text[chunk.start_byte..chunk.end_byte], chunk.content);
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -8,12 +8,86 @@ pub struct Config {
     pub search: SearchConfig,
     pub chunking: ChunkingConfig,
     pub storage: StorageConfig,
+    /// Embedding provider; None keeps search BM25-only
+    #[serde(default)]
+    pub embedder: Option<EmbedderConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     #[serde(default = "default_log_level")]
     pub log_level: String,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Logs are appended here in addition to stderr
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Requests still running after this long are answered with a timeout error. The
+    /// request is not cancelled: it keeps running, so its writes (e.g. a `store_memory`)
+    /// may still commit, and requests queued behind it spend the wait from their own
+    /// timeout and can time out too.
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// OpenAI-compatible chat completions URL used by `summarize_scope`
+    #[serde(default)]
+    pub default_llm_endpoint: Option<String>,
+    /// Environment variable holding the LLM API key, never the key itself
+    #[serde(default = "default_llm_api_key_env")]
+    pub llm_api_key_env: String,
+    #[serde(default = "default_llm_model")]
+    pub llm_model: String,
+    /// Tool results carry a `json` content item with the raw data after the text
+    #[serde(default)]
+    pub structured_responses: bool,
+    /// How JSON-RPC messages are delimited on stdio
+    #[serde(default)]
+    pub transport_framing: FramingMode,
+    /// Token bucket for `tools/call`; unlimited when absent
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests_per_second: f64,
+    /// Calls allowed back to back before the rate applies; defaults to one second's worth
+    #[serde(default)]
+    pub burst: Option<u64>,
+    /// One bucket per tool name instead of one shared by all tools
+    #[serde(default)]
+    pub per_tool: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line: `timestamp`, `level`, `target`, `message` and event fields
+    Json,
+}
+
+/// Message framing of the stdio transport, used for requests and responses alike
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FramingMode {
+    /// One JSON message per line
+    #[default]
+    LineDelimited,
+    /// `Content-Length: N\r\n\r\n` followed by exactly N bytes of JSON
+    ContentLength,
+}
+
+/// How `BM25SearchEngine` weighs query terms in a document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoringMode {
+    /// Okapi BM25 with `bm25_k1` and `bm25_b`
+    #[default]
+    BM25,
+    /// Term count over document length, times a smoothed `ln((N + 1) / (df + 1)) + 1` IDF
+    TfIdf,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +100,59 @@ pub struct SearchConfig {
     pub bm25_k1: f32,
     #[serde(default = "default_bm25_b")]
     pub bm25_b: f32,
+    #[serde(default)]
+    pub scoring_mode: ScoringMode,
+    #[serde(default = "default_use_importance_boost")]
+    pub use_importance_boost: bool,
+    #[serde(default = "default_importance_boost_factor")]
+    pub importance_boost_factor: f32,
+    #[serde(default)]
+    pub time_decay_enabled: bool,
+    #[serde(default = "default_time_decay_half_life_days")]
+    pub time_decay_half_life_days: f32,
+    #[serde(default)]
+    pub stop_words: Option<Vec<String>>,
+    /// Newline-delimited stop words file
+    #[serde(default)]
+    pub stop_words_file: Option<PathBuf>,
+    #[serde(default)]
+    pub replace_default_stop_words: bool,
+    /// Dropped from the built-in list and `stop_words` (e.g. via `configure_search`)
+    #[serde(default)]
+    pub removed_stop_words: Vec<String>,
+    /// Off by default: indexes built without stemming would not match stemmed queries
+    #[serde(default)]
+    pub stemming_enabled: bool,
+    #[serde(default = "default_stemming_language")]
+    pub stemming_language: String,
+    /// Score multiplier when a quoted query phrase appears verbatim
+    #[serde(default = "default_phrase_bonus")]
+    pub phrase_bonus: f32,
+    /// Weight of query matches in a memory's tags and AST node type, added to the
+    /// content score
+    #[serde(default = "default_field_boost")]
+    pub field_boost: f32,
+    /// Also index character ngrams of each token, so "prog" matches "programming"
+    #[serde(default)]
+    pub ngram_enabled: bool,
+    #[serde(default = "default_ngram_size")]
+    pub ngram_size: usize,
+    /// Weight of ngram matches, added to the whole-token score
+    #[serde(default = "default_ngram_weight")]
+    pub ngram_weight: f32,
+    /// Matches counted per memory in regex search mode
+    #[serde(default = "default_regex_max_matches")]
+    pub regex_max_matches: usize,
+    /// Regex search gives up once scanning a scope takes longer than this
+    #[serde(default = "default_regex_timeout_ms")]
+    pub regex_timeout_ms: u64,
+    /// Wrap query terms in `search_memory` results in `highlight_prefix`/`highlight_suffix`
+    #[serde(default = "default_highlight_matches")]
+    pub highlight_matches: bool,
+    #[serde(default = "default_highlight_marker")]
+    pub highlight_prefix: String,
+    #[serde(default = "default_highlight_marker")]
+    pub highlight_suffix: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,12 +171,73 @@ pub struct StorageConfig {
     pub project_db_name: String,
     #[serde(default = "default_max_session_memories")]
     pub max_session_memories: usize,
+    /// Most memories one project may hold; unlimited when unset
+    #[serde(default)]
+    pub max_project_memories: Option<usize>,
+    /// Most memories the global scope may hold; unlimited when unset
+    #[serde(default)]
+    pub global_memory_quota: Option<usize>,
+    #[serde(default = "default_max_ingest_file_size_bytes")]
+    pub max_ingest_file_size_bytes: usize,
+    /// Largest memory content, in bytes, accepted by `store_memory` and file ingestion
+    #[serde(default = "default_max_content_length")]
+    pub max_content_length: usize,
+    #[serde(default = "default_auto_detect_language")]
+    pub auto_detect_language: bool,
+    /// Content-hash -> embedding entries kept (LRU) to skip re-embedding identical content
+    #[serde(default = "default_embedding_cache_max_entries")]
+    pub embedding_cache_max_entries: usize,
+    /// JSON Schema file that `metadata.custom` must satisfy; no validation when unset
+    #[serde(default)]
+    pub custom_metadata_schema: Option<PathBuf>,
+    /// Have `stats` break memories down by tag, language, source file and AST node
+    /// type. Scans every memory of the scope.
+    #[serde(default)]
+    pub extended_stats_enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedderConfig {
+    pub provider: EmbedderProvider,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedderProvider {
+    /// Local model directory (not supported yet; `create_embedder` rejects it)
+    Local(PathBuf),
+    /// OpenAI `/v1/embeddings` or a compatible server at `base_url`
+    OpenAI {
+        /// Environment variable holding the API key, never the key itself
+        api_key_env: String,
+        model: String,
+        #[serde(default)]
+        base_url: Option<String>,
+    },
+    /// Ollama `/api/embeddings`, `http://localhost:11434` unless `base_url` is set
+    Ollama {
+        #[serde(default)]
+        base_url: Option<String>,
+        model: String,
+    },
 }
 
 fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_request_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_llm_api_key_env() -> String {
+    "OPENAI_API_KEY".to_string()
+}
+
+fn default_llm_model() -> String {
+    "gpt-4o-mini".to_string()
+}
+
 fn default_k() -> usize {
     5
 }
@@ -66,6 +254,54 @@ fn default_bm25_b() -> f32 {
     0.75
 }
 
+fn default_use_importance_boost() -> bool {
+    true
+}
+
+fn default_importance_boost_factor() -> f32 {
+    1.0
+}
+
+fn default_time_decay_half_life_days() -> f32 {
+    30.0
+}
+
+fn default_stemming_language() -> String {
+    "en".to_string()
+}
+
+fn default_phrase_bonus() -> f32 {
+    2.0
+}
+
+fn default_field_boost() -> f32 {
+    2.0
+}
+
+fn default_ngram_size() -> usize {
+    3
+}
+
+fn default_ngram_weight() -> f32 {
+    0.3
+}
+
+fn default_regex_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_highlight_matches() -> bool {
+    true
+}
+
+fn default_highlight_marker() -> String {
+    "**".to_string()
+}
+
+fn default_regex_max_matches() -> usize {
+    1000
+}
+
 fn default_max_chunk_size() -> usize {
     512
 }
@@ -94,17 +330,63 @@ fn default_max_session_memories() -> usize {
     1000
 }
 
+fn default_max_ingest_file_size_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_max_content_length() -> usize {
+    64 * 1024
+}
+
+fn default_auto_detect_language() -> bool {
+    true
+}
+
+fn default_embedding_cache_max_entries() -> usize {
+    10_000
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             server: ServerConfig {
                 log_level: default_log_level(),
+                log_format: LogFormat::Text,
+                log_file: None,
+                request_timeout_ms: default_request_timeout_ms(),
+                default_llm_endpoint: None,
+                llm_api_key_env: default_llm_api_key_env(),
+                llm_model: default_llm_model(),
+                structured_responses: false,
+                transport_framing: FramingMode::default(),
+                rate_limit: None,
             },
             search: SearchConfig {
                 default_k: default_k(),
                 min_score: default_min_score(),
                 bm25_k1: default_bm25_k1(),
                 bm25_b: default_bm25_b(),
+                scoring_mode: ScoringMode::BM25,
+                use_importance_boost: default_use_importance_boost(),
+                importance_boost_factor: default_importance_boost_factor(),
+                time_decay_enabled: false,
+                time_decay_half_life_days: default_time_decay_half_life_days(),
+                stop_words: None,
+                stop_words_file: None,
+                replace_default_stop_words: false,
+                removed_stop_words: Vec::new(),
+                stemming_enabled: false,
+                stemming_language: default_stemming_language(),
+                phrase_bonus: default_phrase_bonus(),
+                field_boost: default_field_boost(),
+                ngram_enabled: false,
+                ngram_size: default_ngram_size(),
+                ngram_weight: default_ngram_weight(),
+                regex_max_matches: default_regex_max_matches(),
+                regex_timeout_ms: default_regex_timeout_ms(),
+                highlight_matches: default_highlight_matches(),
+                highlight_prefix: default_highlight_marker(),
+                highlight_suffix: default_highlight_marker(),
             },
             chunking: ChunkingConfig {
                 max_chunk_size: default_max_chunk_size(),
@@ -114,39 +396,251 @@ impl Default for Config {
                 global_db_path: default_global_db_path(),
                 project_db_name: default_project_db_name(),
                 max_session_memories: default_max_session_memories(),
+                max_project_memories: None,
+                global_memory_quota: None,
+                max_ingest_file_size_bytes: default_max_ingest_file_size_bytes(),
+                max_content_length: default_max_content_length(),
+                auto_detect_language: default_auto_detect_language(),
+                embedding_cache_max_entries: default_embedding_cache_max_entries(),
+                custom_metadata_schema: None,
+                extended_stats_enabled: false,
             },
+            embedder: None,
         }
     }
 }
 
 impl Config {
+    /// Config from the default path, or the defaults when that file does not exist
     pub fn load() -> Result<Self> {
         let config_path = Self::config_path();
 
         if config_path.exists() {
-            let contents =
-                std::fs::read_to_string(&config_path).context("Failed to read config file")?;
-            let config: Config =
-                toml::from_str(&contents).context("Failed to parse config file")?;
-            Ok(config)
+            Self::load_from(&config_path)
         } else {
             Ok(Self::default())
         }
     }
 
+    /// Apply the `RAG_MCP_LOG_LEVEL`, `RAG_MCP_BM25_K1`, `RAG_MCP_BM25_B` and
+    /// `RAG_MCP_DEFAULT_K` environment variables over the loaded values. The Zed
+    /// extension passes its settings this way. `RAG_MCP_DB_PATH` is read by the
+    /// `global_db_path` default instead.
+    pub fn apply_env_overrides(&mut self) -> Result<()> {
+        fn var<T: std::str::FromStr>(name: &str) -> Result<Option<T>>
+        where
+            T::Err: std::fmt::Display,
+        {
+            match std::env::var(name) {
+                Ok(value) => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|e| anyhow::anyhow!("Invalid {}={:?}: {}", name, value, e)),
+                Err(_) => Ok(None),
+            }
+        }
+
+        if let Some(level) = var("RAG_MCP_LOG_LEVEL")? {
+            self.server.log_level = level;
+        }
+        if let Some(k1) = var("RAG_MCP_BM25_K1")? {
+            self.search.bm25_k1 = k1;
+        }
+        if let Some(b) = var("RAG_MCP_BM25_B")? {
+            self.search.bm25_b = b;
+        }
+        if let Some(k) = var("RAG_MCP_DEFAULT_K")? {
+            self.search.default_k = k;
+        }
+        Ok(())
+    }
+
+    /// Config from `path`, which must exist
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path();
+        self.save_to(&Self::config_path())
+    }
 
-        if let Some(parent) = config_path.parent() {
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
         let contents = toml::to_string_pretty(self)?;
-        std::fs::write(&config_path, contents)?;
+        std::fs::write(path, contents)?;
 
         Ok(())
     }
 
+    /// Commented TOML equivalent to `Config::default()`, written by `rag-mcp init`.
+    /// Optional settings are included commented out.
+    pub fn default_toml() -> String {
+        let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+        let global_db_path = default_global_db_path();
+
+        format!(
+            r#"# rag-mcp configuration. Every setting is optional; removing a line restores
+# the default shown here.
+
+[server]
+# Log verbosity: "error", "warn", "info", "debug" or "trace" (RUST_LOG overrides it)
+log_level = {log_level}
+# "text" for human-readable lines, "json" for one JSON object per line
+log_format = "text"
+# Also append logs to this file (works in stdio serve mode). Default: unset
+# log_file = "/var/log/rag-mcp.log"
+# Requests still running after this many milliseconds are answered with an error.
+# They are not cancelled: their writes may still land, and queued requests wait for them
+request_timeout_ms = {request_timeout_ms}
+# OpenAI-compatible chat completions URL used by summarize_scope. Default: unset
+# default_llm_endpoint = "https://api.openai.com/v1/chat/completions"
+# Environment variable holding the LLM API key (never the key itself)
+llm_api_key_env = {llm_api_key_env}
+# Chat model sent to default_llm_endpoint
+llm_model = {llm_model}
+# Follow each tool's text result with a {{"type": "json"}} item holding the raw data
+structured_responses = false
+# "line_delimited": one JSON message per line; "content_length": each message follows
+# a Content-Length header, as in the Language Server Protocol
+transport_framing = "line_delimited"
+
+# Token bucket for tools/call; calls beyond it fail with error -32003. Default: unlimited
+# [server.rate_limit]
+# requests_per_second = 10.0
+# Calls allowed back to back before the rate applies. Default: one second's worth
+# burst = 20
+# true gives every tool its own bucket
+# per_tool = false
+
+[search]
+# Results returned by search_memory when k is not given
+default_k = {default_k}
+# Matches scoring below this are dropped
+min_score = {min_score:?}
+# BM25 term frequency saturation, usually 1.2 to 2.0
+bm25_k1 = {bm25_k1:?}
+# BM25 document length normalization, 0.0 (none) to 1.0 (full)
+bm25_b = {bm25_b:?}
+# "bm25", or "tfidf" for classic TF-IDF (bm25_k1 and bm25_b are then unused)
+scoring_mode = "bm25"
+# Multiply scores by each memory's importance_score
+use_importance_boost = {use_importance_boost}
+# Strength of the importance boost; 0.0 disables it
+importance_boost_factor = {importance_boost_factor:?}
+# Halve scores every time_decay_half_life_days of memory age
+time_decay_enabled = false
+time_decay_half_life_days = {time_decay_half_life_days:?}
+# Extra stop words, merged with the built-in English list. Default: unset
+# stop_words = ["fn", "let"]
+# Newline-delimited stop words file. Default: unset
+# stop_words_file = "~/.config/rag-mcp/stop_words.txt"
+# true uses only stop_words and stop_words_file, without the built-in list
+replace_default_stop_words = false
+# Words dropped from the built-in list and stop_words
+removed_stop_words = []
+# Snowball stemming ("programming" matches "program"); reindex after changing it
+stemming_enabled = false
+# Stemming language code, e.g. "en", "de", "fr"
+stemming_language = {stemming_language}
+# Score multiplier when a "quoted phrase" appears verbatim
+phrase_bonus = {phrase_bonus:?}
+# Weight of query matches in tags and AST node type; 0.0 disables it
+field_boost = {field_boost:?}
+# Also match character ngrams of words ("prog" matches "programming"); reindex after
+# changing it
+ngram_enabled = false
+# Characters per ngram
+ngram_size = {ngram_size}
+# Weight of ngram matches relative to whole-word matches
+ngram_weight = {ngram_weight:?}
+# Matches counted per memory in regex search mode
+regex_max_matches = {regex_max_matches}
+# Regex searches scanning longer than this many milliseconds are aborted
+regex_timeout_ms = {regex_timeout_ms}
+# Mark query terms in search_memory results as prefix + term + suffix
+highlight_matches = {highlight_matches}
+highlight_prefix = {highlight_marker}
+highlight_suffix = {highlight_marker}
+
+[chunking]
+# Largest chunk, in bytes, that files are split into
+max_chunk_size = {max_chunk_size}
+# Bytes of the previous chunk repeated at the start of the next one
+chunk_overlap = {chunk_overlap}
+
+[storage]
+# Database holding global memories
+global_db_path = {global_db_path}
+# Project database location, relative to the project root
+project_db_name = {project_db_name}
+# Session memories kept in RAM before the oldest are evicted
+max_session_memories = {max_session_memories}
+# Most memories one project may hold. Default: unset (unlimited)
+# max_project_memories = 10000
+# Most memories the global scope may hold. Default: unset (unlimited)
+# global_memory_quota = 50000
+# Files larger than this many bytes are rejected by ingest_file
+max_ingest_file_size_bytes = {max_ingest_file_size_bytes}
+# Memories (and file chunks) larger than this many bytes are rejected
+max_content_length = {max_content_length}
+# Fill metadata.language when it is not given
+auto_detect_language = {auto_detect_language}
+# Content-hash -> embedding entries kept (LRU) to skip re-embedding identical content
+embedding_cache_max_entries = {embedding_cache_max_entries}
+# JSON Schema file that metadata.custom must satisfy. Default: unset (no validation)
+# custom_metadata_schema = "~/.config/rag-mcp/metadata.schema.json"
+# Break stats down by tag, language, source file and AST node type (scans every memory)
+extended_stats_enabled = false
+
+# Embedding provider for semantic search. Default: unset (BM25-only search)
+# [embedder.provider.openai]
+# api_key_env = "OPENAI_API_KEY"
+# model = "text-embedding-3-small"
+# OpenAI-compatible server such as LM Studio or vLLM. Default: api.openai.com
+# base_url = "http://localhost:1234"
+#
+# or a local Ollama server (default base_url: http://localhost:11434):
+# [embedder.provider.ollama]
+# model = "nomic-embed-text"
+"#,
+            log_level = quote(&default_log_level()),
+            request_timeout_ms = default_request_timeout_ms(),
+            llm_api_key_env = quote(&default_llm_api_key_env()),
+            llm_model = quote(&default_llm_model()),
+            default_k = default_k(),
+            min_score = default_min_score(),
+            bm25_k1 = default_bm25_k1(),
+            bm25_b = default_bm25_b(),
+            use_importance_boost = default_use_importance_boost(),
+            importance_boost_factor = default_importance_boost_factor(),
+            time_decay_half_life_days = default_time_decay_half_life_days(),
+            stemming_language = quote(&default_stemming_language()),
+            phrase_bonus = default_phrase_bonus(),
+            field_boost = default_field_boost(),
+            ngram_size = default_ngram_size(),
+            ngram_weight = default_ngram_weight(),
+            regex_max_matches = default_regex_max_matches(),
+            regex_timeout_ms = default_regex_timeout_ms(),
+            highlight_matches = default_highlight_matches(),
+            highlight_marker = quote(&default_highlight_marker()),
+            max_chunk_size = default_max_chunk_size(),
+            chunk_overlap = default_chunk_overlap(),
+            global_db_path = quote(&global_db_path.to_string_lossy()),
+            project_db_name = quote(&default_project_db_name()),
+            max_session_memories = default_max_session_memories(),
+            max_ingest_file_size_bytes = default_max_ingest_file_size_bytes(),
+            max_content_length = default_max_content_length(),
+            auto_detect_language = default_auto_detect_language(),
+            embedding_cache_max_entries = default_embedding_cache_max_entries(),
+        )
+    }
+
     pub fn config_path() -> PathBuf {
         dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -1,11 +1,14 @@
-pub mod storage;
 pub mod config;
+pub mod storage;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Memory {
@@ -16,10 +19,31 @@ pub struct Memory {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub version: u32,
+    #[serde(default)]
+    pub access_count: u64,
+    #[serde(default)]
+    pub last_accessed_at: Option<DateTime<Utc>>,
+    /// Pinned memories survive `clear_session` and refuse plain deletes
+    #[serde(default)]
+    pub pinned: bool,
+    /// Vector from the configured embedder; empty when none was computed
+    #[serde(default)]
+    pub embedding: Vec<f32>,
 }
 
 impl Memory {
-    pub fn new(content: String, scope: MemoryScope, metadata: MemoryMetadata) -> Self {
+    /// An `importance_score` left at its default is replaced by
+    /// `MemoryMetadata::compute_importance` of the content and tags
+    pub fn new(content: String, scope: MemoryScope, mut metadata: MemoryMetadata) -> Self {
+        if metadata.importance_score == DEFAULT_IMPORTANCE {
+            metadata.recompute_importance(&content);
+        }
+        Self::with_metadata(content, scope, metadata)
+    }
+
+    /// Like `new`, but keeps `metadata` exactly as given, for memories that already
+    /// had a score (copies, imports)
+    pub fn with_metadata(content: String, scope: MemoryScope, metadata: MemoryMetadata) -> Self {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4().to_string(),
@@ -29,8 +53,53 @@ impl Memory {
             created_at: now,
             updated_at: now,
             version: 1,
+            access_count: 0,
+            last_accessed_at: None,
+            pinned: false,
+            embedding: Vec::new(),
         }
     }
+
+    /// Memory for one chunk of a larger document; `parent_id` identifies the document
+    pub fn new_chunk(
+        content: String,
+        scope: MemoryScope,
+        parent_id: String,
+        chunk_index: usize,
+        source_file: Option<PathBuf>,
+        language: Option<String>,
+        tags: Vec<String>,
+    ) -> Self {
+        let metadata = MemoryMetadata {
+            tags,
+            source_file,
+            language,
+            chunk_index: Some(chunk_index),
+            parent_id: Some(parent_id),
+            importance_score: DEFAULT_IMPORTANCE,
+            ..Default::default()
+        };
+        Self::new(content, scope, metadata)
+    }
+
+    pub fn is_chunk(&self) -> bool {
+        self.metadata.chunk_index.is_some()
+    }
+
+    /// `content_hash` of this memory's content
+    pub fn content_hash(&self) -> String {
+        content_hash(&self.content)
+    }
+
+    /// Add `tag` unless already present; returns whether the tags changed
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        self.metadata.add_tag(tag)
+    }
+
+    /// Remove `tag`; returns whether it was present
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        self.metadata.remove_tag(tag)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +112,59 @@ pub struct MemoryMetadata {
     pub ast_node_type: Option<String>,
     pub importance_score: f32,
     pub custom: HashMap<String, serde_json::Value>,
+    /// Leading bytes of a chunk's content repeated from the previous chunk as overlap
+    #[serde(default)]
+    pub context_prefix_bytes: usize,
+}
+
+/// `importance_score` of `MemoryMetadata::default()`
+pub const DEFAULT_IMPORTANCE: f32 = 1.0;
+
+/// Tags that each add 0.5 to `MemoryMetadata::compute_importance`
+const IMPORTANT_TAGS: [&str; 5] = ["important", "critical", "decision", "architecture", "api"];
+
+impl MemoryMetadata {
+    /// Heuristic importance: `DEFAULT_IMPORTANCE`, plus 0.5 per tag from `IMPORTANT_TAGS`
+    /// (case-insensitive), plus 0.3 for content over 500 characters, minus 0.2 for
+    /// content starting with TODO or FIXME; clamped to 0.1..=10.0
+    pub fn compute_importance(content: &str, tags: &[String]) -> f32 {
+        let mut score = DEFAULT_IMPORTANCE;
+        for tag in IMPORTANT_TAGS {
+            if tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                score += 0.5;
+            }
+        }
+        if content.chars().count() > 500 {
+            score += 0.3;
+        }
+        let start = content.trim_start();
+        if start.starts_with("TODO") || start.starts_with("FIXME") {
+            score -= 0.2;
+        }
+        score.clamp(0.1, 10.0)
+    }
+
+    /// Reset `importance_score` to `compute_importance` of `content` and the current
+    /// tags, e.g. after either changed
+    pub fn recompute_importance(&mut self, content: &str) {
+        self.importance_score = Self::compute_importance(content, &self.tags);
+    }
+
+    /// Add `tag` unless already present; returns whether the tags changed
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        if self.tags.iter().any(|t| t == tag) {
+            return false;
+        }
+        self.tags.push(tag.to_string());
+        true
+    }
+
+    /// Remove `tag`; returns whether it was present
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|t| t != tag);
+        self.tags.len() != before
+    }
 }
 
 impl Default for MemoryMetadata {
@@ -54,27 +176,273 @@ impl Default for MemoryMetadata {
             chunk_index: None,
             parent_id: None,
             ast_node_type: None,
-            importance_score: 1.0,
+            importance_score: DEFAULT_IMPORTANCE,
             custom: HashMap::new(),
+            context_prefix_bytes: 0,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Name under which the unnamed session (`Session { name: None }`) is kept
+pub const DEFAULT_SESSION: &str = "default";
+
+/// Serialized forms are fixed (see STORAGE_FORMAT.md): `{"session":{"name":null}}`,
+/// `{"project":{"path":"/abs/path"}}` and `"global"`, independent of the Rust names.
+/// The capitalized names written before the format was pinned are still read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MemoryScope {
-    Session,
-    Project { path: PathBuf },
+    /// In-memory memories of one named session; None is the default session
+    #[serde(rename = "session", alias = "Session")]
+    Session {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    #[serde(rename = "project", alias = "Project")]
+    Project {
+        #[serde(with = "path_serde")]
+        path: PathBuf,
+    },
+    #[serde(rename = "global", alias = "Global")]
     Global,
 }
 
+/// Project paths as UTF-8 strings on every platform. Serializing a path that is not
+/// valid UTF-8 fails instead of writing a lossy copy that would name another directory.
+mod path_serde {
+    use serde::{ser, Deserialize, Deserializer, Serializer};
+    use std::path::{Path, PathBuf};
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        let path = path.to_str().ok_or_else(|| {
+            ser::Error::custom(format!("project path {:?} is not valid UTF-8", path))
+        })?;
+        serializer.serialize_str(path)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        String::deserialize(deserializer).map(PathBuf::from)
+    }
+}
+
+impl MemoryScope {
+    /// The default (unnamed) session
+    pub fn session() -> Self {
+        MemoryScope::Session { name: None }
+    }
+
+    /// Session `name`; `DEFAULT_SESSION` is the unnamed one
+    pub fn named_session(name: impl Into<String>) -> Self {
+        MemoryScope::Session {
+            name: Some(name.into()),
+        }
+        .normalize()
+    }
+
+    /// Key of a session scope's memories (`DEFAULT_SESSION` when unnamed); None for
+    /// other scopes
+    pub fn session_name(&self) -> Option<&str> {
+        match self {
+            MemoryScope::Session { name } => Some(name.as_deref().unwrap_or(DEFAULT_SESSION)),
+            _ => None,
+        }
+    }
+
+    /// Scope kind without the project path: `session`, `project` or `global`
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            MemoryScope::Session { .. } => "session",
+            MemoryScope::Project { .. } => "project",
+            MemoryScope::Global => "global",
+        }
+    }
+
+    /// Resolve a project path to one spelling, so `/p`, `/p/` and `/q/../p` share a
+    /// database: canonical when the path exists, otherwise cleaned lexically
+    /// (`.` dropped, `..` applied). A session named `DEFAULT_SESSION` or `""` becomes
+    /// the unnamed session. Global scope is returned unchanged.
+    pub fn normalize(self) -> Self {
+        match self {
+            MemoryScope::Project { path } => MemoryScope::Project {
+                path: normalize_project_path(&path),
+            },
+            MemoryScope::Session { name } => MemoryScope::Session {
+                name: name.filter(|name| !name.is_empty() && name != DEFAULT_SESSION),
+            },
+            other => other,
+        }
+    }
+
+    /// Prefix of this scope's memory resource URIs, `memory://{scope}`; the memory id
+    /// follows after one more `/`. Project paths and session names are embedded verbatim.
+    pub fn uri_prefix(&self) -> String {
+        match self {
+            MemoryScope::Session { name: None } => "memory://session".to_string(),
+            MemoryScope::Session { name: Some(name) } => format!("memory://session/{}", name),
+            MemoryScope::Global => "memory://global".to_string(),
+            MemoryScope::Project { path } => format!("memory://project/{}", path.display()),
+        }
+    }
+
+    /// Inverse of `uri_prefix`
+    pub fn from_uri_prefix(prefix: &str) -> Option<MemoryScope> {
+        match prefix.strip_prefix("memory://")? {
+            "session" => Some(MemoryScope::session()),
+            "global" => Some(MemoryScope::Global),
+            rest => {
+                if let Some(name) = rest.strip_prefix("session/") {
+                    return (!name.is_empty()).then(|| MemoryScope::named_session(name));
+                }
+                let path = rest.strip_prefix("project/")?;
+                (!path.is_empty()).then(|| MemoryScope::Project {
+                    path: PathBuf::from(path),
+                })
+            }
+        }
+    }
+}
+
+/// Hex SHA-256 of `content`: identical content has the same hash in every scope
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Project path as `MemoryScope::normalize` stores it: canonical when the path exists,
+/// otherwise lexically cleaned
+pub fn normalize_project_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| clean_path(path))
+}
+
+/// Lexical normalization for paths that cannot be canonicalized (no filesystem access)
+fn clean_path(path: &Path) -> PathBuf {
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match cleaned.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    cleaned.pop();
+                }
+                // `/..` is `/`
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => cleaned.push(".."),
+            },
+            other => cleaned.push(other),
+        }
+    }
+    cleaned
+}
+
+/// `session`, `session:<name>`, `global` or `project:<path>`; parsed back by `FromStr`
+impl fmt::Display for MemoryScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryScope::Session { name: Some(name) } => write!(f, "session:{}", name),
+            MemoryScope::Project { path } => write!(f, "project:{}", path.display()),
+            _ => f.write_str(self.display_name()),
+        }
+    }
+}
+
+impl FromStr for MemoryScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "session" => Ok(MemoryScope::session()),
+            "global" => Ok(MemoryScope::Global),
+            "project" | "project:" => anyhow::bail!("Project scope needs a path: project:<path>"),
+            "session:" => anyhow::bail!("Named session scope needs a name: session:<name>"),
+            _ => {
+                if let Some(name) = s.strip_prefix("session:") {
+                    return Ok(MemoryScope::Session {
+                        name: Some(name.to_string()),
+                    });
+                }
+                match s.strip_prefix("project:") {
+                    Some(path) => Ok(MemoryScope::Project {
+                        path: PathBuf::from(path),
+                    }),
+                    None => anyhow::bail!(
+                        "Invalid scope: {}. Use session, session:<name>, global, or project:<path>",
+                        s
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl From<ScopeKey> for MemoryScope {
+    fn from(key: ScopeKey) -> Self {
+        match key {
+            ScopeKey::Session(name) => MemoryScope::Session { name },
+            ScopeKey::Project(path) => MemoryScope::Project { path },
+            ScopeKey::Global => MemoryScope::Global,
+        }
+    }
+}
+
+/// Hashable stand-in for `MemoryScope`, for maps keyed by scope (e.g. per-scope indices)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ScopeKey {
+    Session(Option<String>),
+    Project(PathBuf),
+    Global,
+}
+
+impl From<&MemoryScope> for ScopeKey {
+    fn from(scope: &MemoryScope) -> Self {
+        match scope {
+            MemoryScope::Session { name } => ScopeKey::Session(name.clone()),
+            MemoryScope::Project { path } => ScopeKey::Project(path.clone()),
+            MemoryScope::Global => ScopeKey::Global,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
     pub content: String,
     pub start_byte: usize,
     pub end_byte: usize,
+    /// Leading bytes of `content` repeated from the previous chunk as context;
+    /// `start_byte..end_byte` covers only the rest
+    #[serde(default)]
+    pub context_prefix_bytes: usize,
     pub ast_context: Option<AstContext>,
 }
 
+impl Chunk {
+    /// `Memory::new_chunk` for this chunk, also recording its AST node type when known
+    pub fn to_memory(
+        self,
+        scope: MemoryScope,
+        parent_id: &str,
+        chunk_index: usize,
+        source_file: Option<PathBuf>,
+        language: Option<String>,
+        tags: Vec<String>,
+    ) -> Memory {
+        let ast_node_type = self.ast_context.as_ref().map(|c| c.node_type.clone());
+        let context_prefix_bytes = self.context_prefix_bytes;
+        let mut memory = Memory::new_chunk(
+            self.content,
+            scope,
+            parent_id.to_string(),
+            chunk_index,
+            source_file,
+            language,
+            tags,
+        );
+        memory.metadata.ast_node_type = ast_node_type;
+        memory.metadata.context_prefix_bytes = context_prefix_bytes;
+        memory
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AstContext {
     pub node_type: String,
@@ -1,45 +1,292 @@
-use crate::{Memory, MemoryScope};
+use crate::{content_hash, Memory, MemoryMetadata, MemoryScope, SearchResult, DEFAULT_SESSION};
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection, OptionalExtension};
-use std::collections::HashMap;
+use futures::stream::{self, Stream};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rusqlite::{
+    params, Connection, DatabaseName, OpenFlags, OptionalExtension, TransactionBehavior,
+};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
-use tracing::{debug, info};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemoryStoreError {
+    #[error("Memory {0} is pinned; unpin it or force the deletion")]
+    MemoryPinned(String),
+    #[error("Memory {0} not found")]
+    MemoryNotFound(String),
+    #[error("Custom metadata does not match the schema: {0}")]
+    ValidationError(String),
+    #[error("Memory store is read-only")]
+    ReadOnly,
+    #[error("Content is too large: {actual} bytes (limit {limit})")]
+    ContentTooLarge { actual: usize, limit: usize },
+    #[error("{scope} is full: {current} memories (quota {limit})")]
+    QuotaExceeded {
+        scope: MemoryScope,
+        limit: usize,
+        current: usize,
+    },
+}
+
+/// Change reported by `MemoryStore::watch_scope`
+#[derive(Debug, Clone)]
+pub enum MemoryEvent {
+    /// Memory written, new or replacing an earlier version
+    Stored(Box<Memory>),
+    /// Id of a deleted memory
+    Deleted(String),
+}
+
+/// How `MemoryStore::find_by_tags` combines the requested tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagMatchMode {
+    /// Every requested tag must be present
+    All,
+    /// At least one requested tag must be present
+    Any,
+}
+
+/// Which memory of a duplicate group `MemoryStore::dedup` keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupKeepPolicy {
+    /// The first stored copy, as `get_by_content_hash` returns
+    #[default]
+    KeepOldest,
+    KeepNewest,
+    /// Highest `importance_score`; ties go to the oldest
+    KeepHighestImportance,
+}
+
+impl FromStr for DedupKeepPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "oldest" => Ok(DedupKeepPolicy::KeepOldest),
+            "newest" => Ok(DedupKeepPolicy::KeepNewest),
+            "highest_importance" => Ok(DedupKeepPolicy::KeepHighestImportance),
+            _ => anyhow::bail!(
+                "Invalid keep policy: {}. Use oldest, newest, or highest_importance",
+                s
+            ),
+        }
+    }
+}
+
+/// Ordering for `MemoryStore::list_sorted`, newest / highest first; ties are broken
+/// by ascending id so repeated calls return the same order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemorySort {
+    CreatedDesc,
+    AccessCountDesc,
+    LastAccessedDesc,
+}
+
+impl MemorySort {
+    fn order_by(self) -> &'static str {
+        match self {
+            MemorySort::CreatedDesc => "created_at DESC, id ASC",
+            MemorySort::AccessCountDesc => "access_count DESC, id ASC",
+            // NULLs (never accessed) sort last in SQLite's DESC order
+            MemorySort::LastAccessedDesc => "last_accessed_at DESC, id ASC",
+        }
+    }
+
+    /// Same order as `order_by`, for session memories
+    fn compare(self, a: &Memory, b: &Memory) -> std::cmp::Ordering {
+        let primary = match self {
+            MemorySort::CreatedDesc => b.created_at.cmp(&a.created_at),
+            MemorySort::AccessCountDesc => b.access_count.cmp(&a.access_count),
+            MemorySort::LastAccessedDesc => b.last_accessed_at.cmp(&a.last_accessed_at),
+        };
+        primary.then_with(|| a.id.cmp(&b.id))
+    }
+}
+
+/// Current on-disk layout, stored in each database's `PRAGMA user_version`
+pub const STORAGE_VERSION: u32 = 6;
+
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Schema steps as (version reached, migration); run in order by `migrate_schema`
+const MIGRATIONS: &[(u32, Migration)] = &[
+    (2, migrate_v1_to_v2),
+    (3, migrate_v2_to_v3),
+    (4, migrate_v3_to_v4),
+    (5, migrate_v4_to_v5),
+    (6, migrate_v5_to_v6),
+];
+
+/// Version 2: access tracking and pinning columns
+fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('memories')")?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+
+    for (column, definition) in [
+        ("access_count", "INTEGER NOT NULL DEFAULT 0"),
+        ("last_accessed_at", "INTEGER"),
+        ("pinned", "INTEGER NOT NULL DEFAULT 0"),
+    ] {
+        if !columns.contains(column) {
+            conn.execute(
+                &format!("ALTER TABLE memories ADD COLUMN {} {}", column, definition),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Version 3: embedding vector (little-endian f32 blob, NULL when absent)
+fn migrate_v2_to_v3(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('memories')")?;
+    let has_embedding = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?
+        .contains("embedding");
+
+    if !has_embedding {
+        conn.execute("ALTER TABLE memories ADD COLUMN embedding BLOB", [])?;
+    }
+    Ok(())
+}
+
+/// Version 4: `counts` table holding the number of memories, kept current by triggers
+fn migrate_v3_to_v4(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS counts (
+            name TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+        );
+        INSERT OR REPLACE INTO counts (name, value)
+            SELECT 'memories', COUNT(*) FROM memories;
+        CREATE TRIGGER IF NOT EXISTS memories_count_insert AFTER INSERT ON memories
+        BEGIN
+            UPDATE counts SET value = value + 1 WHERE name = 'memories';
+        END;
+        CREATE TRIGGER IF NOT EXISTS memories_count_delete AFTER DELETE ON memories
+        BEGIN
+            UPDATE counts SET value = value - 1 WHERE name = 'memories';
+        END;",
+    )?;
+    Ok(())
+}
+
+/// Version 5: `search_index` table holding serialized search indices by name
+fn migrate_v4_to_v5(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS search_index (
+            name TEXT PRIMARY KEY,
+            data TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version 6: indexed `content_hash` column for `get_by_content_hash`, filled in for
+/// existing rows
+fn migrate_v5_to_v6(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('memories')")?;
+    let has_hash = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?
+        .contains("content_hash");
+
+    if !has_hash {
+        conn.execute("ALTER TABLE memories ADD COLUMN content_hash TEXT", [])?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS memories_content_hash ON memories (content_hash)",
+        [],
+    )?;
+    MemoryStore::rehash_rows(conn)?;
+    Ok(())
+}
+
+/// `search_index` row written by `save_search_index`
+const SEARCH_INDEX_KEY: &str = "__bm25_index__";
+
+/// Column list matching `MemoryStore::row_to_memory`
+const SELECT_COLUMNS: &str =
+    "id, content, scope, metadata, created_at, updated_at, access_count, last_accessed_at, pinned, embedding";
 
 pub struct MemoryStore {
-    session: HashMap<String, Memory>,
+    /// Session name (`DEFAULT_SESSION` for the unnamed session) -> id -> memory
+    session: HashMap<String, HashMap<String, Memory>>,
     global_db: Option<Arc<Mutex<Connection>>>,
     project_dbs: HashMap<PathBuf, Arc<Mutex<Connection>>>,
     global_db_path: PathBuf,
+    regex_max_matches: usize,
+    regex_timeout: Duration,
+    max_content_length: usize,
+    /// Most memories a project database may hold; None: unlimited
+    max_project_memories: Option<usize>,
+    /// Most memories the global database may hold; None: unlimited
+    global_memory_quota: Option<usize>,
+    /// `stats` fills `MemoryStats::extended`
+    extended_stats: bool,
+    metadata_schema: Option<jsonschema::Validator>,
+    /// The schema document `metadata_schema` was compiled from
+    metadata_schema_source: Option<serde_json::Value>,
+    /// Databases are opened read-only and every write fails with `MemoryStoreError::ReadOnly`
+    read_only: bool,
+    /// Changes made through this store, for `watch_scope`
+    events: broadcast::Sender<(MemoryScope, MemoryEvent)>,
 }
 
+/// Default cap on regex matches counted per memory
+const DEFAULT_REGEX_MAX_MATCHES: usize = 1000;
+/// Default limit enforced by `check_content_length`
+const DEFAULT_MAX_CONTENT_LENGTH: usize = 64 * 1024;
+/// How long a connection waits for another process's lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default limit on how long `search_regex` may scan a scope
+const DEFAULT_REGEX_TIMEOUT: Duration = Duration::from_secs(5);
+/// Memories listed in `MemoryStats::top_important`
+const TOP_IMPORTANT: usize = 5;
+/// Rows `MemoryIter` reads per query
+const ITER_PAGE_SIZE: usize = 64;
+/// Events buffered per `watch_scope` stream before a slow reader starts missing them
+const WATCH_CHANNEL_CAPACITY: usize = 256;
+
 impl MemoryStore {
     pub fn new(global_db_path: PathBuf) -> Result<Self> {
-        let global_db = if global_db_path.exists()
+        Self::open(global_db_path, false)
+    }
+
+    /// Open existing databases without the ability to change them, e.g. to inspect a
+    /// store while a backup or migration runs. Databases must already be at
+    /// `STORAGE_VERSION`; missing databases are not created.
+    pub fn open_readonly(global_db_path: PathBuf) -> Result<ReadonlyMemoryStore> {
+        Ok(ReadonlyMemoryStore(Self::open(global_db_path, true)?))
+    }
+
+    fn open(global_db_path: PathBuf, read_only: bool) -> Result<Self> {
+        let global_db = if read_only {
+            if global_db_path.exists() {
+                let conn = Self::open_db_readonly(&global_db_path)?;
+                Some(Arc::new(Mutex::new(conn)))
+            } else {
+                None
+            }
+        } else if global_db_path.exists()
             || global_db_path.parent().map(|p| p.exists()).unwrap_or(false)
         {
             if let Some(parent) = global_db_path.parent() {
                 std::fs::create_dir_all(parent)?;
             }
 
-            let conn =
-                Connection::open(&global_db_path).context("Failed to open global database")?;
-
-            conn.pragma_update(None, "journal_mode", "WAL")?;
-            conn.pragma_update(None, "synchronous", "NORMAL")?;
-
-            conn.execute(
-                "CREATE TABLE IF NOT EXISTS memories (
-                    id TEXT PRIMARY KEY,
-                    content TEXT NOT NULL,
-                    scope TEXT NOT NULL,
-                    metadata TEXT NOT NULL,
-                    created_at INTEGER NOT NULL,
-                    updated_at INTEGER NOT NULL
-                )",
-                [],
-            )?;
-
+            let conn = Self::open_global_db(&global_db_path)?;
             Some(Arc::new(Mutex::new(conn)))
         } else {
             None
@@ -55,324 +302,2259 @@ impl MemoryStore {
             global_db,
             project_dbs: HashMap::new(),
             global_db_path,
+            regex_max_matches: DEFAULT_REGEX_MAX_MATCHES,
+            regex_timeout: DEFAULT_REGEX_TIMEOUT,
+            max_content_length: DEFAULT_MAX_CONTENT_LENGTH,
+            max_project_memories: None,
+            global_memory_quota: None,
+            extended_stats: false,
+            metadata_schema: None,
+            metadata_schema_source: None,
+            read_only,
+            events: broadcast::channel(WATCH_CHANNEL_CAPACITY).0,
+        })
+    }
+
+    /// Stream of memories stored into and deleted from `scope` through this store,
+    /// starting now. SQLite has no change notification across connections, so writes
+    /// by other processes and bulk `restore`s are not reported. A reader that falls
+    /// more than `WATCH_CHANNEL_CAPACITY` events behind skips the oldest ones.
+    pub fn watch_scope(
+        &self,
+        scope: &MemoryScope,
+    ) -> Result<impl Stream<Item = MemoryEvent> + Send + 'static> {
+        let receiver = self.events.subscribe();
+        Ok(stream::unfold(
+            (receiver, scope.clone()),
+            |(mut receiver, scope)| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok((event_scope, event)) if event_scope == scope => {
+                            return Some((event, (receiver, scope)));
+                        }
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Scope watcher lagged, skipped {} events", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Whether any `watch_scope` stream is listening; events are only built when one is
+    fn watched(&self) -> bool {
+        self.events.receiver_count() > 0
+    }
+
+    fn emit(&self, scope: &MemoryScope, event: MemoryEvent) {
+        let _ = self.events.send((scope.clone(), event));
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(MemoryStoreError::ReadOnly.into());
+        }
+        Ok(())
+    }
+
+    /// Cheap liveness probe: the global database is open and answers a trivial query
+    pub fn global_db_reachable(&self) -> bool {
+        self.global_db.as_ref().is_some_and(|db| {
+            db.lock()
+                .unwrap()
+                .query_row("SELECT 1 FROM memories LIMIT 1", [], |_| Ok(()))
+                .optional()
+                .is_ok()
         })
     }
 
+    /// Cap the matches `search_regex` counts per memory, bounding scans of huge contents
+    pub fn set_regex_max_matches(&mut self, max_matches: usize) {
+        self.regex_max_matches = max_matches.max(1);
+    }
+
+    /// Abort `search_regex` with an error once scanning takes longer than `timeout`
+    pub fn set_regex_timeout(&mut self, timeout: Duration) {
+        self.regex_timeout = timeout;
+    }
+
+    /// Validate `metadata.custom` of every stored memory against the JSON Schema in `path`
+    pub fn load_metadata_schema(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read metadata schema {}", path.display()))?;
+        let schema: serde_json::Value = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid JSON in metadata schema {}", path.display()))?;
+        jsonschema::draft7::meta::validate(&schema).map_err(|e| {
+            anyhow::anyhow!(
+                "Metadata schema {} is not valid draft-07: {}",
+                path.display(),
+                e
+            )
+        })?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| anyhow::anyhow!("Invalid metadata schema {}: {}", path.display(), e))?;
+
+        info!("Validating custom metadata against {:?}", path);
+        self.metadata_schema = Some(validator);
+        self.metadata_schema_source = Some(schema);
+        Ok(())
+    }
+
+    /// The schema loaded by `load_metadata_schema`, as written in the file
+    pub fn metadata_schema(&self) -> Option<&serde_json::Value> {
+        self.metadata_schema_source.as_ref()
+    }
+
+    /// Limit applied by `check_content_length`
+    pub fn set_max_content_length(&mut self, limit: usize) {
+        self.max_content_length = limit;
+    }
+
+    /// Quotas enforced by `store` and `batch_store`: the most memories each project
+    /// database and the global database may hold (None: unlimited)
+    pub fn set_quotas(&mut self, max_project_memories: Option<usize>, global: Option<usize>) {
+        self.max_project_memories = max_project_memories;
+        self.global_memory_quota = global;
+    }
+
+    /// Whether `stats` also computes the `extended_stats` breakdown, which reads every
+    /// memory of the scope
+    pub fn set_extended_stats(&mut self, enabled: bool) {
+        self.extended_stats = enabled;
+    }
+
+    /// Fail with `MemoryStoreError::QuotaExceeded` unless `scope` has room for one more
+    /// memory. Sessions have no quota.
+    pub fn check_quota(&mut self, scope: &MemoryScope) -> Result<()> {
+        self.ensure_room(scope, 1)
+    }
+
+    /// Whether `added` new memories fit into `scope`'s quota
+    fn ensure_room(&mut self, scope: &MemoryScope, added: usize) -> Result<()> {
+        let limit = match scope {
+            MemoryScope::Session { .. } => None,
+            MemoryScope::Global => self.global_memory_quota,
+            MemoryScope::Project { .. } => self.max_project_memories,
+        };
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+        let current = self.count(scope)?;
+        if current + added > limit {
+            return Err(MemoryStoreError::QuotaExceeded {
+                scope: scope.clone(),
+                limit,
+                current,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Reject new content over the configured length with
+    /// `MemoryStoreError::ContentTooLarge`. Checked where content enters the store
+    /// (storing, ingesting), not by `store` itself, so existing memories can always be
+    /// rewritten.
+    pub fn check_content_length(&self, content: &str) -> Result<()> {
+        if content.len() > self.max_content_length {
+            return Err(MemoryStoreError::ContentTooLarge {
+                actual: content.len(),
+                limit: self.max_content_length,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Check `metadata.custom` against the configured schema, if any
+    pub fn validate_metadata(&self, metadata: &MemoryMetadata) -> Result<()> {
+        let Some(validator) = &self.metadata_schema else {
+            return Ok(());
+        };
+
+        let custom = serde_json::to_value(&metadata.custom)?;
+        let errors: Vec<String> = validator
+            .iter_errors(&custom)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                if path.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{}: {}", path, e)
+                }
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(MemoryStoreError::ValidationError(errors.join("; ")).into())
+        }
+    }
+
     pub fn store(&mut self, memory: Memory) -> Result<()> {
-        debug!("Storing memory: id={}, scope={:?}", memory.id, memory.scope);
+        self.ensure_writable()?;
+        debug!("Storing memory: id={}, scope={}", memory.id, memory.scope);
+        self.validate_metadata(&memory.metadata)?;
+        // Rewriting a stored memory doesn't take more room
+        if !self.exists(&memory.id, &memory.scope)? {
+            self.ensure_room(&memory.scope, 1)?;
+        }
+        let event = self.watched().then(|| memory.clone());
 
         match &memory.scope {
-            MemoryScope::Session => {
-                self.session.insert(memory.id.clone(), memory);
+            MemoryScope::Session { .. } => {
+                self.session_entry(&memory.scope)
+                    .insert(memory.id.clone(), memory);
             }
             MemoryScope::Global => {
                 let db = self.get_or_create_global_db()?;
                 let conn = db.lock().unwrap();
-                let metadata_json = serde_json::to_string(&memory.metadata)?;
-
-                conn.execute(
-                    "INSERT OR REPLACE INTO memories (id, content, scope, metadata, created_at, updated_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                    params![
-                        memory.id,
-                        memory.content,
-                        "global",
-                        metadata_json,
-                        memory.created_at.timestamp(),
-                        memory.updated_at.timestamp(),
-                    ],
-                )?;
+                Self::insert_row(&conn, &memory)?;
             }
             MemoryScope::Project { path } => {
                 let db = self.get_or_create_project_db(path)?;
                 let conn = db.lock().unwrap();
-                let metadata_json = serde_json::to_string(&memory.metadata)?;
-                let path_str = path.to_string_lossy();
-
-                conn.execute(
-                    "INSERT OR REPLACE INTO memories (id, content, scope, metadata, created_at, updated_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                    params![
-                        memory.id,
-                        memory.content,
-                        path_str.as_ref(),
-                        metadata_json,
-                        memory.created_at.timestamp(),
-                        memory.updated_at.timestamp(),
-                    ],
-                )?;
+                Self::insert_row(&conn, &memory)?;
             }
         }
 
+        if let Some(memory) = event {
+            self.emit(&memory.scope.clone(), MemoryEvent::Stored(Box::new(memory)));
+        }
         Ok(())
     }
 
-    pub fn get(&self, id: &str, scope: &MemoryScope) -> Result<Option<Memory>> {
-        match scope {
-            MemoryScope::Session => Ok(self.session.get(id).cloned()),
-            MemoryScope::Global => {
-                if let Some(db) = &self.global_db {
-                    let conn = db.lock().unwrap();
-                    let mut stmt = conn.prepare(
-                        "SELECT id, content, scope, metadata, created_at, updated_at
-                         FROM memories WHERE id = ?1",
-                    )?;
+    /// Store many memories; each database receives all of its rows in one transaction.
+    /// Returns the number of memories stored.
+    pub fn batch_store(&mut self, memories: Vec<Memory>) -> Result<usize> {
+        self.ensure_writable()?;
+        for memory in &memories {
+            self.validate_metadata(&memory.metadata)?;
+        }
+        let count = memories.len();
+        let events = self.watched().then(|| memories.clone());
+        let mut by_db: HashMap<Option<PathBuf>, Vec<Memory>> = HashMap::new();
+        let mut sessions = Vec::new();
 
-                    let memory = stmt
-                        .query_row([id], |row| {
-                            Ok(Memory {
-                                id: row.get(0)?,
-                                content: row.get(1)?,
-                                scope: MemoryScope::Global,
-                                metadata: serde_json::from_str(&row.get::<_, String>(3)?)
-                                    .unwrap_or_default(),
-                                created_at: chrono::DateTime::from_timestamp(
-                                    row.get::<_, i64>(4)?,
-                                    0,
-                                )
-                                .unwrap(),
-                                updated_at: chrono::DateTime::from_timestamp(
-                                    row.get::<_, i64>(5)?,
-                                    0,
-                                )
-                                .unwrap(),
-                                version: 1,
-                            })
-                        })
-                        .optional()?;
+        for memory in memories {
+            match &memory.scope {
+                MemoryScope::Session { .. } => sessions.push(memory),
+                MemoryScope::Global => by_db.entry(None).or_default().push(memory),
+                MemoryScope::Project { path } => {
+                    by_db.entry(Some(path.clone())).or_default().push(memory)
+                }
+            }
+        }
 
-                    Ok(memory)
-                } else {
-                    Ok(None)
+        for (project_path, memories) in &by_db {
+            let scope = match project_path {
+                None => MemoryScope::Global,
+                Some(path) => MemoryScope::Project { path: path.clone() },
+            };
+            let mut added = 0;
+            for memory in memories {
+                if !self.exists(&memory.id, &scope)? {
+                    added += 1;
                 }
             }
-            MemoryScope::Project { path } => {
-                if let Some(db) = self.project_dbs.get(path) {
-                    let conn = db.lock().unwrap();
-                    let mut stmt = conn.prepare(
-                        "SELECT id, content, scope, metadata, created_at, updated_at
-                         FROM memories WHERE id = ?1",
-                    )?;
+            self.ensure_room(&scope, added)?;
+        }
 
-                    let memory = stmt
-                        .query_row([id], |row| {
-                            Ok(Memory {
-                                id: row.get(0)?,
-                                content: row.get(1)?,
-                                scope: MemoryScope::Project { path: path.clone() },
-                                metadata: serde_json::from_str(&row.get::<_, String>(3)?)
-                                    .unwrap_or_default(),
-                                created_at: chrono::DateTime::from_timestamp(
-                                    row.get::<_, i64>(4)?,
-                                    0,
-                                )
-                                .unwrap(),
-                                updated_at: chrono::DateTime::from_timestamp(
-                                    row.get::<_, i64>(5)?,
-                                    0,
-                                )
-                                .unwrap(),
-                                version: 1,
-                            })
-                        })
-                        .optional()?;
+        for memory in sessions {
+            self.session_entry(&memory.scope)
+                .insert(memory.id.clone(), memory);
+        }
 
-                    Ok(memory)
-                } else {
-                    Ok(None)
-                }
+        for (project_path, memories) in by_db {
+            let db = match &project_path {
+                None => self.get_or_create_global_db()?,
+                Some(path) => self.get_or_create_project_db(path)?,
+            };
+            let mut conn = db.lock().unwrap();
+            let tx = conn.transaction()?;
+            for memory in &memories {
+                Self::insert_row(&tx, memory)?;
             }
+            tx.commit()?;
+        }
+
+        for memory in events.into_iter().flatten() {
+            self.emit(&memory.scope.clone(), MemoryEvent::Stored(Box::new(memory)));
         }
+        debug!("Batch stored {} memories", count);
+        Ok(count)
     }
 
-    pub fn delete(&mut self, id: &str, scope: &MemoryScope) -> Result<bool> {
+    /// Start a `MemoryStoreTransaction`; nothing is applied until its `commit`
+    pub fn transaction() -> MemoryStoreTransaction {
+        MemoryStoreTransaction::new()
+    }
+
+    fn insert_row(conn: &Connection, memory: &Memory) -> Result<()> {
+        let metadata_json = serde_json::to_string(&memory.metadata)?;
+        let scope_str = match &memory.scope {
+            MemoryScope::Project { path } => path.to_string_lossy().into_owned(),
+            _ => "global".to_string(),
+        };
+
+        conn.execute(
+            "INSERT OR REPLACE INTO memories
+                (id, content, scope, metadata, created_at, updated_at,
+                 access_count, last_accessed_at, pinned, embedding, content_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                memory.id,
+                memory.content,
+                scope_str,
+                metadata_json,
+                memory.created_at.timestamp(),
+                memory.updated_at.timestamp(),
+                memory.access_count as i64,
+                memory.last_accessed_at.map(|t| t.timestamp()),
+                memory.pinned,
+                encode_embedding(&memory.embedding),
+                memory.content_hash(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Recompute `content_hash` for every row; returns the number of rows
+    fn rehash_rows(conn: &Connection) -> Result<usize> {
+        let mut stmt = conn.prepare("SELECT id, content FROM memories")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut update = conn.prepare("UPDATE memories SET content_hash = ?1 WHERE id = ?2")?;
+        for (id, content) in &rows {
+            update.execute(params![content_hash(content), id])?;
+        }
+        Ok(rows.len())
+    }
+
+    /// Read a memory, counting the read: increments `access_count` and sets
+    /// `last_accessed_at`. Use `get_without_tracking` for internal reads. Read-only
+    /// stores do not count reads.
+    pub fn get(&mut self, id: &str, scope: &MemoryScope) -> Result<Option<Memory>> {
+        if self.read_only {
+            if let MemoryScope::Project { path } = scope {
+                self.get_or_create_project_db(path)?;
+            }
+            return self.get_without_tracking(id, scope);
+        }
+        let now = chrono::Utc::now();
+
         match scope {
-            MemoryScope::Session => Ok(self.session.remove(id).is_some()),
-            MemoryScope::Global => {
-                if let Some(db) = &self.global_db {
-                    let conn = db.lock().unwrap();
-                    let affected = conn.execute("DELETE FROM memories WHERE id = ?1", [id])?;
-                    Ok(affected > 0)
-                } else {
-                    Ok(false)
+            MemoryScope::Session { .. } => {
+                if let Some(memory) = self.session_mut(scope).and_then(|m| m.get_mut(id)) {
+                    memory.access_count += 1;
+                    memory.last_accessed_at = Some(now);
                 }
             }
-            MemoryScope::Project { path } => {
-                if let Some(db) = self.project_dbs.get(path) {
+            _ => {
+                if let MemoryScope::Project { path } = scope {
+                    self.get_or_create_project_db(path)?;
+                }
+                if let Some(db) = self.loaded_db(scope) {
                     let conn = db.lock().unwrap();
-                    let affected = conn.execute("DELETE FROM memories WHERE id = ?1", [id])?;
-                    Ok(affected > 0)
-                } else {
-                    Ok(false)
+                    conn.execute(
+                        "UPDATE memories SET access_count = access_count + 1, last_accessed_at = ?2
+                         WHERE id = ?1",
+                        params![id, now.timestamp()],
+                    )?;
                 }
             }
         }
+
+        self.get_without_tracking(id, scope)
+    }
+
+    /// Read a memory without touching its access statistics
+    pub fn get_without_tracking(&self, id: &str, scope: &MemoryScope) -> Result<Option<Memory>> {
+        match scope {
+            MemoryScope::Session { .. } => {
+                Ok(self.session_map(scope).and_then(|m| m.get(id)).cloned())
+            }
+            _ => {
+                let Some(db) = self.loaded_db(scope) else {
+                    return Ok(None);
+                };
+                let conn = db.lock().unwrap();
+                let memory = conn
+                    .query_row(
+                        &format!("SELECT {} FROM memories WHERE id = ?1", SELECT_COLUMNS),
+                        [id],
+                        |row| Self::row_to_memory(row, scope),
+                    )
+                    .optional()?;
+                Ok(memory)
+            }
+        }
     }
 
-    pub fn list(
+    /// The memory in `scope` whose content has `hash` (see `rag_core::content_hash`),
+    /// without tracking the read. When several memories share the content, the oldest
+    /// is the canonical one.
+    pub fn get_by_content_hash(
         &mut self,
         scope: &MemoryScope,
-        limit: usize,
-        offset: usize,
-    ) -> Result<Vec<Memory>> {
-        let mut memories = Vec::new();
+        hash: &str,
+    ) -> Result<Option<Memory>> {
+        if let MemoryScope::Session { .. } = scope {
+            let memory = self
+                .session_map(scope)
+                .into_iter()
+                .flat_map(|memories| memories.values())
+                .filter(|memory| memory.content_hash() == hash)
+                .min_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)))
+                .cloned();
+            return Ok(memory);
+        }
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(None);
+        };
+        let conn = db.lock().unwrap();
+        let memory = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM memories WHERE content_hash = ?1
+                     ORDER BY created_at, rowid LIMIT 1",
+                    SELECT_COLUMNS
+                ),
+                [hash],
+                |row| Self::row_to_memory(row, scope),
+            )
+            .optional()?;
+        Ok(memory)
+    }
 
-        match scope {
-            MemoryScope::Session => {
-                let mut all_memories: Vec<Memory> = self.session.values().cloned().collect();
-                // Sort by created_at descending (newest first)
-                all_memories.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-                // Apply offset and limit
-                memories.extend(all_memories.into_iter().skip(offset).take(limit));
+    /// IDs of memories in `scope` that share their content with another, one group per
+    /// content hash, each ordered oldest first. Memories with unique content are left out.
+    pub fn find_duplicates(&mut self, scope: &MemoryScope) -> Result<Vec<Vec<String>>> {
+        if let MemoryScope::Session { .. } = scope {
+            let mut by_hash: HashMap<String, Vec<&Memory>> = HashMap::new();
+            for memory in self.session_memories(scope) {
+                by_hash
+                    .entry(memory.content_hash())
+                    .or_default()
+                    .push(memory);
             }
-            MemoryScope::Global => {
-                if let Some(db) = &self.global_db {
-                    let conn = db.lock().unwrap();
-                    let mut stmt = conn.prepare(
-                        "SELECT id, content, scope, metadata, created_at, updated_at
-                         FROM memories ORDER BY created_at DESC LIMIT ?1 OFFSET ?2",
-                    )?;
+            let mut groups: Vec<(String, Vec<&Memory>)> = by_hash
+                .into_iter()
+                .filter(|(_, group)| group.len() > 1)
+                .collect();
+            groups.sort_by(|a, b| a.0.cmp(&b.0));
+            return Ok(groups
+                .into_iter()
+                .map(|(_, mut group)| {
+                    group.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+                    group.into_iter().map(|memory| memory.id.clone()).collect()
+                })
+                .collect());
+        }
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(Vec::new());
+        };
+        let conn = db.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT content_hash, id FROM memories WHERE content_hash IN
+                 (SELECT content_hash FROM memories GROUP BY content_hash HAVING COUNT(*) > 1)
+             ORDER BY content_hash, created_at, rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
 
-                    let rows = stmt.query_map(params![limit, offset], |row| {
-                        Ok(Memory {
-                            id: row.get(0)?,
-                            content: row.get(1)?,
-                            scope: MemoryScope::Global,
-                            metadata: serde_json::from_str(&row.get::<_, String>(3)?)
-                                .unwrap_or_default(),
-                            created_at: chrono::DateTime::from_timestamp(row.get::<_, i64>(4)?, 0)
-                                .unwrap(),
-                            updated_at: chrono::DateTime::from_timestamp(row.get::<_, i64>(5)?, 0)
-                                .unwrap(),
-                            version: 1,
-                        })
-                    })?;
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        let mut last_hash = None;
+        for row in rows {
+            let (hash, id) = row?;
+            if last_hash.as_ref() != Some(&hash) {
+                groups.push(Vec::new());
+                last_hash = Some(hash);
+            }
+            groups.last_mut().unwrap().push(id);
+        }
+        Ok(groups)
+    }
 
-                    for row in rows {
-                        memories.push(row?);
-                    }
-                }
+    /// Delete all but one memory of every `find_duplicates` group in `scope`, picking
+    /// the survivor by `keep`. Pinned duplicates are kept as well.
+    pub fn dedup(
+        &mut self,
+        scope: &MemoryScope,
+        keep: DedupKeepPolicy,
+    ) -> Result<DeduplicationReport> {
+        self.ensure_writable()?;
+        let mut report = self.dedup_preview(scope, keep)?;
+        let planned = std::mem::take(&mut report.deleted);
+        report.memories_deleted = 0;
+        report.bytes_freed = 0;
+        for memory in planned {
+            if self.force_delete(&memory.id, scope)? {
+                report.memories_deleted += 1;
+                report.bytes_freed += memory.content.len() as u64;
+                report.deleted.push(memory);
             }
-            MemoryScope::Project { path } => {
-                // Ensure project DB is loaded
-                let db = self.get_or_create_project_db(path)?;
-                let conn = db.lock().unwrap();
-                let mut stmt = conn.prepare(
-                    "SELECT id, content, scope, metadata, created_at, updated_at
-                     FROM memories ORDER BY created_at DESC LIMIT ?1 OFFSET ?2",
-                )?;
+        }
+        info!(
+            "Deduplicated {:?}: {} groups, {} memories deleted, {} bytes freed",
+            scope, report.groups_found, report.memories_deleted, report.bytes_freed
+        );
+        Ok(report)
+    }
 
-                let rows = stmt.query_map(params![limit, offset], |row| {
-                    Ok(Memory {
-                        id: row.get(0)?,
-                        content: row.get(1)?,
-                        scope: MemoryScope::Project { path: path.clone() },
-                        metadata: serde_json::from_str(&row.get::<_, String>(3)?)
-                            .unwrap_or_default(),
-                        created_at: chrono::DateTime::from_timestamp(row.get::<_, i64>(4)?, 0)
-                            .unwrap(),
-                        updated_at: chrono::DateTime::from_timestamp(row.get::<_, i64>(5)?, 0)
-                            .unwrap(),
-                        version: 1,
-                    })
-                })?;
+    /// What `dedup` would delete, without deleting anything
+    pub fn dedup_preview(
+        &mut self,
+        scope: &MemoryScope,
+        keep: DedupKeepPolicy,
+    ) -> Result<DeduplicationReport> {
+        let groups = self.find_duplicates(scope)?;
+        let mut report = DeduplicationReport {
+            groups_found: groups.len(),
+            ..Default::default()
+        };
+        for group in groups {
+            let mut memories = Vec::with_capacity(group.len());
+            for id in &group {
+                memories.extend(self.get_without_tracking(id, scope)?);
+            }
+            // `memories` is oldest first; `max_by` keeps the last of equal maxima
+            let survivor = match keep {
+                DedupKeepPolicy::KeepOldest => memories.first(),
+                DedupKeepPolicy::KeepNewest => memories.last(),
+                DedupKeepPolicy::KeepHighestImportance => memories.iter().rev().max_by(|a, b| {
+                    a.metadata
+                        .importance_score
+                        .total_cmp(&b.metadata.importance_score)
+                }),
+            }
+            .map(|memory| memory.id.clone());
 
-                for row in rows {
-                    memories.push(row?);
+            for memory in memories {
+                if memory.pinned || Some(&memory.id) == survivor.as_ref() {
+                    continue;
                 }
+                report.memories_deleted += 1;
+                report.bytes_freed += memory.content.len() as u64;
+                report.deleted.push(memory);
             }
         }
+        Ok(report)
+    }
 
-        Ok(memories)
+    /// Recompute the content hashes `get_by_content_hash` looks up in `scope`, e.g.
+    /// after the database was edited outside this store. Returns the number of
+    /// memories hashed; sessions are hashed on lookup and report 0.
+    pub fn rebuild_hash_index(&mut self, scope: &MemoryScope) -> Result<usize> {
+        self.ensure_writable()?;
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(0);
+        }
+        let db = self.persistent_db(scope)?.clone();
+        let mut conn = db.lock().unwrap();
+        let tx = conn.transaction()?;
+        let count = Self::rehash_rows(&tx)?;
+        tx.commit()?;
+        Ok(count)
     }
 
-    pub fn list_all(&mut self, scope: &MemoryScope) -> Result<Vec<Memory>> {
-        // SQLite can't handle usize::MAX, use i64::MAX instead (safe limit)
-        self.list(scope, i64::MAX as usize, 0)
+    /// Whether `id` is stored in `scope`, checked by primary key without reading or
+    /// decoding the record
+    pub fn exists(&mut self, id: &str, scope: &MemoryScope) -> Result<bool> {
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(self.session_map(scope).is_some_and(|m| m.contains_key(id)));
+        }
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(false);
+        };
+        let found = db
+            .lock()
+            .unwrap()
+            .query_row("SELECT 1 FROM memories WHERE id = ?1", [id], |_| Ok(()))
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    /// Database already open for a global or project scope; None for session scope
+    fn loaded_db(&self, scope: &MemoryScope) -> Option<&Arc<Mutex<Connection>>> {
+        match scope {
+            MemoryScope::Session { .. } => None,
+            MemoryScope::Global => self.global_db.as_ref(),
+            MemoryScope::Project { path } => self.project_dbs.get(path),
+        }
     }
 
-    pub fn clear_session(&mut self) {
-        info!("Clearing session memories");
-        self.session.clear();
+    fn row_to_memory(row: &rusqlite::Row, scope: &MemoryScope) -> rusqlite::Result<Memory> {
+        Ok(Memory {
+            id: row.get(0)?,
+            content: row.get(1)?,
+            scope: scope.clone(),
+            metadata: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+            created_at: chrono::DateTime::from_timestamp(row.get::<_, i64>(4)?, 0).unwrap(),
+            updated_at: chrono::DateTime::from_timestamp(row.get::<_, i64>(5)?, 0).unwrap(),
+            version: 1,
+            access_count: row.get::<_, i64>(6)? as u64,
+            last_accessed_at: row
+                .get::<_, Option<i64>>(7)?
+                .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0)),
+            pinned: row.get(8)?,
+            embedding: decode_embedding(row.get(9)?),
+        })
     }
 
-    pub fn stats(&mut self, scope: &MemoryScope) -> Result<MemoryStats> {
-        let count = match scope {
-            MemoryScope::Session => self.session.len(),
+    /// Delete a memory. Pinned memories are refused with `MemoryStoreError::MemoryPinned`;
+    /// use `force_delete` to remove them anyway.
+    pub fn delete(&mut self, id: &str, scope: &MemoryScope) -> Result<bool> {
+        if let Some(memory) = self.get_without_tracking(id, scope)? {
+            if memory.pinned {
+                return Err(MemoryStoreError::MemoryPinned(id.to_string()).into());
+            }
+        }
+        self.force_delete(id, scope)
+    }
+
+    /// Delete a memory whether or not it is pinned
+    pub fn force_delete(&mut self, id: &str, scope: &MemoryScope) -> Result<bool> {
+        self.ensure_writable()?;
+        let deleted = match scope {
+            MemoryScope::Session { .. } => self
+                .session_mut(scope)
+                .is_some_and(|memories| memories.remove(id).is_some()),
             MemoryScope::Global => {
                 if let Some(db) = &self.global_db {
                     let conn = db.lock().unwrap();
-                    let count: i64 =
-                        conn.query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))?;
-                    count as usize
+                    let affected = conn.execute("DELETE FROM memories WHERE id = ?1", [id])?;
+                    affected > 0
                 } else {
-                    0
+                    false
                 }
             }
             MemoryScope::Project { path } => {
-                // Ensure project DB is loaded
-                let db = self.get_or_create_project_db(path)?;
-                let conn = db.lock().unwrap();
-                let count: i64 =
+                if let Some(db) = self.project_dbs.get(path) {
+                    let conn = db.lock().unwrap();
+                    let affected = conn.execute("DELETE FROM memories WHERE id = ?1", [id])?;
+                    affected > 0
+                } else {
+                    false
+                }
+            }
+        };
+
+        if deleted {
+            self.emit(scope, MemoryEvent::Deleted(id.to_string()));
+        }
+        Ok(deleted)
+    }
+
+    /// Delete many memories of one scope in a single transaction, pinned or not.
+    /// IDs that do not exist are skipped; returns how many rows were actually deleted.
+    pub fn batch_delete(&mut self, ids: &[&str], scope: &MemoryScope) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut deleted = Vec::new();
+        if let MemoryScope::Session { .. } = scope {
+            if let Some(memories) = self.session_mut(scope) {
+                deleted.extend(
+                    ids.iter()
+                        .copied()
+                        .filter(|id| memories.remove(*id).is_some()),
+                );
+            }
+        } else {
+            if let MemoryScope::Project { path } = scope {
+                self.get_or_create_project_db(path)?;
+            }
+            let Some(db) = self.loaded_db(scope) else {
+                return Ok(0);
+            };
+
+            let mut conn = db.lock().unwrap();
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare("DELETE FROM memories WHERE id = ?1")?;
+                for id in ids {
+                    if stmt.execute([id])? > 0 {
+                        deleted.push(*id);
+                    }
+                }
+            }
+            tx.commit()?;
+        }
+
+        for id in &deleted {
+            self.emit(scope, MemoryEvent::Deleted(id.to_string()));
+        }
+        debug!("Batch deleted {} of {} memories", deleted.len(), ids.len());
+        Ok(deleted.len())
+    }
+
+    /// Move a memory to another scope: store the copy in `to`, then delete from `from`.
+    /// Best-effort, not transactional: if the delete fails after the store succeeded,
+    /// the memory exists in both scopes. Returns false when `id` is not found in `from`.
+    pub fn move_memory(&mut self, id: &str, from: &MemoryScope, to: &MemoryScope) -> Result<bool> {
+        self.ensure_writable()?;
+        if from == to {
+            return Ok(self.get_loaded(id, from)?.is_some());
+        }
+
+        let Some(mut memory) = self.get_loaded(id, from)? else {
+            return Ok(false);
+        };
+
+        memory.scope = to.clone();
+        memory.updated_at = chrono::Utc::now();
+        self.store(memory)?;
+        // The pin travels with the memory, so the source copy may go
+        self.force_delete(id, from)?;
+
+        info!("Moved memory {} from {:?} to {:?}", id, from, to);
+        Ok(true)
+    }
+
+    /// Duplicate a memory into `to` with a fresh id, `version = 1`, new timestamps and
+    /// `metadata.parent_id` pointing at the original. Returns the copy, or None when
+    /// `id` is not found in `from`.
+    pub fn copy_memory(
+        &mut self,
+        id: &str,
+        from: &MemoryScope,
+        to: &MemoryScope,
+    ) -> Result<Option<Memory>> {
+        self.ensure_writable()?;
+        let Some(original) = self.get_loaded(id, from)? else {
+            return Ok(None);
+        };
+
+        let metadata = MemoryMetadata {
+            parent_id: Some(original.id.clone()),
+            ..original.metadata
+        };
+        let copy = Memory::with_metadata(original.content, to.clone(), metadata);
+        self.store(copy.clone())?;
+
+        info!("Copied memory {} to {:?} as {}", id, to, copy.id);
+        Ok(Some(copy))
+    }
+
+    /// Combine `ids` (in order) into one new memory: contents joined by `separator`, tags
+    /// unioned with `extra_tags`, earliest `created_at`, latest `updated_at`, and
+    /// `metadata.parent_id` set to the first source. The sources are then deleted.
+    /// Fails without changes if a source is missing or pinned.
+    pub fn merge_memories(
+        &mut self,
+        ids: &[String],
+        scope: &MemoryScope,
+        separator: &str,
+        extra_tags: &[String],
+    ) -> Result<Memory> {
+        self.ensure_writable()?;
+        let mut sources = Vec::with_capacity(ids.len());
+        for id in ids {
+            let memory = self
+                .get_loaded(id, scope)?
+                .ok_or_else(|| MemoryStoreError::MemoryNotFound(id.clone()))?;
+            if memory.pinned {
+                return Err(MemoryStoreError::MemoryPinned(id.clone()).into());
+            }
+            sources.push(memory);
+        }
+        anyhow::ensure!(!sources.is_empty(), "Nothing to merge");
+
+        let mut tags: Vec<String> = Vec::new();
+        for tag in sources
+            .iter()
+            .flat_map(|m| m.metadata.tags.iter())
+            .chain(extra_tags)
+        {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        let content = sources
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join(separator);
+        let metadata = MemoryMetadata {
+            tags,
+            parent_id: Some(sources[0].id.clone()),
+            ..Default::default()
+        };
+
+        let mut merged = Memory::new(content, scope.clone(), metadata);
+        merged.created_at = sources.iter().map(|m| m.created_at).min().unwrap();
+        merged.updated_at = sources.iter().map(|m| m.updated_at).max().unwrap();
+        self.store(merged.clone())?;
+
+        for source in &sources {
+            self.force_delete(&source.id, scope)?;
+        }
+
+        info!("Merged {} memories into {}", sources.len(), merged.id);
+        Ok(merged)
+    }
+
+    /// Split a memory at byte offsets (strictly increasing, inside the content, on char
+    /// boundaries) into `split_at.len() + 1` memories that keep the original's tags,
+    /// language and source file, with `parent_id` = original id and sequential
+    /// `chunk_index`. The original is deleted. Returns the new memories in order.
+    pub fn split_memory(
+        &mut self,
+        id: &str,
+        scope: &MemoryScope,
+        split_at: &[usize],
+    ) -> Result<Vec<Memory>> {
+        self.ensure_writable()?;
+        let original = self
+            .get_loaded(id, scope)?
+            .ok_or_else(|| MemoryStoreError::MemoryNotFound(id.to_string()))?;
+        if original.pinned {
+            return Err(MemoryStoreError::MemoryPinned(id.to_string()).into());
+        }
+
+        let content = &original.content;
+        let mut prev = 0;
+        for &offset in split_at {
+            anyhow::ensure!(
+                offset > prev && offset < content.len(),
+                "Split offset {} out of bounds or not increasing (content is {} bytes)",
+                offset,
+                content.len()
+            );
+            anyhow::ensure!(
+                content.is_char_boundary(offset),
+                "Split offset {} is not on a UTF-8 character boundary",
+                offset
+            );
+            prev = offset;
+        }
+
+        let bounds: Vec<usize> = std::iter::once(0)
+            .chain(split_at.iter().copied())
+            .chain(std::iter::once(content.len()))
+            .collect();
+
+        let parts: Vec<Memory> = bounds
+            .windows(2)
+            .enumerate()
+            .map(|(index, window)| {
+                Memory::new_chunk(
+                    content[window[0]..window[1]].to_string(),
+                    scope.clone(),
+                    original.id.clone(),
+                    index,
+                    original.metadata.source_file.clone(),
+                    original.metadata.language.clone(),
+                    original.metadata.tags.clone(),
+                )
+            })
+            .collect();
+
+        self.batch_store(parts.clone())?;
+        self.force_delete(id, scope)?;
+
+        info!("Split memory {} into {} parts", id, parts.len());
+        Ok(parts)
+    }
+
+    /// `get` that first opens the scope's database, so project scopes not yet
+    /// touched in this process are readable
+    fn get_loaded(&mut self, id: &str, scope: &MemoryScope) -> Result<Option<Memory>> {
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        self.get_without_tracking(id, scope)
+    }
+
+    pub fn list(
+        &mut self,
+        scope: &MemoryScope,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Memory>> {
+        self.list_sorted(scope, MemorySort::CreatedDesc, limit, offset)
+    }
+
+    pub fn list_sorted(
+        &mut self,
+        scope: &MemoryScope,
+        sort: MemorySort,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Memory>> {
+        let mut memories = Vec::new();
+
+        match scope {
+            MemoryScope::Session { .. } => {
+                let mut all_memories: Vec<Memory> = self.session_memories(scope).cloned().collect();
+                all_memories.sort_by(|a, b| sort.compare(a, b));
+                // Apply offset and limit
+                memories.extend(all_memories.into_iter().skip(offset).take(limit));
+            }
+            _ => {
+                // Ensure project DB is loaded
+                if let MemoryScope::Project { path } = scope {
+                    self.get_or_create_project_db(path)?;
+                }
+                let Some(db) = self.loaded_db(scope) else {
+                    return Ok(memories);
+                };
+
+                let conn = db.lock().unwrap();
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {} FROM memories ORDER BY {} LIMIT ?1 OFFSET ?2",
+                    SELECT_COLUMNS,
+                    sort.order_by()
+                ))?;
+
+                let rows = stmt.query_map(params![limit, offset], |row| {
+                    Self::row_to_memory(row, scope)
+                })?;
+
+                for row in rows {
+                    memories.push(row?);
+                }
+            }
+        }
+
+        Ok(memories)
+    }
+
+    /// `list_sorted` page together with the number of memories in the whole scope, for
+    /// pagination controls. Database scopes take the total from the `counts` table
+    /// instead of scanning the records a second time.
+    pub fn list_with_total(
+        &mut self,
+        scope: &MemoryScope,
+        limit: usize,
+        offset: usize,
+        sort: MemorySort,
+    ) -> Result<(Vec<Memory>, usize)> {
+        if let MemoryScope::Session { .. } = scope {
+            let mut all_memories: Vec<Memory> = self.session_memories(scope).cloned().collect();
+            all_memories.sort_by(|a, b| sort.compare(a, b));
+            let total = all_memories.len();
+            let page = all_memories.into_iter().skip(offset).take(limit).collect();
+            return Ok((page, total));
+        }
+
+        let page = self.list_sorted(scope, sort, limit, offset)?;
+        Ok((page, self.count(scope)?))
+    }
+
+    /// Keyset pagination: up to `limit` memories ordered by `created_at DESC, id ASC`,
+    /// starting right after `after_id` (from the newest when None). Cost does not grow
+    /// with the page number, unlike `offset`. Errors when `after_id` is not in `scope`.
+    pub fn list_after_cursor(
+        &mut self,
+        scope: &MemoryScope,
+        after_id: Option<&str>,
+        limit: usize,
+    ) -> Result<MemoryPage> {
+        // One extra row tells whether another page follows
+        let fetch = limit.saturating_add(1);
+        let mut memories = Vec::new();
+
+        match scope {
+            MemoryScope::Session { .. } => {
+                let mut all_memories: Vec<&Memory> = self.session_memories(scope).collect();
+                all_memories.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id)));
+                let start = match after_id {
+                    Some(id) => {
+                        all_memories
+                            .iter()
+                            .position(|m| m.id == id)
+                            .with_context(|| format!("Unknown cursor: {}", id))?
+                            + 1
+                    }
+                    None => 0,
+                };
+                memories.extend(all_memories.into_iter().skip(start).take(fetch).cloned());
+            }
+            _ => {
+                if let MemoryScope::Project { path } = scope {
+                    self.get_or_create_project_db(path)?;
+                }
+                if let Some(db) = self.loaded_db(scope) {
+                    let conn = db.lock().unwrap();
+                    let cursor = match after_id {
+                        Some(id) => Some(
+                            conn.query_row(
+                                "SELECT created_at FROM memories WHERE id = ?1",
+                                [id],
+                                |row| row.get::<_, i64>(0),
+                            )
+                            .optional()?
+                            .map(|created_at| (created_at, id))
+                            .with_context(|| format!("Unknown cursor: {}", id))?,
+                        ),
+                        None => None,
+                    };
+
+                    let mut stmt = conn.prepare(&format!(
+                        "SELECT {} FROM memories
+                         WHERE ?1 IS NULL OR created_at < ?1 OR (created_at = ?1 AND id > ?2)
+                         ORDER BY created_at DESC, id ASC LIMIT ?3",
+                        SELECT_COLUMNS
+                    ))?;
+                    let (created_at, id) = cursor.unzip();
+                    let rows = stmt.query_map(params![created_at, id, fetch], |row| {
+                        Self::row_to_memory(row, scope)
+                    })?;
+                    for row in rows {
+                        memories.push(row?);
+                    }
+                }
+            }
+        }
+
+        let has_more = memories.len() > limit;
+        memories.truncate(limit);
+        let next_cursor = if has_more {
+            memories.last().map(|m| m.id.clone())
+        } else {
+            None
+        };
+
+        Ok(MemoryPage {
+            memories,
+            next_cursor,
+            has_more,
+        })
+    }
+
+    /// The `k` memories read most often through `get`
+    pub fn get_most_accessed(&mut self, scope: &MemoryScope, k: usize) -> Result<Vec<Memory>> {
+        self.list_sorted(scope, MemorySort::AccessCountDesc, k, 0)
+    }
+
+    pub fn list_all(&mut self, scope: &MemoryScope) -> Result<Vec<Memory>> {
+        // SQLite can't handle usize::MAX, use i64::MAX instead (safe limit)
+        self.list(scope, i64::MAX as usize, 0)
+    }
+
+    /// Every memory in `scope`, read lazily a small page at a time instead of
+    /// materializing the whole scope like `list_all`. Rows come in insertion order.
+    /// A project database that was never opened yields nothing.
+    pub fn iter_all<'a>(
+        &'a self,
+        scope: &'a MemoryScope,
+    ) -> Result<Box<dyn Iterator<Item = Result<Memory>> + 'a>> {
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(Box::new(self.session_memories(scope).cloned().map(Ok)));
+        }
+
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(Box::new(std::iter::empty()));
+        };
+        Ok(Box::new(MemoryIter {
+            db,
+            scope,
+            last_rowid: 0,
+            page: std::collections::VecDeque::new(),
+            done: false,
+        }))
+    }
+
+    /// Up to `k` memories of `scope` picked uniformly at random, reproducibly when
+    /// `seed` is given. Shuffles every ID in the scope; `reservoir_sample` avoids
+    /// holding them all. Sampled reads are not counted as accesses.
+    pub fn get_random(
+        &mut self,
+        scope: &MemoryScope,
+        k: usize,
+        seed: Option<u64>,
+    ) -> Result<Vec<Memory>> {
+        let mut ids = self.all_ids(scope)?;
+        ids.shuffle(&mut sample_rng(seed));
+
+        let mut memories = Vec::with_capacity(k.min(ids.len()));
+        for id in ids.iter().take(k) {
+            memories.extend(self.get_without_tracking(id, scope)?);
+        }
+        Ok(memories)
+    }
+
+    /// Up to `k` memories of `scope` picked uniformly at random in one pass over
+    /// `iter_all`, keeping only `k` memories in memory. Reproducible for a given
+    /// `seed` while the scope is unchanged; not the same sample as `get_random`.
+    pub fn reservoir_sample(
+        &mut self,
+        scope: &MemoryScope,
+        k: usize,
+        seed: Option<u64>,
+    ) -> Result<Vec<Memory>> {
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+
+        let mut rng = sample_rng(seed);
+        let mut reservoir = Vec::with_capacity(k);
+        let memories: Box<dyn Iterator<Item = Result<Memory>>> =
+            if let MemoryScope::Session { .. } = scope {
+                // Session maps iterate in arbitrary order; sort so a seed means something
+                let mut session: Vec<Memory> = self.session_memories(scope).cloned().collect();
+                session.sort_by(|a, b| a.id.cmp(&b.id));
+                Box::new(session.into_iter().map(Ok))
+            } else {
+                self.iter_all(scope)?
+            };
+        for (seen, memory) in memories.enumerate() {
+            let memory = memory?;
+            if seen < k {
+                reservoir.push(memory);
+            } else {
+                let slot = rng.random_range(0..=seen);
+                if slot < k {
+                    reservoir[slot] = memory;
+                }
+            }
+        }
+        Ok(reservoir)
+    }
+
+    /// IDs in `scope`, in insertion order for databases and sorted for sessions
+    fn all_ids(&mut self, scope: &MemoryScope) -> Result<Vec<String>> {
+        if let MemoryScope::Session { .. } = scope {
+            let mut ids: Vec<String> = self
+                .session_memories(scope)
+                .map(|memory| memory.id.clone())
+                .collect();
+            ids.sort();
+            return Ok(ids);
+        }
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(Vec::new());
+        };
+        let conn = db.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id FROM memories ORDER BY rowid")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(ids)
+    }
+
+    /// Memories in `scope` whose tags match `tags` under `match_mode`, newest first
+    pub fn find_by_tags(
+        &mut self,
+        scope: &MemoryScope,
+        tags: &[String],
+        match_mode: TagMatchMode,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Memory>> {
+        let matches = |memory: &Memory| match match_mode {
+            TagMatchMode::All => tags.iter().all(|t| memory.metadata.tags.contains(t)),
+            TagMatchMode::Any => tags.iter().any(|t| memory.metadata.tags.contains(t)),
+        };
+
+        Ok(self
+            .list_all(scope)?
+            .into_iter()
+            .filter(matches)
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
+
+    /// Memories in `scope` whose content matches `pattern`, ranked by match count
+    /// (the `score`), newest first among equal counts. At most `k` results.
+    /// Fails when the scan runs past the regex timeout.
+    pub fn search_regex(
+        &mut self,
+        scope: &MemoryScope,
+        pattern: &str,
+        k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let regex = regex::Regex::new(pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid regex pattern {:?}: {}", pattern, e))?;
+
+        let deadline = Instant::now() + self.regex_timeout;
+        let mut matches: Vec<(Memory, usize)> = Vec::new();
+        for memory in self.list_all(scope)? {
+            anyhow::ensure!(
+                Instant::now() < deadline,
+                "Regex search timed out after {} ms",
+                self.regex_timeout.as_millis()
+            );
+            let count = regex
+                .find_iter(&memory.content)
+                .take(self.regex_max_matches)
+                .count();
+            if count > 0 {
+                matches.push((memory, count));
+            }
+        }
+
+        // Stable sort keeps list_all's newest-first order for ties
+        matches.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        Ok(matches
+            .into_iter()
+            .take(k)
+            .enumerate()
+            .map(|(rank, (memory, count))| SearchResult {
+                memory,
+                score: count as f32,
+                rank,
+            })
+            .collect())
+    }
+
+    /// Delete every memory in `scope`, returning how many were removed.
+    /// Session: drops that session's memories. Global: deletes all rows. Project: closes
+    /// the database and removes its files from disk.
+    pub fn delete_scope(&mut self, scope: &MemoryScope) -> Result<usize> {
+        self.ensure_writable()?;
+        let ids: Vec<String> = if self.watched() {
+            self.list_all(scope)?.into_iter().map(|m| m.id).collect()
+        } else {
+            Vec::new()
+        };
+        let deleted = match scope {
+            MemoryScope::Session { .. } => scope
+                .session_name()
+                .and_then(|name| self.session.remove(name))
+                .map_or(0, |memories| memories.len()),
+            MemoryScope::Global => {
+                let db = self.get_or_create_global_db()?;
+                let conn = db.lock().unwrap();
+                conn.execute("DELETE FROM search_index", [])?;
+                conn.execute("DELETE FROM memories", [])?
+            }
+            MemoryScope::Project { path } => {
+                let count = self.stats(scope)?.total_memories;
+                self.project_dbs.remove(path.as_path());
+
+                let db_path = Self::project_db_path(path);
+                for suffix in ["", "-wal", "-shm"] {
+                    let file = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+                    if file.exists() {
+                        std::fs::remove_file(&file)
+                            .with_context(|| format!("Failed to remove {:?}", file))?;
+                    }
+                }
+                count
+            }
+        };
+
+        for id in ids {
+            self.emit(scope, MemoryEvent::Deleted(id));
+        }
+        info!("Deleted scope {}: {} memories", scope, deleted);
+        Ok(deleted)
+    }
+
+    /// Memories of every known project at or below `path_prefix` (whole path
+    /// components), newest first. Projects whose database file is gone are skipped.
+    pub fn list_by_path_prefix(
+        &mut self,
+        path_prefix: &Path,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Memory>> {
+        let mut memories = Vec::new();
+        for path in self.list_known_projects()? {
+            if path.starts_with(path_prefix) && Self::project_db_exists(&path) {
+                memories.extend(self.list_all(&MemoryScope::Project { path })?);
+            }
+        }
+
+        memories.sort_by(|a, b| MemorySort::CreatedDesc.compare(a, b));
+        Ok(memories.into_iter().skip(offset).take(limit).collect())
+    }
+
+    /// Memories in `scope` whose `metadata.source_file` is exactly `path`, in chunk order
+    pub fn find_by_source_file(&mut self, scope: &MemoryScope, path: &Path) -> Result<Vec<Memory>> {
+        let mut memories = self.list_all(scope)?;
+        memories.retain(|memory| memory.metadata.source_file.as_deref() == Some(path));
+        memories.sort_by_key(|memory| memory.metadata.chunk_index);
+        Ok(memories)
+    }
+
+    /// Chunks of the document `parent_id` in `scope`, in chunk order
+    pub fn list_chunks(&mut self, scope: &MemoryScope, parent_id: &str) -> Result<Vec<Memory>> {
+        let mut memories = self.list_all(scope)?;
+        memories.retain(|memory| {
+            memory.is_chunk() && memory.metadata.parent_id.as_deref() == Some(parent_id)
+        });
+        memories.sort_by_key(|memory| memory.metadata.chunk_index);
+        Ok(memories)
+    }
+
+    /// The `metadata.parent_id` chain above `id`, from the root down to `id` itself.
+    /// The walk stops at a memory without a parent, or whose parent is not a memory in
+    /// `scope` (e.g. the document id shared by file chunks). Reads are not tracked.
+    pub fn get_ancestors(&mut self, id: &str, scope: &MemoryScope) -> Result<Vec<Memory>> {
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let memory = self
+            .get_without_tracking(id, scope)?
+            .ok_or_else(|| MemoryStoreError::MemoryNotFound(id.to_string()))?;
+
+        let mut seen = HashSet::from([memory.id.clone()]);
+        let mut chain = vec![memory];
+        while let Some(parent_id) = chain.last().and_then(|m| m.metadata.parent_id.clone()) {
+            // A cycle would otherwise loop forever
+            if !seen.insert(parent_id.clone()) {
+                break;
+            }
+            match self.get_without_tracking(&parent_id, scope)? {
+                Some(parent) => chain.push(parent),
+                None => break,
+            }
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+
+    /// Every memory below `id` through `metadata.parent_id` links: children first, then
+    /// grandchildren and so on, each generation in chunk order
+    pub fn get_descendants(&mut self, id: &str, scope: &MemoryScope) -> Result<Vec<Memory>> {
+        let mut children: HashMap<String, Vec<Memory>> = HashMap::new();
+        for memory in self.list_all(scope)? {
+            if let Some(parent_id) = &memory.metadata.parent_id {
+                children.entry(parent_id.clone()).or_default().push(memory);
+            }
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by(|a, b| {
+                (a.metadata.chunk_index, a.created_at, &a.id).cmp(&(
+                    b.metadata.chunk_index,
+                    b.created_at,
+                    &b.id,
+                ))
+            });
+        }
+
+        let mut seen = HashSet::from([id.to_string()]);
+        let mut descendants = Vec::new();
+        let mut generation = vec![id.to_string()];
+        while !generation.is_empty() {
+            let mut next = Vec::new();
+            for parent_id in &generation {
+                for child in children.remove(parent_id).unwrap_or_default() {
+                    if seen.insert(child.id.clone()) {
+                        next.push(child.id.clone());
+                        descendants.push(child);
+                    }
+                }
+            }
+            generation = next;
+        }
+        Ok(descendants)
+    }
+
+    /// Delete every memory ingested from `path` (`metadata.source_file`), pinned or not.
+    /// Returns the deleted ids.
+    pub fn delete_by_source_file(
+        &mut self,
+        scope: &MemoryScope,
+        path: &Path,
+    ) -> Result<Vec<String>> {
+        let ids: Vec<String> = self
+            .find_by_source_file(scope, path)?
+            .into_iter()
+            .map(|memory| memory.id)
+            .collect();
+
+        let refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+        self.batch_delete(&refs, scope)?;
+
+        Ok(ids)
+    }
+
+    /// Check that every row in `scope` decodes into a `Memory`: metadata must be valid
+    /// JSON for `MemoryMetadata` and timestamps must be in range. Session memories live
+    /// in memory and are always valid.
+    pub fn verify_integrity(&mut self, scope: &MemoryScope) -> Result<IntegrityReport> {
+        if let MemoryScope::Session { .. } = scope {
+            let total = self.session_memories(scope).count();
+            return Ok(IntegrityReport {
+                total,
+                corrupt: Vec::new(),
+                valid: total,
+            });
+        }
+
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(IntegrityReport::default());
+        };
+
+        let conn = db.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, metadata, created_at, updated_at FROM memories")?;
+        let mut rows = stmt.query([])?;
+
+        let mut report = IntegrityReport::default();
+        while let Some(row) = rows.next()? {
+            report.total += 1;
+            let id: String = row.get(0)?;
+
+            let metadata_ok = row
+                .get::<_, String>(1)
+                .ok()
+                .is_some_and(|json| serde_json::from_str::<MemoryMetadata>(&json).is_ok());
+            let timestamp_ok = |idx: usize| {
+                row.get::<_, i64>(idx)
+                    .ok()
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .is_some()
+            };
+
+            if metadata_ok && timestamp_ok(2) && timestamp_ok(3) {
+                report.valid += 1;
+            } else {
+                report.corrupt.push(id);
+            }
+        }
+
+        info!(
+            "Verified {:?}: {} total, {} corrupt",
+            scope,
+            report.total,
+            report.corrupt.len()
+        );
+        Ok(report)
+    }
+
+    /// Checkpoint the WAL of every open database into its main file, so a clean exit
+    /// leaves nothing behind in `-wal` files. A no-op for read-only stores.
+    pub fn flush(&self) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        for db in self.global_db.iter().chain(self.project_dbs.values()) {
+            db.lock()
+                .unwrap()
+                .execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        }
+        Ok(())
+    }
+
+    /// Rebuild a scope's database file with VACUUM to reclaim space left by deletes,
+    /// then checkpoint the WAL so the sizes reflect the file on disk. Session memories
+    /// live in memory and report zero sizes.
+    pub fn compact(&mut self, scope: &MemoryScope) -> Result<CompactionReport> {
+        self.ensure_writable()?;
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(CompactionReport {
+                records_copied: self.session_memories(scope).count(),
+                ..Default::default()
+            });
+        }
+
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(CompactionReport::default());
+        };
+
+        let conn = db.lock().unwrap();
+        let db_path = PathBuf::from(conn.path().context("Database has no file path")?);
+
+        let old_size_bytes = db_file_size(&db_path);
+        conn.execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")?;
+        let records_copied: i64 =
+            conn.query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))?;
+        let new_size_bytes = db_file_size(&db_path);
+
+        info!(
+            "Compacted {:?}: {} records, {} -> {} bytes",
+            scope, records_copied, old_size_bytes, new_size_bytes
+        );
+        Ok(CompactionReport {
+            records_copied: records_copied as usize,
+            old_size_bytes,
+            new_size_bytes,
+        })
+    }
+
+    /// Copy a scope's database to `dest_path` with SQLite's online backup, giving a
+    /// consistent snapshot even while other connections write. Session memories are
+    /// not persisted and cannot be backed up.
+    pub fn backup(&mut self, scope: &MemoryScope, dest_path: &Path) -> Result<BackupReport> {
+        if dest_path.exists() {
+            anyhow::bail!("Backup destination {:?} already exists", dest_path);
+        }
+        if let Some(parent) = dest_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = self.persistent_db(scope)?;
+        let conn = db.lock().unwrap();
+        conn.backup(DatabaseName::Main, dest_path, None)
+            .with_context(|| format!("Failed to back up to {:?}", dest_path))?;
+
+        let report = BackupReport {
+            files_copied: 1,
+            total_bytes: db_file_size(dest_path),
+            timestamp: chrono::Utc::now(),
+        };
+        info!(
+            "Backed up {} to {:?} ({} bytes)",
+            scope, dest_path, report.total_bytes
+        );
+        Ok(report)
+    }
+
+    /// Replace a scope's database contents with a file written by `backup`, upgrading
+    /// it to the current storage version. Returns the number of memories restored.
+    pub fn restore(&mut self, backup_path: &Path, scope: &MemoryScope) -> Result<usize> {
+        self.ensure_writable()?;
+        if !backup_path.is_file() {
+            anyhow::bail!("Backup {:?} does not exist", backup_path);
+        }
+
+        let db = self.persistent_db(scope)?.clone();
+        {
+            let mut conn = db.lock().unwrap();
+            conn.restore(
+                DatabaseName::Main,
+                backup_path,
+                None::<fn(rusqlite::backup::Progress)>,
+            )
+            .with_context(|| format!("Failed to restore from {:?}", backup_path))?;
+            Self::migrate_schema(&mut conn)?;
+        }
+
+        let count = self.count(scope)?;
+        info!(
+            "Restored {} memories into {} from {:?}",
+            count, scope, backup_path
+        );
+        Ok(count)
+    }
+
+    /// Copy every memory of a database written by the original SQLite store (columns
+    /// `id, content, scope, metadata, created_at, updated_at`) into `scope`. Fields that
+    /// schema lacks get their defaults: version 1, no embedding, no accesses, unpinned.
+    /// The source is opened read-only. Returns the number of memories imported.
+    pub fn migrate_from_sqlite(
+        &mut self,
+        sqlite_path: &Path,
+        scope: &MemoryScope,
+    ) -> Result<usize> {
+        self.ensure_writable()?;
+        if !sqlite_path.is_file() {
+            anyhow::bail!("Database {:?} does not exist", sqlite_path);
+        }
+
+        let conn = Connection::open_with_flags(sqlite_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open {:?}", sqlite_path))?;
+        let mut stmt =
+            conn.prepare("SELECT id, content, metadata, created_at, updated_at FROM memories")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+
+        let mut memories = Vec::new();
+        for row in rows {
+            let (id, content, metadata, created_at, updated_at) = row?;
+            let metadata = serde_json::from_str(&metadata)
+                .with_context(|| format!("Invalid metadata for memory {}", id))?;
+            let timestamp = |secs: i64| {
+                chrono::DateTime::from_timestamp(secs, 0)
+                    .with_context(|| format!("Invalid timestamp for memory {}", id))
+            };
+            let mut memory = Memory::with_metadata(content, scope.clone(), metadata);
+            memory.created_at = timestamp(created_at)?;
+            memory.updated_at = timestamp(updated_at)?;
+            memory.id = id;
+            memories.push(memory);
+        }
+
+        let count = self.batch_store(memories)?;
+        info!(
+            "Migrated {} memories into {} from {:?}",
+            count, scope, sqlite_path
+        );
+        Ok(count)
+    }
+
+    /// Open database of a global or project scope
+    fn persistent_db(&mut self, scope: &MemoryScope) -> Result<&Arc<Mutex<Connection>>> {
+        match scope {
+            MemoryScope::Session { .. } => {
+                anyhow::bail!("Session memories live in memory and have no database")
+            }
+            MemoryScope::Global => self.get_or_create_global_db(),
+            MemoryScope::Project { path } => self.get_or_create_project_db(path),
+        }
+    }
+
+    /// Drop the memories of session `name` (None: the default session) except pinned
+    /// ones; other sessions are untouched
+    pub fn clear_session(&mut self, name: Option<&str>) {
+        let scope = MemoryScope::Session {
+            name: name.map(str::to_string),
+        }
+        .normalize();
+        info!("Clearing session memories: {}", scope);
+        let mut cleared = Vec::new();
+        if let Some(memories) = self.session_mut(&scope) {
+            memories.retain(|id, memory| {
+                if !memory.pinned {
+                    cleared.push(id.clone());
+                }
+                memory.pinned
+            });
+        }
+        for id in cleared {
+            self.emit(&scope, MemoryEvent::Deleted(id));
+        }
+    }
+
+    /// Names of sessions holding memories, sorted; the default session is
+    /// `DEFAULT_SESSION`
+    pub fn list_sessions(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .session
+            .iter()
+            .filter(|(_, memories)| !memories.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Memories of the session `scope` names; None for other scopes and for sessions
+    /// never written to
+    fn session_map(&self, scope: &MemoryScope) -> Option<&HashMap<String, Memory>> {
+        scope.session_name().and_then(|name| self.session.get(name))
+    }
+
+    /// `session_map` as an iterator, empty when there is no such session
+    fn session_memories(&self, scope: &MemoryScope) -> impl Iterator<Item = &Memory> {
+        self.session_map(scope)
+            .into_iter()
+            .flat_map(HashMap::values)
+    }
+
+    fn session_mut(&mut self, scope: &MemoryScope) -> Option<&mut HashMap<String, Memory>> {
+        scope
+            .session_name()
+            .and_then(|name| self.session.get_mut(name))
+    }
+
+    /// Memories of the session `scope` names, created on first write
+    fn session_entry(&mut self, scope: &MemoryScope) -> &mut HashMap<String, Memory> {
+        let name = scope.session_name().unwrap_or(DEFAULT_SESSION);
+        self.session.entry(name.to_string()).or_default()
+    }
+
+    /// Overwrite an existing memory (content, metadata, pin state) and bump `updated_at`.
+    /// Returns false when `memory.id` is not in `memory.scope`.
+    pub fn update(&mut self, mut memory: Memory) -> Result<bool> {
+        self.ensure_writable()?;
+        if !self.exists(&memory.id, &memory.scope)? {
+            return Ok(false);
+        }
+
+        memory.updated_at = chrono::Utc::now();
+        self.store(memory)?;
+        Ok(true)
+    }
+
+    /// Replace `old_tag` with `new_tag` on every memory in `scope` carrying it; a memory
+    /// that already has `new_tag` just loses `old_tag`. Returns the number modified.
+    pub fn rename_tag(
+        &mut self,
+        scope: &MemoryScope,
+        old_tag: &str,
+        new_tag: &str,
+    ) -> Result<usize> {
+        if old_tag == new_tag {
+            return Ok(0);
+        }
+        self.retag(scope, |memory| {
+            if !memory.remove_tag(old_tag) {
+                return false;
+            }
+            memory.add_tag(new_tag);
+            true
+        })
+    }
+
+    /// Remove `tag` from every memory in `scope`. Returns the number modified.
+    pub fn delete_tag(&mut self, scope: &MemoryScope, tag: &str) -> Result<usize> {
+        self.retag(scope, |memory| memory.remove_tag(tag))
+    }
+
+    /// Memories in `scope` without any tag, e.g. left behind by `delete_tag`
+    pub fn list_orphaned_memories(&mut self, scope: &MemoryScope) -> Result<Vec<Memory>> {
+        let mut memories = self.list_all(scope)?;
+        memories.retain(|memory| memory.metadata.tags.is_empty());
+        Ok(memories)
+    }
+
+    /// Apply `edit` to every memory in `scope` and write back, in one transaction, those
+    /// it reports as changed. Returns how many changed.
+    fn retag(
+        &mut self,
+        scope: &MemoryScope,
+        mut edit: impl FnMut(&mut Memory) -> bool,
+    ) -> Result<usize> {
+        self.ensure_writable()?;
+        let now = chrono::Utc::now();
+        let mut changed = Vec::new();
+        for mut memory in self.list_all(scope)? {
+            if edit(&mut memory) {
+                memory.updated_at = now;
+                changed.push(memory);
+            }
+        }
+        self.batch_store(changed)
+    }
+
+    /// Pin or unpin a memory. Returns false when `id` is not found in `scope`.
+    pub fn set_pinned(&mut self, id: &str, scope: &MemoryScope, pinned: bool) -> Result<bool> {
+        self.ensure_writable()?;
+        let Some(mut memory) = self.get_loaded(id, scope)? else {
+            return Ok(false);
+        };
+
+        memory.pinned = pinned;
+        memory.updated_at = chrono::Utc::now();
+        self.store(memory)?;
+        Ok(true)
+    }
+
+    /// Replace only the embedding column; metadata and timestamps are left untouched.
+    /// Returns false when `id` is not in `scope`.
+    pub fn update_embedding(
+        &mut self,
+        id: &str,
+        scope: &MemoryScope,
+        embedding: Vec<f32>,
+    ) -> Result<bool> {
+        self.ensure_writable()?;
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(match self.session_mut(scope).and_then(|m| m.get_mut(id)) {
+                Some(memory) => {
+                    memory.embedding = embedding;
+                    true
+                }
+                None => false,
+            });
+        }
+
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(false);
+        };
+
+        let conn = db.lock().unwrap();
+        let affected = conn.execute(
+            "UPDATE memories SET embedding = ?1 WHERE id = ?2",
+            params![encode_embedding(&embedding), id],
+        )?;
+        Ok(affected > 0)
+    }
+
+    pub fn stats(&mut self, scope: &MemoryScope) -> Result<MemoryStats> {
+        let count = self.count(scope)?;
+        let (total_accesses, most_accessed_id) = match scope {
+            MemoryScope::Session { .. } => {
+                let total: u64 = self.session_memories(scope).map(|m| m.access_count).sum();
+                let most = self
+                    .session_memories(scope)
+                    .filter(|m| m.access_count > 0)
+                    .max_by_key(|m| m.access_count)
+                    .map(|m| m.id.clone());
+                (total, most)
+            }
+            _ => match self.loaded_db(scope) {
+                Some(db) => {
+                    let conn = db.lock().unwrap();
+                    let total: i64 = conn.query_row(
+                        "SELECT COALESCE(SUM(access_count), 0) FROM memories",
+                        [],
+                        |row| row.get(0),
+                    )?;
+                    let most: Option<String> = conn
+                        .query_row(
+                            "SELECT id FROM memories WHERE access_count > 0
+                             ORDER BY access_count DESC LIMIT 1",
+                            [],
+                            |row| row.get(0),
+                        )
+                        .optional()?;
+                    (total as u64, most)
+                }
+                None => (0, None),
+            },
+        };
+
+        let mut scores = self.importance_scores(scope)?;
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let ascending: Vec<f32> = scores.iter().rev().map(|(_, score)| *score).collect();
+        scores.truncate(TOP_IMPORTANT);
+
+        let extended = if self.extended_stats {
+            Some(self.extended_stats(scope)?)
+        } else {
+            None
+        };
+
+        Ok(MemoryStats {
+            total_memories: count,
+            scope: scope.clone(),
+            total_accesses,
+            most_accessed_id,
+            importance: ImportanceStats::from_sorted(&ascending),
+            top_important: scores,
+            extended,
+        })
+    }
+
+    /// Count the memories of `scope` by tag, language, source file and AST node type,
+    /// reading every memory. `stats` includes this when enabled with
+    /// `set_extended_stats`.
+    pub fn extended_stats(&mut self, scope: &MemoryScope) -> Result<ExtendedStats> {
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        let mut extended = ExtendedStats::default();
+        for memory in self.iter_all(scope)? {
+            let metadata = memory?.metadata;
+            for tag in metadata.tags {
+                *extended.by_tag.entry(tag).or_default() += 1;
+            }
+            if let Some(language) = metadata.language {
+                *extended.by_language.entry(language).or_default() += 1;
+            }
+            if let Some(source_file) = metadata.source_file {
+                *extended.by_source_file.entry(source_file).or_default() += 1;
+            }
+            if let Some(node_type) = metadata.ast_node_type {
+                *extended.by_ast_node_type.entry(node_type).or_default() += 1;
+            }
+        }
+        Ok(extended)
+    }
+
+    /// `(id, importance_score)` of every memory in `scope`, without decoding the records
+    fn importance_scores(&mut self, scope: &MemoryScope) -> Result<Vec<(String, f32)>> {
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(self
+                .session_memories(scope)
+                .map(|m| (m.id.clone(), m.metadata.importance_score))
+                .collect());
+        }
+        let Some(db) = self.loaded_db(scope) else {
+            return Ok(Vec::new());
+        };
+        let conn = db.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id, json_extract(metadata, '$.importance_score') FROM memories")?;
+        let rows = stmt.query_map([], |row| {
+            let score: Option<f64> = row.get(1)?;
+            Ok((row.get(0)?, score.unwrap_or(1.0) as f32))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Set a memory's `importance_score`; returns false when `id` is not in `scope`
+    pub fn update_importance(&mut self, id: &str, scope: &MemoryScope, score: f32) -> Result<bool> {
+        self.update_metadata(id, scope, |metadata| metadata.importance_score = score)
+    }
+
+    /// Apply `f` to a memory's metadata and write it back with a new `updated_at`.
+    /// Content is untouched, so BM25 indices built from it stay valid and callers need
+    /// not re-index. Returns false when `id` is not in `scope`.
+    pub fn update_metadata(
+        &mut self,
+        id: &str,
+        scope: &MemoryScope,
+        f: impl FnOnce(&mut MemoryMetadata),
+    ) -> Result<bool> {
+        self.ensure_writable()?;
+        let Some(mut memory) = self.get_loaded(id, scope)? else {
+            return Ok(false);
+        };
+
+        f(&mut memory.metadata);
+        memory.updated_at = chrono::Utc::now();
+        self.store(memory)?;
+        Ok(true)
+    }
+
+    /// Number of memories in `scope`, read from the trigger-maintained `counts` table
+    /// rather than by scanning the records
+    pub fn count(&mut self, scope: &MemoryScope) -> Result<usize> {
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(self.session_memories(scope).count());
+        }
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        match self.loaded_db(scope) {
+            Some(db) => {
+                let count: i64 = db.lock().unwrap().query_row(
+                    "SELECT value FROM counts WHERE name = 'memories'",
+                    [],
+                    |row| row.get(0),
+                )?;
+                Ok(count as usize)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Recount the records in `scope` and store the result as its memory count; repairs
+    /// drift from writers that ran without `recursive_triggers`. Returns the count.
+    pub fn rebuild_counts(&mut self, scope: &MemoryScope) -> Result<usize> {
+        self.ensure_writable()?;
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(self.session_memories(scope).count());
+        }
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        match self.loaded_db(scope) {
+            Some(db) => {
+                let conn = db.lock().unwrap();
+                let count: i64 =
                     conn.query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))?;
-                count as usize
+                conn.execute(
+                    "INSERT OR REPLACE INTO counts (name, value) VALUES ('memories', ?1)",
+                    [count],
+                )?;
+                Ok(count as usize)
             }
-        };
+            None => Ok(0),
+        }
+    }
 
-        Ok(MemoryStats {
-            total_memories: count,
-            scope: scope.clone(),
-        })
+    /// Open a database file with WAL enabled and the `memories` table in place
+    fn open_db(db_path: &Path) -> Result<Connection> {
+        let mut conn = Connection::open(db_path)
+            .with_context(|| format!("Failed to open database {:?}", db_path))?;
+        // Another process (CLI, `watch`) may hold the write lock, e.g. while creating tables
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        // `INSERT OR REPLACE` fires the delete trigger for the row it replaces, keeping
+        // the memory count exact
+        conn.pragma_update(None, "recursive_triggers", true)?;
+        // Version 1 layout; later columns arrive through MIGRATIONS
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS memories (
+                id TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                scope TEXT NOT NULL,
+                metadata TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        Self::migrate_schema(&mut conn)
+            .with_context(|| format!("Failed to migrate database {:?}", db_path))?;
+        Ok(conn)
+    }
+
+    /// Open a database file without write access; it must exist and be current, since
+    /// migrations cannot run
+    fn open_db_readonly(db_path: &Path) -> Result<Connection> {
+        if !db_path.exists() {
+            anyhow::bail!("Database {:?} does not exist", db_path);
+        }
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Failed to open database {:?} read-only", db_path))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+
+        let version = Self::read_version(&conn)?.max(1);
+        if version != STORAGE_VERSION {
+            anyhow::bail!(
+                "Database {:?} is at storage version {} (expected {}); run `rag-mcp migrate` first",
+                db_path,
+                version,
+                STORAGE_VERSION
+            );
+        }
+        Ok(conn)
+    }
+
+    /// Bring a database up to `STORAGE_VERSION`, one transaction per step.
+    /// The version lives in `PRAGMA user_version`; 0 means a database created before
+    /// versioning, which has the version 1 layout.
+    fn migrate_schema(conn: &mut Connection) -> Result<()> {
+        let mut version = Self::read_version(conn)?.max(1);
+
+        for &(target, migration) in MIGRATIONS {
+            if version >= target {
+                continue;
+            }
+            // Take the write lock up front (waiting out BUSY_TIMEOUT) and re-check:
+            // another process opening the same fresh database may have migrated it
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            version = Self::read_version(&tx)?.max(version);
+            if version >= target {
+                continue;
+            }
+            migration(&tx)?;
+            tx.pragma_update(None, "user_version", target)?;
+            tx.commit()?;
+            info!("Migrated storage from version {} to {}", version, target);
+            version = target;
+        }
+
+        if Self::read_version(conn)? == 0 {
+            conn.pragma_update(None, "user_version", version)?;
+        }
+        Ok(())
+    }
+
+    fn read_version(conn: &Connection) -> Result<u32> {
+        Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+    }
+
+    /// Storage version of the scope's database (session scope is always current)
+    pub fn storage_version(&mut self, scope: &MemoryScope) -> Result<u32> {
+        match scope {
+            MemoryScope::Session { .. } => Ok(STORAGE_VERSION),
+            MemoryScope::Global => {
+                let db = self.get_or_create_global_db()?;
+                Self::read_version(&db.lock().unwrap())
+            }
+            MemoryScope::Project { path } => {
+                let db = self.get_or_create_project_db(path)?;
+                Self::read_version(&db.lock().unwrap())
+            }
+        }
+    }
+
+    /// Rewrite every record in `scope` in the current format (schema migrations already
+    /// ran when the database was opened). Returns the number of records rewritten.
+    /// Fails without rewriting anything when some record's metadata can't be decoded,
+    /// since reading it would replace that metadata with defaults.
+    pub fn migrate(&mut self, scope: &MemoryScope) -> Result<usize> {
+        if let MemoryScope::Project { path } = scope {
+            self.get_or_create_project_db(path)?;
+        }
+        if let Some(db) = self.loaded_db(scope) {
+            let conn = db.lock().unwrap();
+            let mut stmt = conn.prepare("SELECT id, metadata FROM memories ORDER BY id")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            let mut undecodable = Vec::new();
+            for row in rows {
+                let (id, metadata) = row?;
+                if serde_json::from_str::<MemoryMetadata>(&metadata).is_err() {
+                    undecodable.push(id);
+                }
+            }
+            if !undecodable.is_empty() {
+                anyhow::bail!(
+                    "{} memories in {} have metadata that can't be decoded; fix or delete them before migrating: {}",
+                    undecodable.len(),
+                    scope,
+                    undecodable.join(", ")
+                );
+            }
+        }
+        let memories = self.list_all(scope)?;
+        self.batch_store(memories)
+    }
+
+    /// The global database additionally holds the registry of known project paths
+    fn open_global_db(db_path: &Path) -> Result<Connection> {
+        let conn = Self::open_db(db_path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS project_registry (
+                path TEXT PRIMARY KEY,
+                registered_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS embedding_cache (
+                hash TEXT PRIMARY KEY,
+                embedding BLOB NOT NULL
+            )",
+            [],
+        )?;
+        Ok(conn)
+    }
+
+    fn register_project(&mut self, path: &Path) -> Result<()> {
+        let db = self.get_or_create_global_db()?;
+        let conn = db.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO project_registry (path, registered_at) VALUES (?1, ?2)",
+            params![path.to_string_lossy(), chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Every project path whose database has been opened, in any process, sorted by path.
+    /// Entries are never removed; the database file may since have been deleted.
+    pub fn list_known_projects(&mut self) -> Result<Vec<PathBuf>> {
+        let db = self.get_or_create_global_db()?;
+        let conn = db.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path FROM project_registry ORDER BY path")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut projects = Vec::new();
+        for row in rows {
+            projects.push(PathBuf::from(row?));
+        }
+        Ok(projects)
+    }
+
+    /// Persist a scope's serialized search index, replacing the previous one. Session
+    /// indices are not persisted, like session memories.
+    pub fn save_search_index(&mut self, scope: &MemoryScope, data: &str) -> Result<()> {
+        self.ensure_writable()?;
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(());
+        }
+        let db = self.persistent_db(scope)?;
+        db.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO search_index (name, data) VALUES (?1, ?2)",
+            params![SEARCH_INDEX_KEY, data],
+        )?;
+        Ok(())
+    }
+
+    /// Search index saved by `save_search_index`; None for session scopes and scopes
+    /// never saved
+    pub fn load_search_index(&mut self, scope: &MemoryScope) -> Result<Option<String>> {
+        if let MemoryScope::Session { .. } = scope {
+            return Ok(None);
+        }
+        let data = self
+            .persistent_db(scope)?
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM search_index WHERE name = ?1",
+                [SEARCH_INDEX_KEY],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data)
+    }
+
+    /// Persisted embedding cache entries (content hash, embedding), at most `limit`
+    pub fn load_embedding_cache(&mut self, limit: usize) -> Result<Vec<(String, Vec<f32>)>> {
+        let db = self.get_or_create_global_db()?;
+        let conn = db.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT hash, embedding FROM embedding_cache LIMIT ?1")?;
+        let rows = stmt.query_map([limit], |row| {
+            Ok((row.get::<_, String>(0)?, decode_embedding(row.get(1)?)))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(row?);
+        }
+        Ok(entries)
+    }
+
+    pub fn save_cached_embedding(&mut self, hash: &str, embedding: &[f32]) -> Result<()> {
+        self.ensure_writable()?;
+        let db = self.get_or_create_global_db()?;
+        let conn = db.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO embedding_cache (hash, embedding) VALUES (?1, ?2)",
+            params![hash, encode_embedding(embedding)],
+        )?;
+        Ok(())
+    }
+
+    pub fn evict_cached_embedding(&mut self, hash: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let db = self.get_or_create_global_db()?;
+        let conn = db.lock().unwrap();
+        conn.execute("DELETE FROM embedding_cache WHERE hash = ?1", [hash])?;
+        Ok(())
+    }
+
+    /// Whether a project's database file exists on disk
+    pub fn project_db_exists(path: &Path) -> bool {
+        Self::project_db_path(path).exists()
     }
 
     fn get_or_create_global_db(&mut self) -> Result<&Arc<Mutex<Connection>>> {
         if self.global_db.is_none() {
+            if self.read_only {
+                anyhow::bail!("Database {:?} does not exist", self.global_db_path);
+            }
             if let Some(parent) = self.global_db_path.parent() {
                 std::fs::create_dir_all(parent)?;
             }
 
-            let conn = Connection::open(&self.global_db_path)?;
-            conn.pragma_update(None, "journal_mode", "WAL")?;
-            conn.pragma_update(None, "synchronous", "NORMAL")?;
-            conn.execute(
-                "CREATE TABLE IF NOT EXISTS memories (
-                    id TEXT PRIMARY KEY,
-                    content TEXT NOT NULL,
-                    scope TEXT NOT NULL,
-                    metadata TEXT NOT NULL,
-                    created_at INTEGER NOT NULL,
-                    updated_at INTEGER NOT NULL
-                )",
-                [],
-            )?;
-
+            let conn = Self::open_global_db(&self.global_db_path)?;
             self.global_db = Some(Arc::new(Mutex::new(conn)));
         }
         Ok(self.global_db.as_ref().unwrap())
     }
 
+    fn project_db_path(path: &Path) -> PathBuf {
+        path.join(".rag-mcp").join("data.db")
+    }
+
     fn get_or_create_project_db(&mut self, path: &Path) -> Result<&Arc<Mutex<Connection>>> {
         if !self.project_dbs.contains_key(path) {
-            let db_path = path.join(".rag-mcp").join("data.db");
+            let db_path = Self::project_db_path(path);
+            if self.read_only {
+                let conn = Self::open_db_readonly(&db_path)?;
+                self.project_dbs
+                    .insert(path.to_path_buf(), Arc::new(Mutex::new(conn)));
+                return Ok(self.project_dbs.get(path).unwrap());
+            }
             if let Some(parent) = db_path.parent() {
                 std::fs::create_dir_all(parent)?;
             }
 
-            let conn = Connection::open(&db_path)?;
-            conn.pragma_update(None, "journal_mode", "WAL")?;
-            conn.pragma_update(None, "synchronous", "NORMAL")?;
-            conn.execute(
-                "CREATE TABLE IF NOT EXISTS memories (
-                    id TEXT PRIMARY KEY,
-                    content TEXT NOT NULL,
-                    scope TEXT NOT NULL,
-                    metadata TEXT NOT NULL,
-                    created_at INTEGER NOT NULL,
-                    updated_at INTEGER NOT NULL
-                )",
-                [],
-            )?;
-
+            let conn = Self::open_db(&db_path)?;
+            self.register_project(path)?;
             self.project_dbs
                 .insert(path.to_path_buf(), Arc::new(Mutex::new(conn)));
         }
@@ -380,8 +2562,524 @@ impl MemoryStore {
     }
 }
 
+/// Generator for `get_random` and `reservoir_sample`: seeded when asked, else from the OS
+fn sample_rng(seed: Option<u64>) -> StdRng {
+    seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64)
+}
+
+/// `MemoryStore` opened with `MemoryStore::open_readonly`: reads only, and the write
+/// methods it keeps fail with `MemoryStoreError::ReadOnly`
+pub struct ReadonlyMemoryStore(MemoryStore);
+
+impl ReadonlyMemoryStore {
+    /// Same as `MemoryStore::get`; reads are not counted
+    pub fn get(&mut self, id: &str, scope: &MemoryScope) -> Result<Option<Memory>> {
+        self.0.get(id, scope)
+    }
+
+    pub fn exists(&mut self, id: &str, scope: &MemoryScope) -> Result<bool> {
+        self.0.exists(id, scope)
+    }
+
+    pub fn list(
+        &mut self,
+        scope: &MemoryScope,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Memory>> {
+        self.0.list(scope, limit, offset)
+    }
+
+    pub fn list_all(&mut self, scope: &MemoryScope) -> Result<Vec<Memory>> {
+        self.0.list_all(scope)
+    }
+
+    pub fn iter_all<'a>(
+        &'a self,
+        scope: &'a MemoryScope,
+    ) -> Result<Box<dyn Iterator<Item = Result<Memory>> + 'a>> {
+        self.0.iter_all(scope)
+    }
+
+    pub fn stats(&mut self, scope: &MemoryScope) -> Result<MemoryStats> {
+        self.0.stats(scope)
+    }
+
+    pub fn store(&mut self, _memory: Memory) -> Result<()> {
+        Err(MemoryStoreError::ReadOnly.into())
+    }
+
+    pub fn delete(&mut self, _id: &str, _scope: &MemoryScope) -> Result<bool> {
+        Err(MemoryStoreError::ReadOnly.into())
+    }
+
+    pub fn clear_session(&mut self, _name: Option<&str>) -> Result<()> {
+        Err(MemoryStoreError::ReadOnly.into())
+    }
+
+    /// The underlying store, for code written against `MemoryStore`; it keeps
+    /// refusing writes
+    pub fn into_inner(self) -> MemoryStore {
+        self.0
+    }
+}
+
+/// Operation queued on a `MemoryStoreTransaction`
+enum TransactionOp {
+    DeleteIfExists {
+        id: String,
+        scope: MemoryScope,
+    },
+    Store(Box<Memory>),
+    UpdateMetadata {
+        id: String,
+        scope: MemoryScope,
+        f: Box<dyn FnOnce(&mut MemoryMetadata)>,
+    },
+}
+
+/// Stores, deletes and metadata updates applied all-or-nothing by `commit`.
+///
+/// Session changes are made to a copy of the sessions that replaces them on success.
+/// Each database runs its operations inside one SQLite transaction that is rolled back
+/// if any operation fails. Databases commit one after another at the end, so only a
+/// failing COMMIT can leave a transaction spanning several databases half-applied.
+#[derive(Default)]
+pub struct MemoryStoreTransaction {
+    ops: Vec<TransactionOp>,
+}
+
+/// What `MemoryStoreTransaction::commit` changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransactionReport {
+    /// Memories written by `store` or `update_metadata`
+    pub stores: usize,
+    /// Memories that existed and were deleted
+    pub deletes: usize,
+}
+
+impl MemoryStoreTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delete `id` from `scope` when it is there. A pinned memory fails the transaction
+    /// with `MemoryStoreError::MemoryPinned`, as `MemoryStore::delete` does.
+    pub fn delete_if_exists(mut self, id: impl Into<String>, scope: MemoryScope) -> Self {
+        self.ops.push(TransactionOp::DeleteIfExists {
+            id: id.into(),
+            scope,
+        });
+        self
+    }
+
+    pub fn store(mut self, memory: Memory) -> Self {
+        self.ops.push(TransactionOp::Store(Box::new(memory)));
+        self
+    }
+
+    /// Same as `MemoryStore::update_metadata`, except that a missing memory fails the
+    /// transaction with `MemoryStoreError::MemoryNotFound`
+    pub fn update_metadata(
+        mut self,
+        id: impl Into<String>,
+        scope: MemoryScope,
+        f: impl FnOnce(&mut MemoryMetadata) + 'static,
+    ) -> Self {
+        self.ops.push(TransactionOp::UpdateMetadata {
+            id: id.into(),
+            scope,
+            f: Box::new(f),
+        });
+        self
+    }
+
+    /// Apply the queued operations in order. On error nothing is changed.
+    pub fn commit(self, store: &mut MemoryStore) -> Result<TransactionReport> {
+        store.ensure_writable()?;
+        let mut staged = StagedTransaction {
+            session: store.session.clone(),
+            databases: HashMap::new(),
+            report: TransactionReport::default(),
+            events: Vec::new(),
+        };
+
+        if let Err(e) = self
+            .ops
+            .into_iter()
+            .try_for_each(|op| staged.apply(store, op))
+        {
+            staged.databases.values().for_each(rollback);
+            return Err(e);
+        }
+
+        commit_all(staged.databases.values())?;
+        store.session = staged.session;
+        for (scope, event) in staged.events {
+            store.emit(&scope, event);
+        }
+        debug!(
+            "Transaction committed: {} stores, {} deletes",
+            staged.report.stores, staged.report.deletes
+        );
+        Ok(staged.report)
+    }
+}
+
+/// COMMIT each database in turn. After a failed COMMIT, that database and every one
+/// not yet committed are rolled back, so none is left holding the write lock inside
+/// an open transaction. Databases committed before the failure stay committed.
+fn commit_all<'a>(databases: impl IntoIterator<Item = &'a Arc<Mutex<Connection>>>) -> Result<()> {
+    let mut databases = databases.into_iter();
+    while let Some(db) = databases.next() {
+        let committed = db.lock().unwrap().execute_batch("COMMIT");
+        if let Err(e) = committed {
+            std::iter::once(db).chain(databases).for_each(rollback);
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// ROLLBACK the open transaction of `db`, if any
+fn rollback(db: &Arc<Mutex<Connection>>) {
+    let conn = db.lock().unwrap();
+    if conn.is_autocommit() {
+        return;
+    }
+    if let Err(e) = conn.execute_batch("ROLLBACK") {
+        warn!("Failed to roll back transaction: {}", e);
+    }
+}
+
+/// Changes of a `MemoryStoreTransaction` not yet committed
+struct StagedTransaction {
+    session: HashMap<String, HashMap<String, Memory>>,
+    /// Databases inside `BEGIN IMMEDIATE`, keyed by project path (None for global)
+    databases: HashMap<Option<PathBuf>, Arc<Mutex<Connection>>>,
+    report: TransactionReport,
+    events: Vec<(MemoryScope, MemoryEvent)>,
+}
+
+impl StagedTransaction {
+    fn apply(&mut self, store: &mut MemoryStore, op: TransactionOp) -> Result<()> {
+        match op {
+            TransactionOp::DeleteIfExists { id, scope } => {
+                let Some(memory) = self.get(store, &id, &scope)? else {
+                    return Ok(());
+                };
+                if memory.pinned {
+                    return Err(MemoryStoreError::MemoryPinned(id).into());
+                }
+                self.remove(store, &id, &scope)?;
+                self.report.deletes += 1;
+                self.events.push((scope, MemoryEvent::Deleted(id)));
+            }
+            TransactionOp::Store(memory) => {
+                self.put(store, *memory)?;
+            }
+            TransactionOp::UpdateMetadata { id, scope, f } => {
+                let Some(mut memory) = self.get(store, &id, &scope)? else {
+                    return Err(MemoryStoreError::MemoryNotFound(id).into());
+                };
+                f(&mut memory.metadata);
+                memory.updated_at = chrono::Utc::now();
+                self.put(store, memory)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Connection for a global or project scope, starting its transaction on first use
+    fn database(
+        &mut self,
+        store: &mut MemoryStore,
+        scope: &MemoryScope,
+    ) -> Result<Arc<Mutex<Connection>>> {
+        let key = match scope {
+            MemoryScope::Project { path } => Some(path.clone()),
+            _ => None,
+        };
+        if let Some(db) = self.databases.get(&key) {
+            return Ok(db.clone());
+        }
+
+        let db = match &key {
+            None => store.get_or_create_global_db()?,
+            Some(path) => store.get_or_create_project_db(path)?,
+        }
+        .clone();
+        db.lock().unwrap().execute_batch("BEGIN IMMEDIATE")?;
+        self.databases.insert(key, db.clone());
+        Ok(db)
+    }
+
+    fn get(
+        &mut self,
+        store: &mut MemoryStore,
+        id: &str,
+        scope: &MemoryScope,
+    ) -> Result<Option<Memory>> {
+        if let Some(name) = scope.session_name() {
+            return Ok(self.session.get(name).and_then(|m| m.get(id)).cloned());
+        }
+        let db = self.database(store, scope)?;
+        let memory = db
+            .lock()
+            .unwrap()
+            .query_row(
+                &format!("SELECT {} FROM memories WHERE id = ?1", SELECT_COLUMNS),
+                [id],
+                |row| MemoryStore::row_to_memory(row, scope),
+            )
+            .optional()?;
+        Ok(memory)
+    }
+
+    fn put(&mut self, store: &mut MemoryStore, memory: Memory) -> Result<()> {
+        store.validate_metadata(&memory.metadata)?;
+        if let Some(name) = memory.scope.session_name() {
+            self.session
+                .entry(name.to_string())
+                .or_default()
+                .insert(memory.id.clone(), memory.clone());
+        } else {
+            let db = self.database(store, &memory.scope)?;
+            MemoryStore::insert_row(&db.lock().unwrap(), &memory)?;
+        }
+        self.report.stores += 1;
+        if store.watched() {
+            self.events
+                .push((memory.scope.clone(), MemoryEvent::Stored(Box::new(memory))));
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, store: &mut MemoryStore, id: &str, scope: &MemoryScope) -> Result<()> {
+        if let Some(name) = scope.session_name() {
+            if let Some(memories) = self.session.get_mut(name) {
+                memories.remove(id);
+            }
+        } else {
+            let db = self.database(store, scope)?;
+            db.lock()
+                .unwrap()
+                .execute("DELETE FROM memories WHERE id = ?1", [id])?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    pub total: usize,
+    /// IDs of rows that failed to decode
+    pub corrupt: Vec<String>,
+    pub valid: usize,
+}
+
+/// One page of `list_after_cursor`; pass `next_cursor` back as `after_id` for the next
+#[derive(Debug, Clone)]
+pub struct MemoryPage {
+    pub memories: Vec<Memory>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompactionReport {
+    pub records_copied: usize,
+    /// Database file plus WAL, before and after
+    pub old_size_bytes: u64,
+    pub new_size_bytes: u64,
+}
+
+/// What `MemoryStore::dedup` removed, or `dedup_preview` would remove
+#[derive(Debug, Clone, Default)]
+pub struct DeduplicationReport {
+    /// Distinct contents stored more than once
+    pub groups_found: usize,
+    pub memories_deleted: usize,
+    /// Content bytes of the deleted memories; the database file shrinks on `compact`
+    pub bytes_freed: u64,
+    /// The memories deleted, so callers can drop them from search indices
+    pub deleted: Vec<Memory>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupReport {
+    pub files_copied: usize,
+    pub total_bytes: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Clone)]
 pub struct MemoryStats {
     pub total_memories: usize,
     pub scope: MemoryScope,
+    /// Sum of `access_count` over the scope
+    pub total_accesses: u64,
+    /// None until some memory has been read via `get`
+    pub most_accessed_id: Option<String>,
+    /// None for an empty scope
+    pub importance: Option<ImportanceStats>,
+    /// Up to five `(id, importance_score)` pairs, most important first
+    pub top_important: Vec<(String, f32)>,
+    /// None unless enabled with `MemoryStore::set_extended_stats`
+    pub extended: Option<ExtendedStats>,
+}
+
+/// Memory counts per metadata value. A memory counts once under each of its tags;
+/// memories without a language, source file or AST node type are not counted there.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtendedStats {
+    pub by_tag: HashMap<String, usize>,
+    pub by_language: HashMap<String, usize>,
+    pub by_source_file: HashMap<PathBuf, usize>,
+    pub by_ast_node_type: HashMap<String, usize>,
+}
+
+/// Distribution of `importance_score` over a scope
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportanceStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    /// Nearest-rank 90th percentile
+    pub percentile_90: f32,
+}
+
+impl ImportanceStats {
+    /// Statistics of `scores` sorted ascending; None when empty
+    fn from_sorted(scores: &[f32]) -> Option<Self> {
+        let (&min, &max) = (scores.first()?, scores.last()?);
+        let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+        let rank = (scores.len() as f32 * 0.9).ceil() as usize;
+        Some(Self {
+            min,
+            max,
+            mean,
+            percentile_90: scores[rank.max(1) - 1],
+        })
+    }
+}
+
+/// Keyset-paginated walk over a `memories` table; see `MemoryStore::iter_all`
+struct MemoryIter<'a> {
+    db: &'a Arc<Mutex<Connection>>,
+    scope: &'a MemoryScope,
+    last_rowid: i64,
+    page: std::collections::VecDeque<Memory>,
+    done: bool,
+}
+
+impl MemoryIter<'_> {
+    fn fetch_page(&mut self) -> Result<()> {
+        let conn = self.db.lock().unwrap();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {}, rowid FROM memories WHERE rowid > ?1 ORDER BY rowid LIMIT ?2",
+            SELECT_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![self.last_rowid, ITER_PAGE_SIZE as i64])?;
+        while let Some(row) = rows.next()? {
+            self.page
+                .push_back(MemoryStore::row_to_memory(row, self.scope)?);
+            self.last_rowid = row.get(10)?;
+        }
+        self.done = self.page.len() < ITER_PAGE_SIZE;
+        Ok(())
+    }
+}
+
+impl Iterator for MemoryIter<'_> {
+    type Item = Result<Memory>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.done {
+            if let Err(e) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+/// Size of a SQLite database on disk: the main file plus its WAL, if any
+fn db_file_size(db_path: &Path) -> u64 {
+    ["", "-wal"]
+        .iter()
+        .filter_map(|suffix| std::fs::metadata(format!("{}{}", db_path.display(), suffix)).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Embedding column value: little-endian f32s, NULL for an empty vector
+fn encode_embedding(embedding: &[f32]) -> Option<Vec<u8>> {
+    if embedding.is_empty() {
+        return None;
+    }
+    Some(embedding.iter().flat_map(|v| v.to_le_bytes()).collect())
+}
+
+fn decode_embedding(blob: Option<Vec<u8>>) -> Vec<f32> {
+    blob.unwrap_or_default()
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Connection inside a transaction whose COMMIT fails when `violate` is set: a
+    /// deferred foreign key is only checked at COMMIT
+    fn pending(violate: bool) -> Arc<Mutex<Connection>> {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE parent (id INTEGER PRIMARY KEY);
+             CREATE TABLE child (
+                 parent_id INTEGER REFERENCES parent(id) DEFERRABLE INITIALLY DEFERRED
+             );
+             BEGIN IMMEDIATE;",
+        )
+        .unwrap();
+        let parent_id = if violate { 42 } else { 1 };
+        conn.execute_batch(&format!(
+            "INSERT INTO parent VALUES (1); INSERT INTO child VALUES ({parent_id});"
+        ))
+        .unwrap();
+        Arc::new(Mutex::new(conn))
+    }
+
+    fn rows(db: &Arc<Mutex<Connection>>) -> i64 {
+        db.lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM child", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn failed_commit_rolls_back_remaining_databases() {
+        let databases = [pending(false), pending(true), pending(false)];
+
+        assert!(commit_all(&databases).is_err());
+
+        assert!(databases
+            .iter()
+            .all(|db| db.lock().unwrap().is_autocommit()));
+        assert_eq!(rows(&databases[0]), 1);
+        assert_eq!(rows(&databases[1]), 0);
+        assert_eq!(rows(&databases[2]), 0);
+
+        // The connections accept new transactions
+        databases[2]
+            .lock()
+            .unwrap()
+            .execute_batch("BEGIN IMMEDIATE; INSERT INTO child VALUES (NULL); COMMIT;")
+            .unwrap();
+        assert_eq!(rows(&databases[2]), 1);
+    }
 }
@@ -0,0 +1,63 @@
+// Each test binary uses only some of these helpers
+#![allow(dead_code)]
+
+use rag_core::storage::MemoryStore;
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Fresh directory `rag-core-{name}-{pid}` under the system temp dir, removed again on
+/// drop. `name` must be unique among the tests of one binary, which run in parallel.
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("rag-core-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// Path of the global database `store` opens
+    pub fn db_path(&self) -> PathBuf {
+        self.0.join("global.db")
+    }
+
+    /// Open (or reopen) a store whose global database lives in this directory
+    pub fn store(&self) -> anyhow::Result<MemoryStore> {
+        MemoryStore::new(self.db_path())
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The in-memory default session and the on-disk global scope, for tests that must
+/// hold for both storage backends
+pub fn scopes() -> [MemoryScope; 2] {
+    [MemoryScope::session(), MemoryScope::Global]
+}
+
+/// Memory with default metadata
+pub fn new_memory(content: &str, scope: &MemoryScope) -> Memory {
+    Memory::new(
+        content.to_string(),
+        scope.clone(),
+        MemoryMetadata::default(),
+    )
+}
+
+pub fn ids(memories: &[Memory]) -> Vec<&str> {
+    memories.iter().map(|m| m.id.as_str()).collect()
+}
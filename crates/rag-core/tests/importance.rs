@@ -0,0 +1,80 @@
+mod common;
+
+use common::TestDir;
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+
+fn tags(names: &[&str]) -> Vec<String> {
+    names.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn critical_long_memory_scores_higher() {
+    let memory = Memory::new(
+        "x".repeat(600),
+        MemoryScope::session(),
+        MemoryMetadata {
+            tags: tags(&["critical"]),
+            ..Default::default()
+        },
+    );
+    assert!(memory.metadata.importance_score >= 1.5);
+}
+
+#[test]
+fn compute_importance_follows_heuristic() {
+    assert_eq!(MemoryMetadata::compute_importance("note", &[]), 1.0);
+    assert_eq!(
+        MemoryMetadata::compute_importance("note", &tags(&["api", "Decision", "misc"])),
+        2.0
+    );
+    let todo = MemoryMetadata::compute_importance("TODO: wire up retries", &[]);
+    assert!((todo - 0.8).abs() < 1e-6);
+}
+
+#[test]
+fn explicit_importance_is_kept() {
+    let memory = Memory::new(
+        "x".repeat(600),
+        MemoryScope::session(),
+        MemoryMetadata {
+            tags: tags(&["critical"]),
+            importance_score: 4.0,
+            ..Default::default()
+        },
+    );
+    assert_eq!(memory.metadata.importance_score, 4.0);
+}
+
+#[test]
+fn recompute_importance_uses_current_tags() {
+    let mut metadata = MemoryMetadata::default();
+    metadata.add_tag("architecture");
+    metadata.recompute_importance("FIXME: split this module");
+    assert!((metadata.importance_score - 1.3).abs() < 1e-6);
+}
+
+#[test]
+fn copies_keep_an_explicit_default_score() -> anyhow::Result<()> {
+    let dir = TestDir::new("importance-copy");
+    let mut store = dir.store()?;
+
+    let mut memory = Memory::new(
+        "Decided to keep the v1 wire format".to_string(),
+        MemoryScope::session(),
+        MemoryMetadata {
+            tags: tags(&["critical", "decision"]),
+            ..Default::default()
+        },
+    );
+    memory.metadata.importance_score = 1.0;
+    store.store(memory.clone())?;
+
+    let copy = store
+        .copy_memory(&memory.id, &MemoryScope::session(), &MemoryScope::Global)?
+        .unwrap();
+    assert_eq!(copy.metadata.importance_score, 1.0);
+    let stored = store.get_without_tracking(&copy.id, &MemoryScope::Global)?;
+    assert_eq!(stored.unwrap().metadata.importance_score, 1.0);
+
+    Ok(())
+}
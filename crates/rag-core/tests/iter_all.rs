@@ -0,0 +1,77 @@
+mod common;
+
+use common::{new_memory, TestDir};
+use rag_core::{Memory, MemoryScope};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tracks live and peak heap bytes so the test can compare memory spikes
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(current, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Peak heap growth while running `f`
+fn peak_growth<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let value = f();
+    (value, PEAK.load(Ordering::SeqCst) - baseline)
+}
+
+#[test]
+fn iter_all_matches_list_all_with_less_memory() -> anyhow::Result<()> {
+    let dir = TestDir::new("iter-all");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+
+    let padding = "x".repeat(2048);
+    let memories: Vec<Memory> = (0..2000)
+        .map(|i| new_memory(&format!("Memory {} {}", i, padding), &scope))
+        .collect();
+    store.batch_store(memories)?;
+
+    let (listed, list_peak) = peak_growth(|| store.list_all(&scope));
+    let mut listed: Vec<String> = listed?.into_iter().map(|m| m.id).collect();
+
+    let (iterated, iter_peak) = peak_growth(|| -> anyhow::Result<Vec<String>> {
+        let mut ids = Vec::with_capacity(2000);
+        for memory in store.iter_all(&scope)? {
+            ids.push(memory?.id);
+        }
+        Ok(ids)
+    });
+    let mut iterated = iterated?;
+
+    listed.sort();
+    iterated.sort();
+    assert_eq!(listed, iterated);
+    assert!(
+        iter_peak * 4 < list_peak,
+        "iter_all peak {} bytes vs list_all peak {} bytes",
+        iter_peak,
+        list_peak
+    );
+
+    Ok(())
+}
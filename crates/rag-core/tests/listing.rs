@@ -0,0 +1,316 @@
+mod common;
+
+use common::{ids, new_memory, scopes, TestDir};
+use rag_core::storage::{MemorySort, MemoryStore};
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[test]
+fn list_breaks_created_at_ties_by_id() -> anyhow::Result<()> {
+    let dir = TestDir::new("list-order");
+
+    let mut store = dir.store()?;
+    let created_at = chrono::Utc::now();
+
+    for scope in scopes() {
+        let mut ids = Vec::new();
+        for i in 0..10 {
+            let mut memory = new_memory(&format!("Batch memory {}", i), &scope);
+            memory.created_at = created_at;
+            ids.push(memory.id.clone());
+            store.store(memory)?;
+        }
+        ids.sort();
+
+        for _ in 0..3 {
+            let listed: Vec<String> = store
+                .list(&scope, 10, 0)?
+                .into_iter()
+                .map(|m| m.id)
+                .collect();
+            assert_eq!(listed, ids, "{}", scope);
+
+            let by_access: Vec<String> = store
+                .list_sorted(&scope, MemorySort::AccessCountDesc, 10, 0)?
+                .into_iter()
+                .map(|m| m.id)
+                .collect();
+            assert_eq!(by_access, ids, "{}", scope);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn list_after_cursor_walks_every_memory_once() -> anyhow::Result<()> {
+    let dir = TestDir::new("pagination");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+
+    // Same-second timestamps: the id tie-break keeps pages disjoint
+    let memories: Vec<Memory> = (0..10)
+        .map(|i| new_memory(&format!("Memory {}", i), &scope))
+        .collect();
+    let mut expected: Vec<String> = memories.iter().map(|m| m.id.clone()).collect();
+    store.batch_store(memories)?;
+
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = store.list_after_cursor(&scope, cursor.as_deref(), 3)?;
+        seen.extend(page.memories.into_iter().map(|m| m.id));
+        if !page.has_more {
+            break;
+        }
+        cursor = page.next_cursor;
+    }
+
+    assert_eq!(seen.len(), 10);
+    seen.sort();
+    expected.sort();
+    assert_eq!(seen, expected);
+
+    assert!(store.list_after_cursor(&scope, Some("missing"), 3).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn list_with_total_counts_the_whole_scope() -> anyhow::Result<()> {
+    let dir = TestDir::new("list-total");
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        store.batch_store(
+            (0..7)
+                .map(|i| new_memory(&format!("Memory {}", i), &scope))
+                .collect(),
+        )?;
+
+        for (limit, offset, page_len) in [(3, 0, 3), (3, 6, 1), (10, 0, 7), (3, 20, 0)] {
+            let (page, total) =
+                store.list_with_total(&scope, limit, offset, MemorySort::CreatedDesc)?;
+            assert_eq!(page.len(), page_len);
+            assert_eq!(total, 7);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fixed_seed_samples_are_reproducible() -> anyhow::Result<()> {
+    let dir = TestDir::new("random");
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        for i in 0..50 {
+            store.store(new_memory(&format!("Sampled memory {}", i), &scope))?;
+        }
+
+        let first = store.get_random(&scope, 10, Some(7))?;
+        assert_eq!(first.len(), 10);
+        assert_eq!(ids(&first), ids(&store.get_random(&scope, 10, Some(7))?));
+        assert_ne!(ids(&first), ids(&store.get_random(&scope, 10, Some(8))?));
+
+        let reservoir = store.reservoir_sample(&scope, 10, Some(7))?;
+        assert_eq!(reservoir.len(), 10);
+        assert_eq!(
+            ids(&reservoir),
+            ids(&store.reservoir_sample(&scope, 10, Some(7))?)
+        );
+
+        // Asking for more than the scope holds returns every memory once
+        let everything = store.reservoir_sample(&scope, 100, None)?;
+        let mut all = ids(&everything);
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), 50);
+        assert_eq!(store.get_random(&scope, 100, None)?.len(), 50);
+        assert!(store.reservoir_sample(&scope, 0, Some(7))?.is_empty());
+
+        // Sampling does not count as reading
+        assert!(first.iter().all(|m| m.access_count == 0));
+    }
+
+    Ok(())
+}
+
+fn stored(store: &mut MemoryStore, project: &Path, content: &str, age_secs: i64) -> Memory {
+    let mut memory = Memory::new(
+        content.to_string(),
+        MemoryScope::Project {
+            path: project.to_path_buf(),
+        },
+        MemoryMetadata::default(),
+    );
+    memory.created_at = chrono::Utc::now() - chrono::Duration::seconds(age_secs);
+    store.store(memory.clone()).unwrap();
+    memory
+}
+
+#[test]
+fn path_prefix_collects_every_sub_project() -> anyhow::Result<()> {
+    let dir = TestDir::new("path-prefix");
+    let root = dir.join("proj");
+    let (frontend, backend) = (root.join("frontend"), root.join("backend"));
+    // A sibling sharing the prefix as a string, not as a path component
+    let sibling = dir.join("proj-old");
+    for path in [&frontend, &backend, &sibling] {
+        std::fs::create_dir_all(path)?;
+    }
+
+    let mut store = dir.store()?;
+    let older = stored(&mut store, &frontend, "Frontend uses signals", 60);
+    let newer = stored(&mut store, &backend, "Backend uses axum", 10);
+    stored(&mut store, &sibling, "Legacy notes", 0);
+
+    let ids: Vec<String> = store
+        .list_by_path_prefix(&root, 10, 0)?
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(ids, vec![newer.id.clone(), older.id.clone()]);
+
+    let page: Vec<String> = store
+        .list_by_path_prefix(&root, 1, 1)?
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(page, vec![older.id]);
+    assert!(store
+        .list_by_path_prefix(&dir.join("none"), 10, 0)?
+        .is_empty());
+
+    Ok(())
+}
+
+fn project(path: impl Into<PathBuf>) -> MemoryScope {
+    MemoryScope::Project { path: path.into() }
+}
+
+#[test]
+fn project_path_spellings_share_one_database() -> anyhow::Result<()> {
+    let dir = TestDir::new("project-path");
+    std::fs::create_dir_all(dir.join("proj"))?;
+    std::fs::create_dir_all(dir.join("other"))?;
+
+    let plain = project(dir.join("proj")).normalize();
+    let slash = project(format!("{}/", dir.join("proj").display())).normalize();
+    let dotted = project(dir.join("other").join("..").join("proj")).normalize();
+    assert_eq!(plain, slash);
+    assert_eq!(plain, dotted);
+
+    let mut store = dir.store()?;
+    let memory = new_memory("Stored through the trailing slash", &slash);
+    store.store(memory.clone())?;
+
+    let listed = store.list_all(&dotted)?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, memory.id);
+    assert_eq!(store.list_known_projects()?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn missing_project_path_is_cleaned_lexically() {
+    assert_eq!(
+        project("/no/such/dir/./sub/../proj/").normalize(),
+        project("/no/such/dir/proj")
+    );
+    assert_eq!(project("/..").normalize(), project("/"));
+    assert_eq!(
+        project("../../missing-dir-x").normalize(),
+        project("../../missing-dir-x")
+    );
+    assert_eq!(MemoryScope::Global.normalize(), MemoryScope::Global);
+}
+
+fn child(content: &str, scope: &MemoryScope, parent: &Memory, index: usize) -> Memory {
+    Memory::new(
+        content.to_string(),
+        scope.clone(),
+        MemoryMetadata {
+            parent_id: Some(parent.id.clone()),
+            chunk_index: Some(index),
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn ancestors_and_descendants_follow_parent_links() -> anyhow::Result<()> {
+    let dir = TestDir::new("hierarchy");
+    let mut store = dir.store()?;
+
+    for scope in scopes() {
+        let root = new_memory("root", &scope);
+        let second = child("second", &scope, &root, 1);
+        let first = child("first", &scope, &root, 0);
+        let grandchild = child("grandchild", &scope, &first, 0);
+        let unrelated = new_memory("other", &scope);
+        store.batch_store(vec![
+            grandchild.clone(),
+            second.clone(),
+            root.clone(),
+            first.clone(),
+            unrelated,
+        ])?;
+
+        let chain = store.get_ancestors(&grandchild.id, &scope)?;
+        assert_eq!(ids(&chain), vec![&root.id, &first.id, &grandchild.id]);
+        assert_eq!(ids(&store.get_ancestors(&root.id, &scope)?), vec![&root.id]);
+
+        // Children in chunk order, then the next generation
+        let descendants = store.get_descendants(&root.id, &scope)?;
+        assert_eq!(
+            ids(&descendants),
+            vec![&first.id, &second.id, &grandchild.id]
+        );
+        assert_eq!(
+            ids(&store.get_descendants(&first.id, &scope)?),
+            vec![&grandchild.id]
+        );
+        assert!(store.get_descendants(&grandchild.id, &scope)?.is_empty());
+
+        assert!(store.get_ancestors("missing", &scope).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn regex_search_finds_rust_function_definitions() -> anyhow::Result<()> {
+    let dir = TestDir::new("regex");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::session();
+    for content in [
+        "fn parse(input: &str) {}\nfn render() {}",
+        "pub fn main() { let f = 1; }",
+        "a sentence that ends with fn",
+        "let function_count = 3;",
+    ] {
+        store.store(new_memory(content, &scope))?;
+    }
+
+    let results = store.search_regex(&scope, r"\bfn\s+\w+", 10)?;
+    let contents: Vec<&str> = results.iter().map(|r| r.memory.content.as_str()).collect();
+    assert_eq!(contents.len(), 2, "{:?}", contents);
+    assert!(contents[0].starts_with("fn parse"));
+    assert_eq!(results[0].score, 2.0);
+    assert!(contents[1].starts_with("pub fn main"));
+
+    let err = store.search_regex(&scope, "(unclosed", 10).unwrap_err();
+    assert!(err.to_string().contains("unclosed group"), "{}", err);
+
+    store.set_regex_timeout(Duration::ZERO);
+    let err = store.search_regex(&scope, r"\bfn", 10).unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{}", err);
+
+    Ok(())
+}
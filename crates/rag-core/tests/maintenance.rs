@@ -0,0 +1,361 @@
+mod common;
+
+use common::{ids, new_memory, scopes, TestDir};
+use rag_core::storage::{DedupKeepPolicy, MemoryStore, STORAGE_VERSION};
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+use rusqlite::params;
+
+#[test]
+fn restore_brings_back_backed_up_memories() -> anyhow::Result<()> {
+    let dir = TestDir::new("backup");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+    let memories: Vec<Memory> = (0..5)
+        .map(|i| new_memory(&format!("Memory {}", i), &scope))
+        .collect();
+    store.batch_store(memories.clone())?;
+
+    let backup_path = dir.join("backups").join("global.db");
+    let report = store.backup(&scope, &backup_path)?;
+    assert_eq!(report.files_copied, 1);
+    assert!(report.total_bytes > 0);
+    assert!(
+        store.backup(&scope, &backup_path).is_err(),
+        "never overwrites"
+    );
+
+    store.delete_scope(&scope)?;
+    assert_eq!(store.count(&scope)?, 0);
+
+    assert_eq!(store.restore(&backup_path, &scope)?, 5);
+    let mut ids: Vec<String> = store.list_all(&scope)?.into_iter().map(|m| m.id).collect();
+    ids.sort();
+    let mut expected: Vec<String> = memories.into_iter().map(|m| m.id).collect();
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    assert!(store
+        .backup(&MemoryScope::session(), &dir.join("session.db"))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn compact_preserves_records_and_reclaims_space() -> anyhow::Result<()> {
+    let dir = TestDir::new("compaction");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+
+    let padding = "lorem ipsum dolor sit amet ".repeat(200);
+    let memories: Vec<Memory> = (0..50)
+        .map(|i| new_memory(&format!("Memory {} {}", i, padding), &scope))
+        .collect();
+    let keep: Vec<String> = memories.iter().take(10).map(|m| m.id.clone()).collect();
+    store.batch_store(memories.clone())?;
+    for memory in &memories[10..] {
+        store.force_delete(&memory.id, &scope)?;
+    }
+
+    let report = store.compact(&scope)?;
+    assert_eq!(report.records_copied, 10);
+    assert!(
+        report.new_size_bytes < report.old_size_bytes,
+        "{:?}",
+        report
+    );
+
+    let mut ids: Vec<String> = store.list_all(&scope)?.into_iter().map(|m| m.id).collect();
+    ids.sort();
+    let mut expected = keep;
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    Ok(())
+}
+
+#[test]
+fn verify_integrity_reports_corrupt_rows() -> anyhow::Result<()> {
+    let dir = TestDir::new("integrity");
+    let db_path = dir.db_path();
+
+    let mut store = dir.store()?;
+    let good = new_memory("Intact memory", &MemoryScope::Global);
+    store.store(good.clone())?;
+
+    // Write rows the store would never produce, straight through SQLite
+    let conn = rusqlite::Connection::open(&db_path)?;
+    conn.execute(
+        "INSERT INTO memories (id, content, scope, metadata, created_at, updated_at)
+         VALUES ('bad-json', 'x', 'global', '{not json', 0, 0)",
+        [],
+    )?;
+    conn.execute(
+        "INSERT INTO memories (id, content, scope, metadata, created_at, updated_at)
+         VALUES ('bad-time', 'x', 'global', ?1, 'yesterday', 0)",
+        [serde_json::to_string(&MemoryMetadata::default())?],
+    )?;
+
+    let mut report = store.verify_integrity(&MemoryScope::Global)?;
+    report.corrupt.sort();
+    assert_eq!(report.total, 3);
+    assert_eq!(report.valid, 1);
+    assert_eq!(report.corrupt, vec!["bad-json", "bad-time"]);
+
+    for id in &report.corrupt {
+        store.force_delete(id, &MemoryScope::Global)?;
+    }
+    let report = store.verify_integrity(&MemoryScope::Global)?;
+    assert!(report.corrupt.is_empty());
+    assert_eq!(report.valid, 1);
+
+    Ok(())
+}
+
+fn store_copies(
+    store: &mut MemoryStore,
+    scope: &MemoryScope,
+    content: &str,
+    n: usize,
+) -> anyhow::Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for _ in 0..n {
+        let memory = new_memory(content, scope);
+        ids.push(memory.id.clone());
+        store.store(memory)?;
+    }
+    Ok(ids)
+}
+
+#[test]
+fn keep_oldest_retains_first_copy() -> anyhow::Result<()> {
+    let dir = TestDir::new("dedup");
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        let copies = store_copies(&mut store, &scope, "Use WAL mode for SQLite", 3)?;
+        store_copies(&mut store, &scope, "Unique note", 1)?;
+
+        assert_eq!(store.find_duplicates(&scope)?, vec![copies.clone()]);
+
+        let preview = store.dedup_preview(&scope, DedupKeepPolicy::KeepOldest)?;
+        assert_eq!(preview.memories_deleted, 2);
+        assert_eq!(store.count(&scope)?, 4);
+
+        let report = store.dedup(&scope, DedupKeepPolicy::KeepOldest)?;
+        assert_eq!(report.groups_found, 1);
+        assert_eq!(report.memories_deleted, 2);
+        assert_eq!(
+            report.bytes_freed,
+            2 * "Use WAL mode for SQLite".len() as u64
+        );
+        assert_eq!(ids(&report.deleted), copies[1..].to_vec());
+
+        assert!(store.exists(&copies[0], &scope)?);
+        assert_eq!(store.count(&scope)?, 2);
+        assert!(store.find_duplicates(&scope)?.is_empty());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keep_policies_pick_survivor_and_spare_pinned() -> anyhow::Result<()> {
+    let dir = TestDir::new("dedup-policy");
+    let scope = MemoryScope::named_session("dedup-policies");
+    let mut store = dir.store()?;
+
+    let copies = store_copies(&mut store, &scope, "Same text", 3)?;
+    store.update_importance(&copies[1], &scope, 5.0)?;
+    let report = store.dedup(&scope, DedupKeepPolicy::KeepHighestImportance)?;
+    assert_eq!(report.memories_deleted, 2);
+    assert!(store.exists(&copies[1], &scope)?);
+
+    let copies = store_copies(&mut store, &scope, "Other text", 3)?;
+    store.set_pinned(&copies[0], &scope, true)?;
+    let report = store.dedup(&scope, DedupKeepPolicy::KeepNewest)?;
+    assert_eq!(ids(&report.deleted), vec![copies[1].clone()]);
+    assert!(store.exists(&copies[0], &scope)?);
+    assert!(store.exists(&copies[2], &scope)?);
+
+    assert!("sometimes".parse::<DedupKeepPolicy>().is_err());
+    Ok(())
+}
+
+#[test]
+fn search_index_survives_reopen_and_delete_scope_drops_it() -> anyhow::Result<()> {
+    let dir = TestDir::new("search-index");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+    assert_eq!(store.load_search_index(&scope)?, None);
+
+    store.save_search_index(&scope, r#"{"doc_count":1}"#)?;
+    store.save_search_index(&scope, r#"{"doc_count":2}"#)?;
+    drop(store);
+
+    let mut store = dir.store()?;
+    assert_eq!(
+        store.load_search_index(&scope)?.as_deref(),
+        Some(r#"{"doc_count":2}"#)
+    );
+
+    // Sessions live in memory and are always reindexed
+    let session = MemoryScope::session();
+    store.save_search_index(&session, "{}")?;
+    assert_eq!(store.load_search_index(&session)?, None);
+
+    store.delete_scope(&scope)?;
+    assert_eq!(store.load_search_index(&scope)?, None);
+
+    Ok(())
+}
+
+#[test]
+fn version_1_database_is_migrated_on_open() -> anyhow::Result<()> {
+    let dir = TestDir::new("migration");
+    let db_path = dir.db_path();
+
+    // Layout written by releases before storage versioning
+    {
+        let conn = rusqlite::Connection::open(&db_path)?;
+        conn.execute(
+            "CREATE TABLE memories (
+                id TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                scope TEXT NOT NULL,
+                metadata TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "INSERT INTO memories VALUES ('legacy', 'Old memory', 'global', ?1, 1700000000, 1700000000)",
+            [r#"{"tags":["old"],"source_file":null,"language":null,"chunk_index":null,"parent_id":null,"ast_node_type":null,"importance_score":1.0,"custom":{}}"#],
+        )?;
+    }
+
+    let mut store = dir.store()?;
+    assert_eq!(
+        store.storage_version(&MemoryScope::Global)?,
+        STORAGE_VERSION
+    );
+
+    let memory = store
+        .get_without_tracking("legacy", &MemoryScope::Global)?
+        .expect("legacy memory readable after migration");
+    assert_eq!(memory.content, "Old memory");
+    assert_eq!(memory.metadata.tags, vec!["old".to_string()]);
+    assert_eq!(memory.access_count, 0);
+    assert!(!memory.pinned);
+    assert!(memory.embedding.is_empty());
+
+    assert_eq!(store.migrate(&MemoryScope::Global)?, 1);
+    assert_eq!(store.count(&MemoryScope::Global)?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn migrate_refuses_undecodable_metadata() -> anyhow::Result<()> {
+    let dir = TestDir::new("migration-undecodable");
+    let db_path = dir.db_path();
+
+    let mut store = dir.store()?;
+    let mut memory = new_memory("Tagged memory", &MemoryScope::Global);
+    memory.add_tag("keep");
+    store.store(memory.clone())?;
+    {
+        let conn = rusqlite::Connection::open(&db_path)?;
+        conn.execute(
+            "UPDATE memories SET metadata = '{\"tags\": 7}' WHERE id = ?1",
+            [&memory.id],
+        )?;
+    }
+
+    let err = store.migrate(&MemoryScope::Global).unwrap_err();
+    assert!(err.to_string().contains(&memory.id), "{}", err);
+
+    // The record was not rewritten with default metadata
+    let conn = rusqlite::Connection::open(&db_path)?;
+    let metadata: String = conn.query_row(
+        "SELECT metadata FROM memories WHERE id = ?1",
+        [&memory.id],
+        |row| row.get(0),
+    )?;
+    assert_eq!(metadata, r#"{"tags": 7}"#);
+
+    Ok(())
+}
+
+#[test]
+fn migrate_from_sqlite_imports_legacy_rows() -> anyhow::Result<()> {
+    let dir = TestDir::new("migrate-sqlite");
+
+    // Schema of the original SQLite store: no access stats, pins or embeddings
+    let legacy_path = dir.join("legacy.db");
+    let legacy = rusqlite::Connection::open(&legacy_path)?;
+    legacy.execute(
+        "CREATE TABLE memories (
+            id TEXT PRIMARY KEY,
+            content TEXT NOT NULL,
+            scope TEXT NOT NULL,
+            metadata TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    // The old store wrote 1.0 for every memory, whatever its tags
+    for (id, content, tags, importance_score) in [
+        (
+            "legacy-1",
+            "Deploys go through staging",
+            vec!["critical"],
+            1.0,
+        ),
+        (
+            "legacy-2",
+            "Parser uses a Pratt loop",
+            vec!["rust", "parser"],
+            2.0,
+        ),
+    ] {
+        let metadata = MemoryMetadata {
+            tags: tags.into_iter().map(String::from).collect(),
+            importance_score,
+            ..Default::default()
+        };
+        legacy.execute(
+            "INSERT INTO memories VALUES (?1, ?2, 'global', ?3, 1700000000, 1700000100)",
+            params![id, content, serde_json::to_string(&metadata)?],
+        )?;
+    }
+    drop(legacy);
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+    assert_eq!(store.migrate_from_sqlite(&legacy_path, &scope)?, 2);
+
+    let memory = store.get_without_tracking("legacy-2", &scope)?.unwrap();
+    assert_eq!(memory.content, "Parser uses a Pratt loop");
+    assert_eq!(memory.metadata.tags, vec!["rust", "parser"]);
+    assert_eq!(memory.metadata.importance_score, 2.0);
+    assert_eq!(memory.created_at.timestamp(), 1700000000);
+    assert_eq!(memory.updated_at.timestamp(), 1700000100);
+    assert_eq!(memory.version, 1);
+    assert!(memory.embedding.is_empty());
+    assert!(!memory.pinned);
+    let critical = store.get_without_tracking("legacy-1", &scope)?.unwrap();
+    assert_eq!(critical.metadata.importance_score, 1.0);
+    assert_eq!(store.count(&scope)?, 2);
+
+    assert!(store
+        .migrate_from_sqlite(&dir.join("missing.db"), &scope)
+        .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use rag_core::{AstContext, Chunk, Memory, MemoryMetadata, MemoryScope};
+use std::path::PathBuf;
+
+#[test]
+fn is_chunk_distinguishes_chunk_memories() {
+    let regular = Memory::new(
+        "Standalone note".to_string(),
+        MemoryScope::session(),
+        MemoryMetadata::default(),
+    );
+    assert!(!regular.is_chunk());
+
+    let chunk = Memory::new_chunk(
+        "fn main() {}".to_string(),
+        MemoryScope::Global,
+        "doc-1".to_string(),
+        3,
+        Some(PathBuf::from("src/main.rs")),
+        Some("rust".to_string()),
+        vec!["code".to_string()],
+    );
+    assert!(chunk.is_chunk());
+    assert_eq!(chunk.metadata.chunk_index, Some(3));
+    assert_eq!(chunk.metadata.parent_id.as_deref(), Some("doc-1"));
+    assert_eq!(
+        chunk.metadata.source_file,
+        Some(PathBuf::from("src/main.rs"))
+    );
+    assert_eq!(chunk.metadata.language.as_deref(), Some("rust"));
+    assert_eq!(chunk.metadata.tags, vec!["code".to_string()]);
+    assert_eq!(chunk.metadata.importance_score, 1.0);
+}
+
+#[test]
+fn scope_uri_prefix_round_trips() {
+    let scopes = [
+        MemoryScope::session(),
+        MemoryScope::named_session("review"),
+        MemoryScope::Global,
+        MemoryScope::Project {
+            path: PathBuf::from("/home/dev/project"),
+        },
+    ];
+    for scope in scopes {
+        let prefix = scope.uri_prefix();
+        assert!(prefix.starts_with("memory://"), "{}", prefix);
+        assert_eq!(MemoryScope::from_uri_prefix(&prefix), Some(scope));
+    }
+
+    assert_eq!(MemoryScope::from_uri_prefix("memory://galaxy"), None);
+    assert_eq!(MemoryScope::from_uri_prefix("memory://project/"), None);
+    assert_eq!(MemoryScope::from_uri_prefix("file://global"), None);
+}
+
+#[test]
+fn scope_display_round_trips() -> anyhow::Result<()> {
+    let project = MemoryScope::Project {
+        path: PathBuf::from("/home/user/code"),
+    };
+    assert_eq!(project.to_string(), "project:/home/user/code");
+    assert_eq!(project.display_name(), "project");
+
+    let named = MemoryScope::named_session("review");
+    assert_eq!(named.to_string(), "session:review");
+
+    for scope in [MemoryScope::session(), named, MemoryScope::Global, project] {
+        assert_eq!(scope.to_string().parse::<MemoryScope>()?, scope);
+    }
+
+    assert!("project".parse::<MemoryScope>().is_err());
+    assert!("galaxy".parse::<MemoryScope>().is_err());
+    Ok(())
+}
+
+#[test]
+fn chunk_to_memory_fills_chunk_metadata() {
+    let chunk = Chunk {
+        content: "fn main() {}".to_string(),
+        start_byte: 0,
+        end_byte: 12,
+        context_prefix_bytes: 0,
+        ast_context: Some(AstContext {
+            node_type: "function_item".to_string(),
+            parent_types: vec!["source_file".to_string()],
+            depth: 1,
+            is_declaration: true,
+        }),
+    };
+
+    let memory = chunk.to_memory(
+        MemoryScope::Global,
+        "doc-1",
+        3,
+        Some(PathBuf::from("src/main.rs")),
+        Some("rust".to_string()),
+        vec!["code".to_string()],
+    );
+
+    assert!(memory.is_chunk());
+    assert_eq!(memory.content, "fn main() {}");
+    assert_eq!(memory.scope, MemoryScope::Global);
+    assert_eq!(memory.metadata.parent_id.as_deref(), Some("doc-1"));
+    assert_eq!(memory.metadata.chunk_index, Some(3));
+    assert_eq!(
+        memory.metadata.source_file,
+        Some(PathBuf::from("src/main.rs"))
+    );
+    assert_eq!(memory.metadata.language.as_deref(), Some("rust"));
+    assert_eq!(memory.metadata.tags, vec!["code".to_string()]);
+    assert_eq!(
+        memory.metadata.ast_node_type.as_deref(),
+        Some("function_item")
+    );
+}
+
+/// These strings are the stored form documented in STORAGE_FORMAT.md; changing one
+/// breaks every record written before
+#[test]
+fn scope_json_matches_documented_format() -> anyhow::Result<()> {
+    let cases = [
+        (MemoryScope::session(), r#"{"session":{"name":null}}"#),
+        (
+            MemoryScope::Session {
+                name: Some("review".to_string()),
+            },
+            r#"{"session":{"name":"review"}}"#,
+        ),
+        (
+            MemoryScope::Project {
+                path: PathBuf::from("/home/me/app"),
+            },
+            r#"{"project":{"path":"/home/me/app"}}"#,
+        ),
+        (MemoryScope::Global, r#""global""#),
+    ];
+
+    for (scope, json) in cases {
+        assert_eq!(serde_json::to_string(&scope)?, json);
+        assert_eq!(serde_json::from_str::<MemoryScope>(json)?, scope);
+    }
+    Ok(())
+}
+
+#[test]
+fn scope_reads_names_written_before_the_format_was_pinned() -> anyhow::Result<()> {
+    assert_eq!(
+        serde_json::from_str::<MemoryScope>(r#"{"Session":{"name":null}}"#)?,
+        MemoryScope::session()
+    );
+    assert_eq!(
+        serde_json::from_str::<MemoryScope>(r#"{"Project":{"path":"/srv/app"}}"#)?,
+        MemoryScope::Project {
+            path: PathBuf::from("/srv/app")
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<MemoryScope>(r#""Global""#)?,
+        MemoryScope::Global
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_project_path_fails_to_serialize() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let scope = MemoryScope::Project {
+        path: PathBuf::from(OsStr::from_bytes(b"/tmp/\xff")),
+    };
+    assert!(serde_json::to_string(&scope).is_err());
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::{scopes, TestDir};
+use rag_core::storage::ImportanceStats;
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+use std::path::PathBuf;
+
+#[test]
+fn extended_stats_break_down_every_memory() -> anyhow::Result<()> {
+    let dir = TestDir::new("extended");
+
+    let mut store = dir.store()?;
+    let entries = [
+        ("rust", "backend", "src/lib.rs", "function"),
+        ("rust", "backend", "src/lib.rs", "struct"),
+        ("python", "scripts", "tools/gen.py", "function"),
+        ("typescript", "frontend", "web/app.ts", "function"),
+        ("rust", "frontend", "web/wasm.rs", "impl"),
+    ];
+    for scope in scopes() {
+        assert!(store.stats(&scope)?.extended.is_none());
+
+        for (i, (language, tag, file, node)) in entries.iter().enumerate() {
+            store.store(Memory::new(
+                format!("Memory {}", i),
+                scope.clone(),
+                MemoryMetadata {
+                    tags: vec![tag.to_string()],
+                    language: Some(language.to_string()),
+                    source_file: Some(PathBuf::from(file)),
+                    ast_node_type: Some(node.to_string()),
+                    ..Default::default()
+                },
+            ))?;
+        }
+
+        assert!(store.stats(&scope)?.extended.is_none());
+        store.set_extended_stats(true);
+        let stats = store.stats(&scope)?;
+        store.set_extended_stats(false);
+        let extended = stats.extended.expect("extended stats enabled");
+
+        assert_eq!(stats.total_memories, entries.len());
+        for counts in [
+            extended.by_tag.values().sum::<usize>(),
+            extended.by_language.values().sum(),
+            extended.by_source_file.values().sum(),
+            extended.by_ast_node_type.values().sum(),
+        ] {
+            assert_eq!(counts, stats.total_memories);
+        }
+        assert_eq!(extended.by_language["rust"], 3);
+        assert_eq!(extended.by_tag["frontend"], 2);
+        assert_eq!(extended.by_source_file[&PathBuf::from("src/lib.rs")], 2);
+        assert_eq!(extended.by_ast_node_type["function"], 3);
+        assert_eq!(store.extended_stats(&scope)?, extended);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn stats_report_importance_distribution() -> anyhow::Result<()> {
+    let dir = TestDir::new("importance");
+
+    let mut store = dir.store()?;
+    assert_eq!(store.stats(&MemoryScope::Global)?.importance, None);
+
+    for scope in scopes() {
+        let memories: Vec<Memory> = (1..=10)
+            .map(|i| {
+                Memory::new(
+                    format!("Memory {}", i),
+                    scope.clone(),
+                    MemoryMetadata {
+                        importance_score: i as f32,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        store.batch_store(memories.clone())?;
+
+        let stats = store.stats(&scope)?;
+        assert_eq!(
+            stats.importance,
+            Some(ImportanceStats {
+                min: 1.0,
+                max: 10.0,
+                mean: 5.5,
+                percentile_90: 9.0,
+            })
+        );
+        let top: Vec<(String, f32)> = memories
+            .iter()
+            .rev()
+            .take(5)
+            .map(|m| (m.id.clone(), m.metadata.importance_score))
+            .collect();
+        assert_eq!(stats.top_important, top);
+
+        assert!(store.update_importance(&memories[0].id, &scope, 20.0)?);
+        let stats = store.stats(&scope)?;
+        assert_eq!(stats.importance.unwrap().max, 20.0);
+        assert_eq!(stats.top_important[0].0, memories[0].id);
+
+        assert!(!store.update_importance("no-such-memory", &scope, 1.0)?);
+    }
+
+    Ok(())
+}
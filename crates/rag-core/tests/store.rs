@@ -0,0 +1,594 @@
+mod common;
+
+use common::{new_memory, scopes, TestDir};
+use futures::StreamExt;
+use rag_core::storage::{MemoryEvent, MemoryStore, MemoryStoreError, TransactionReport};
+use rag_core::{content_hash, Memory, MemoryMetadata, MemoryScope, DEFAULT_SESSION};
+use serde_json::json;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// How long `watch_scope` streams get to deliver an event
+const WAIT: Duration = Duration::from_millis(100);
+
+#[test]
+fn count_tracks_store_delete_and_clear() -> anyhow::Result<()> {
+    let dir = TestDir::new("count");
+    let db_path = dir.db_path();
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+    assert_eq!(store.count(&scope)?, 0);
+
+    let memories: Vec<Memory> = (0..3)
+        .map(|i| new_memory(&format!("Memory {}", i), &scope))
+        .collect();
+    store.batch_store(memories.clone())?;
+    assert_eq!(store.count(&scope)?, 3);
+
+    // Overwriting an existing record is not a new memory
+    store.store(memories[0].clone())?;
+    assert_eq!(store.count(&scope)?, 3);
+
+    assert!(store.delete(&memories[0].id, &scope)?);
+    assert!(!store.delete(&memories[0].id, &scope)?);
+    assert_eq!(store.count(&scope)?, 2);
+
+    store.batch_delete(&[&memories[1].id], &scope)?;
+    assert_eq!(store.count(&scope)?, 1);
+    assert_eq!(store.stats(&scope)?.total_memories, 1);
+
+    store.delete_scope(&scope)?;
+    assert_eq!(store.count(&scope)?, 0);
+
+    store.batch_store(memories[..2].to_vec())?;
+    drop(store);
+    let mut store = dir.store()?;
+    assert_eq!(store.count(&scope)?, 2);
+
+    // A counter that drifted is recomputed from the records
+    rusqlite::Connection::open(&db_path)?
+        .execute("UPDATE counts SET value = 99 WHERE name = 'memories'", [])?;
+    assert_eq!(store.count(&scope)?, 99);
+    assert_eq!(store.rebuild_counts(&scope)?, 2);
+    assert_eq!(store.count(&scope)?, 2);
+
+    let session = new_memory("Session note", &MemoryScope::session());
+    store.store(session)?;
+    assert_eq!(store.count(&MemoryScope::session())?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn exists_checks_membership_without_decoding() -> anyhow::Result<()> {
+    let dir = TestDir::new("exists");
+    let db_path = dir.db_path();
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        let memory = new_memory("Membership check", &scope);
+        assert!(!store.exists(&memory.id, &scope)?);
+        store.store(memory.clone())?;
+        assert!(store.exists(&memory.id, &scope)?);
+        assert!(!store.exists("no-such-memory", &scope)?);
+    }
+
+    // A row that cannot be decoded into a Memory still exists
+    let conn = rusqlite::Connection::open(&db_path)?;
+    conn.execute(
+        "INSERT INTO memories (id, content, scope, metadata, created_at, updated_at)
+         VALUES ('undecodable', 'x', 'global', '{not json', 'yesterday', 0)",
+        [],
+    )?;
+    assert!(store.exists("undecodable", &MemoryScope::Global)?);
+    assert!(store
+        .get_without_tracking("undecodable", &MemoryScope::Global)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn update_overwrites_existing_memories_only() -> anyhow::Result<()> {
+    let dir = TestDir::new("update");
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        let mut memory = new_memory("Draft note", &scope);
+        memory.updated_at = chrono::Utc::now() - chrono::Duration::hours(1);
+        store.store(memory.clone())?;
+
+        let mut edited = memory.clone();
+        edited.content = "Final note".to_string();
+        edited.pinned = true;
+        edited.metadata.add_tag("reviewed");
+        assert!(store.update(edited)?);
+
+        let stored = store.get_without_tracking(&memory.id, &scope)?.unwrap();
+        assert_eq!(stored.content, "Final note");
+        assert!(stored.pinned);
+        assert_eq!(stored.metadata.tags, vec!["reviewed"]);
+        assert_eq!(stored.created_at.timestamp(), memory.created_at.timestamp());
+        assert!(stored.updated_at > memory.updated_at);
+
+        let unknown = new_memory("Never stored", &scope);
+        assert!(!store.update(unknown.clone())?);
+        assert!(!store.exists(&unknown.id, &scope)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn update_metadata_persists_and_keeps_content() -> anyhow::Result<()> {
+    let dir = TestDir::new("update-metadata");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+    let mut memory = new_memory("Borrow checker notes", &scope);
+    memory.embedding = vec![0.5, 0.25];
+    memory.updated_at = chrono::Utc::now() - chrono::Duration::hours(1);
+    store.store(memory.clone())?;
+
+    assert!(store.update_metadata(&memory.id, &scope, |metadata| {
+        metadata.add_tag("rust");
+        metadata.importance_score = 2.5;
+    })?);
+    assert!(!store.update_metadata("missing", &scope, |metadata| {
+        metadata.add_tag("rust");
+    })?);
+
+    let updated = store.get(&memory.id, &scope)?.unwrap();
+    assert_eq!(updated.metadata.tags, vec!["rust"]);
+    assert_eq!(updated.metadata.importance_score, 2.5);
+    assert_eq!(updated.content, memory.content);
+    assert_eq!(updated.embedding, memory.embedding);
+    assert!(updated.updated_at > memory.updated_at);
+
+    assert!(store.update_metadata(&memory.id, &scope, |metadata| {
+        metadata.remove_tag("rust");
+    })?);
+    let updated = store.get_without_tracking(&memory.id, &scope)?.unwrap();
+    assert!(updated.metadata.tags.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn batch_delete_skips_missing_ids() -> anyhow::Result<()> {
+    let dir = TestDir::new("batch-delete");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+
+    let memories: Vec<Memory> = (0..3)
+        .map(|i| new_memory(&format!("Memory {}", i), &scope))
+        .collect();
+    store.batch_store(memories.clone())?;
+
+    let deleted = store.batch_delete(
+        &[&memories[0].id, &memories[1].id, "no-such-memory"],
+        &scope,
+    )?;
+    assert_eq!(deleted, 2);
+
+    let remaining: Vec<String> = store.list_all(&scope)?.into_iter().map(|m| m.id).collect();
+    assert_eq!(remaining, vec![memories[2].id.clone()]);
+
+    let session = new_memory("Session note", &MemoryScope::session());
+    let session_id = session.id.clone();
+    store.store(session)?;
+    assert_eq!(
+        store.batch_delete(&[&session_id, &session_id], &MemoryScope::session())?,
+        1
+    );
+    assert!(store.list_all(&MemoryScope::session())?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn get_by_content_hash_finds_oldest_copy_and_survives_rebuild() -> anyhow::Result<()> {
+    let dir = TestDir::new("content-hash");
+    let db_path = dir.db_path();
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        let mut first = new_memory("same text", &scope);
+        first.created_at -= chrono::Duration::hours(1);
+        let second = new_memory("same text", &scope);
+        let other = new_memory("other text", &scope);
+        store.batch_store(vec![second, first.clone(), other.clone()])?;
+
+        let found = store.get_by_content_hash(&scope, &content_hash("same text"))?;
+        assert_eq!(found.map(|m| m.id), Some(first.id.clone()));
+        let found = store.get_by_content_hash(&scope, &other.content_hash())?;
+        assert_eq!(found.map(|m| m.content), Some("other text".to_string()));
+        assert!(store
+            .get_by_content_hash(&scope, &content_hash("missing"))?
+            .is_none());
+    }
+
+    // Rows edited behind the store's back are found again after a rebuild
+    rusqlite::Connection::open(&db_path)?.execute("UPDATE memories SET content_hash = NULL", [])?;
+    let scope = MemoryScope::Global;
+    assert!(store
+        .get_by_content_hash(&scope, &content_hash("other text"))?
+        .is_none());
+    assert_eq!(store.rebuild_hash_index(&scope)?, 3);
+    assert!(store
+        .get_by_content_hash(&scope, &content_hash("other text"))?
+        .is_some());
+    assert_eq!(store.rebuild_hash_index(&MemoryScope::session())?, 0);
+
+    Ok(())
+}
+
+fn tagged(content: &str, tags: &[&str]) -> Memory {
+    Memory::new(
+        content.to_string(),
+        MemoryScope::Global,
+        MemoryMetadata {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn rename_tag_replaces_without_duplicates() -> anyhow::Result<()> {
+    let dir = TestDir::new("rename-tag");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+    let memories = vec![
+        tagged("Ownership rules", &["rust", "notes"]),
+        tagged("Already migrated", &["rust", "rust-lang"]),
+        tagged("Unrelated", &["python"]),
+    ];
+    store.batch_store(memories.clone())?;
+
+    assert_eq!(store.rename_tag(&scope, "rust", "rust-lang")?, 2);
+    // Nothing left to rename
+    assert_eq!(store.rename_tag(&scope, "rust", "rust-lang")?, 0);
+
+    let tags_of = |store: &mut MemoryStore, i: usize| -> anyhow::Result<Vec<String>> {
+        Ok(store
+            .get_without_tracking(&memories[i].id, &scope)?
+            .unwrap()
+            .metadata
+            .tags)
+    };
+    assert_eq!(tags_of(&mut store, 0)?, vec!["notes", "rust-lang"]);
+    assert_eq!(tags_of(&mut store, 1)?, vec!["rust-lang"]);
+    assert_eq!(tags_of(&mut store, 2)?, vec!["python"]);
+
+    Ok(())
+}
+
+#[test]
+fn delete_tag_keeps_other_tags() -> anyhow::Result<()> {
+    let dir = TestDir::new("delete-tag");
+
+    let mut store = dir.store()?;
+    let scope = MemoryScope::Global;
+    let memories = vec![
+        tagged("Ownership rules", &["rust", "notes"]),
+        tagged("Only rust", &["rust"]),
+        tagged("Unrelated", &["python"]),
+    ];
+    store.batch_store(memories.clone())?;
+    assert!(store.list_orphaned_memories(&scope)?.is_empty());
+
+    assert_eq!(store.delete_tag(&scope, "rust")?, 2);
+    assert_eq!(store.delete_tag(&scope, "rust")?, 0);
+
+    for memory in store.list_all(&scope)? {
+        assert!(!memory.metadata.tags.contains(&"rust".to_string()));
+    }
+    let first = store
+        .get_without_tracking(&memories[0].id, &scope)?
+        .unwrap();
+    assert_eq!(first.metadata.tags, vec!["notes"]);
+
+    let orphaned = store.list_orphaned_memories(&scope)?;
+    assert_eq!(orphaned.len(), 1);
+    assert_eq!(orphaned[0].id, memories[1].id);
+
+    Ok(())
+}
+
+#[test]
+fn transaction_replaces_memory_atomically() -> anyhow::Result<()> {
+    let dir = TestDir::new("transaction");
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        let old = new_memory("Old note", &scope);
+        let tagged = new_memory("Tagged note", &scope);
+        store.store(old.clone())?;
+        store.store(tagged.clone())?;
+
+        let new = new_memory("New note", &scope);
+        let report = MemoryStore::transaction()
+            .delete_if_exists(&old.id, scope.clone())
+            .delete_if_exists("no-such-memory", scope.clone())
+            .store(new.clone())
+            .update_metadata(&tagged.id, scope.clone(), |m| {
+                m.add_tag("kept");
+            })
+            .commit(&mut store)?;
+
+        assert_eq!(
+            report,
+            TransactionReport {
+                stores: 2,
+                deletes: 1
+            }
+        );
+        assert!(!store.exists(&old.id, &scope)?);
+        assert!(store.exists(&new.id, &scope)?);
+        let tagged = store.get_without_tracking(&tagged.id, &scope)?.unwrap();
+        assert_eq!(tagged.metadata.tags, vec!["kept".to_string()]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn failed_transaction_leaves_no_partial_changes() -> anyhow::Result<()> {
+    let dir = TestDir::new("transaction-rollback");
+
+    let mut store = dir.store()?;
+    for scope in scopes() {
+        let old = new_memory("Old note", &scope);
+        store.store(old.clone())?;
+        let new = new_memory("New note", &scope);
+
+        let err = MemoryStore::transaction()
+            .delete_if_exists(&old.id, scope.clone())
+            .store(new.clone())
+            .update_metadata("no-such-memory", scope.clone(), |m| {
+                m.add_tag("x");
+            })
+            .commit(&mut store)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<MemoryStoreError>(),
+            Some(MemoryStoreError::MemoryNotFound(_))
+        ));
+
+        assert!(store.exists(&old.id, &scope)?);
+        assert!(!store.exists(&new.id, &scope)?);
+        assert_eq!(store.count(&scope)?, 1);
+        store.force_delete(&old.id, &scope)?;
+    }
+
+    // A pinned memory fails the transaction like `delete` refuses it
+    let pinned = new_memory("Pinned note", &MemoryScope::Global);
+    store.store(pinned.clone())?;
+    store.set_pinned(&pinned.id, &MemoryScope::Global, true)?;
+    let new = new_memory("Replacement", &MemoryScope::Global);
+    let err = MemoryStore::transaction()
+        .store(new.clone())
+        .delete_if_exists(&pinned.id, MemoryScope::Global)
+        .commit(&mut store)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MemoryStoreError>(),
+        Some(MemoryStoreError::MemoryPinned(_))
+    ));
+    assert!(store.exists(&pinned.id, &MemoryScope::Global)?);
+    assert!(!store.exists(&new.id, &MemoryScope::Global)?);
+
+    Ok(())
+}
+
+#[test]
+fn named_sessions_are_isolated() -> anyhow::Result<()> {
+    let dir = TestDir::new("named-sessions");
+
+    let mut store = dir.store()?;
+    let alice = MemoryScope::named_session("alice");
+    let bob = MemoryScope::named_session("bob");
+    let note = |scope: &MemoryScope| new_memory(&format!("Note of {}", scope), scope);
+    let alice_note = note(&alice);
+    store.store(alice_note.clone())?;
+    store.store(note(&bob))?;
+    store.store(note(&MemoryScope::session()))?;
+
+    assert_eq!(store.count(&alice)?, 1);
+    assert_eq!(store.count(&bob)?, 1);
+    assert!(store.get_without_tracking(&alice_note.id, &bob)?.is_none());
+    assert!(store
+        .get_without_tracking(&alice_note.id, &alice)?
+        .is_some());
+    assert_eq!(store.list_sessions(), vec!["alice", "bob", DEFAULT_SESSION]);
+    // Naming the default session explicitly is the same as leaving it unnamed
+    assert_eq!(
+        MemoryScope::named_session(DEFAULT_SESSION),
+        MemoryScope::session()
+    );
+
+    store.clear_session(Some("alice"));
+    assert_eq!(store.count(&alice)?, 0);
+    assert_eq!(store.count(&bob)?, 1);
+    assert_eq!(store.count(&MemoryScope::session())?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn store_rejects_memories_over_quota() -> anyhow::Result<()> {
+    let dir = TestDir::new("quota");
+
+    let mut store = dir.store()?;
+    store.set_quotas(Some(3), Some(3));
+    let project = MemoryScope::Project {
+        path: dir.join("project"),
+    };
+
+    for scope in [MemoryScope::Global, project] {
+        let stored: Vec<Memory> = (0..3)
+            .map(|i| new_memory(&format!("Memory {}", i), &scope))
+            .collect();
+        for memory in &stored {
+            store.store(memory.clone())?;
+        }
+
+        let err = store.store(new_memory("Memory 3", &scope)).unwrap_err();
+        match err.downcast_ref::<MemoryStoreError>() {
+            Some(MemoryStoreError::QuotaExceeded {
+                scope: full,
+                limit,
+                current,
+            }) => {
+                assert_eq!(full, &scope);
+                assert_eq!((*limit, *current), (3, 3));
+            }
+            other => panic!("Expected QuotaExceeded, got {:?}", other),
+        }
+        assert!(store
+            .batch_store(vec![new_memory("Memory 3", &scope)])
+            .is_err());
+        assert_eq!(store.count(&scope)?, 3);
+
+        // Rewriting a stored memory needs no room
+        let mut updated = stored[0].clone();
+        updated.content = "Memory 0, edited".to_string();
+        store.store(updated.clone())?;
+        store.batch_store(vec![updated])?;
+
+        store.delete(&stored[1].id, &scope)?;
+        store.store(new_memory("Memory 3", &scope))?;
+    }
+
+    // Sessions are bounded by max_session_memories, not the quotas
+    for i in 0..5 {
+        store.store(new_memory(
+            &format!("Session {}", i),
+            &MemoryScope::session(),
+        ))?;
+    }
+
+    Ok(())
+}
+
+fn is_read_only_error(result: anyhow::Result<impl std::fmt::Debug>) -> bool {
+    matches!(
+        result.unwrap_err().downcast_ref::<MemoryStoreError>(),
+        Some(MemoryStoreError::ReadOnly)
+    )
+}
+
+#[test]
+fn readonly_store_reads_but_refuses_writes() -> anyhow::Result<()> {
+    let dir = TestDir::new("readonly");
+    let scope = MemoryScope::Global;
+
+    let mut writer = dir.store()?;
+    let memory = new_memory("Inspect me", &scope);
+    writer.store(memory.clone())?;
+
+    let mut reader = MemoryStore::open_readonly(dir.db_path())?;
+    let read = reader.get(&memory.id, &scope)?.expect("memory visible");
+    assert_eq!(read.content, "Inspect me");
+    assert_eq!(read.access_count, 0, "reads are not counted");
+    assert_eq!(reader.list_all(&scope)?.len(), 1);
+    assert_eq!(reader.stats(&scope)?.total_memories, 1);
+
+    assert!(is_read_only_error(reader.store(memory.clone())));
+    assert!(is_read_only_error(reader.delete(&memory.id, &scope)));
+    assert!(is_read_only_error(reader.clear_session(None)));
+
+    // Unwrapped, the store still refuses every write
+    let mut inner = reader.into_inner();
+    assert!(inner.is_read_only());
+    assert!(is_read_only_error(inner.store(memory.clone())));
+    assert!(is_read_only_error(inner.force_delete(&memory.id, &scope)));
+    assert!(is_read_only_error(
+        inner.set_pinned(&memory.id, &scope, true)
+    ));
+    assert!(writer.get_without_tracking(&memory.id, &scope)?.is_some());
+
+    // Nothing is created for databases that do not exist
+    let missing = dir.join("missing").join("global.db");
+    let mut reader = MemoryStore::open_readonly(missing.clone())?;
+    assert!(reader.list_all(&scope)?.is_empty());
+    assert!(!missing.exists());
+
+    Ok(())
+}
+
+#[test]
+fn schema_requiring_author_rejects_memories_without_it() -> anyhow::Result<()> {
+    let dir = TestDir::new("schema");
+
+    let schema_path = dir.join("schema.json");
+    std::fs::write(
+        &schema_path,
+        json!({
+            "type": "object",
+            "required": ["author"],
+            "properties": {"author": {"type": "string"}}
+        })
+        .to_string(),
+    )?;
+
+    let mut store = dir.store()?;
+    store.load_metadata_schema(&schema_path)?;
+
+    let anonymous = MemoryMetadata::default();
+    let err = store
+        .store(Memory::new(
+            "No author".into(),
+            MemoryScope::Global,
+            anonymous.clone(),
+        ))
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MemoryStoreError>(),
+        Some(MemoryStoreError::ValidationError(_))
+    ));
+    assert!(store.validate_metadata(&anonymous).is_err());
+
+    let mut wrong_type = MemoryMetadata::default();
+    wrong_type.custom.insert("author".into(), json!(42));
+    assert!(store.validate_metadata(&wrong_type).is_err());
+
+    let mut signed = MemoryMetadata::default();
+    signed.custom.insert("author".into(), json!("ada"));
+    store.store(Memory::new("Signed".into(), MemoryScope::Global, signed))?;
+    assert_eq!(store.list_all(&MemoryScope::Global)?.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn watch_scope_reports_stores_and_deletes() -> anyhow::Result<()> {
+    let dir = TestDir::new("watch-scope");
+
+    let mut store = dir.store()?;
+    let global = store.watch_scope(&MemoryScope::Global)?;
+    let session = store.watch_scope(&MemoryScope::session())?;
+    tokio::pin!(global, session);
+
+    let memory = new_memory("Watched", &MemoryScope::Global);
+    store.store(memory.clone())?;
+    match timeout(WAIT, global.next()).await? {
+        Some(MemoryEvent::Stored(stored)) => assert_eq!(stored.id, memory.id),
+        other => panic!("expected Stored, got {:?}", other),
+    }
+
+    store.delete(&memory.id, &MemoryScope::Global)?;
+    match timeout(WAIT, global.next()).await? {
+        Some(MemoryEvent::Deleted(id)) => assert_eq!(id, memory.id),
+        other => panic!("expected Deleted, got {:?}", other),
+    }
+
+    // Other scopes' changes are not delivered
+    assert!(timeout(WAIT, session.next()).await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,313 @@
+use anyhow::{Context, Result};
+use lru::LruCache;
+use rag_core::config::{EmbedderConfig, EmbedderProvider};
+use rag_core::storage::MemoryStore;
+use rag_core::MemoryScope;
+use serde::Deserialize;
+use serde_json::json;
+use std::num::NonZeroUsize;
+use std::sync::OnceLock;
+use tracing::info;
+
+/// Default endpoint root for `OpenAIEmbedder`; `/v1/embeddings` is appended
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com";
+
+/// Default endpoint root for `OllamaEmbedder`; `/api/embeddings` is appended
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+/// Text to vector. Implementations are blocking and shareable across threads.
+pub trait Embedder: Send + Sync {
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Results follow input order; the default embeds one text at a time
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
+
+    /// Vector length; None until known (from the model name or a first response)
+    fn dimension(&self) -> Option<usize>;
+}
+
+/// Build the embedder selected by `config.provider`
+pub fn create_embedder(config: &EmbedderConfig) -> Result<Box<dyn Embedder>> {
+    match &config.provider {
+        EmbedderProvider::Local(path) => anyhow::bail!(
+            "Local embedding models are not supported yet ({}); use openai or ollama",
+            path.display()
+        ),
+        EmbedderProvider::OpenAI {
+            api_key_env,
+            model,
+            base_url,
+        } => {
+            let api_key = std::env::var(api_key_env)
+                .with_context(|| format!("Environment variable {} is not set", api_key_env))?;
+            Ok(Box::new(OpenAIEmbedder::new(
+                api_key,
+                model.clone(),
+                base_url.clone(),
+            )))
+        }
+        EmbedderProvider::Ollama { base_url, model } => Ok(Box::new(OllamaEmbedder::new(
+            model.clone(),
+            base_url.clone(),
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReindexReport {
+    pub total: usize,
+    pub embedded: usize,
+    /// Memories that already had an embedding (only without `force`)
+    pub skipped: usize,
+}
+
+/// Backfill embeddings for every memory in `scope`, `batch_size` texts per
+/// `embed_batch` call. Memories that already have one are skipped unless `force`.
+/// Logs progress as a percentage after each batch.
+pub fn reindex_embeddings(
+    store: &mut MemoryStore,
+    scope: &MemoryScope,
+    embedder: &dyn Embedder,
+    batch_size: usize,
+    force: bool,
+) -> Result<ReindexReport> {
+    let memories = store.list_all(scope)?;
+    let total = memories.len();
+    let pending: Vec<_> = memories
+        .into_iter()
+        .filter(|memory| force || memory.embedding.is_empty())
+        .collect();
+
+    let mut report = ReindexReport {
+        total,
+        skipped: total - pending.len(),
+        ..Default::default()
+    };
+
+    for batch in pending.chunks(batch_size.max(1)) {
+        let texts: Vec<&str> = batch.iter().map(|memory| memory.content.as_str()).collect();
+        let embeddings = embedder.embed_batch(&texts)?;
+
+        for (memory, embedding) in batch.iter().zip(embeddings) {
+            if store.update_embedding(&memory.id, scope, embedding)? {
+                report.embedded += 1;
+            }
+        }
+        info!(
+            "Reindexed {}/{} memories ({:.0}%)",
+            report.embedded,
+            pending.len(),
+            report.embedded as f64 * 100.0 / pending.len() as f64
+        );
+    }
+
+    Ok(report)
+}
+
+/// Least-recently-used map from `content_hash(content)` to its embedding, so identical
+/// content is embedded once. Persistence is the caller's job: `insert` reports evictions.
+pub struct EmbeddingCache {
+    entries: LruCache<String, Vec<f32>>,
+}
+
+impl EmbeddingCache {
+    /// `max_entries` of 0 is treated as 1
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: LruCache::new(NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN)),
+        }
+    }
+
+    /// Hex SHA-256 of `content`, the cache key
+    pub fn content_hash(content: &str) -> String {
+        rag_core::content_hash(content)
+    }
+
+    /// Marks the entry as recently used
+    pub fn get(&mut self, content_hash: &str) -> Option<Vec<f32>> {
+        self.entries.get(content_hash).cloned()
+    }
+
+    /// Returns the hash evicted to make room, if any
+    pub fn insert(&mut self, content_hash: String, embedding: Vec<f32>) -> Option<String> {
+        match self.entries.push(content_hash.clone(), embedding) {
+            Some((evicted, _)) if evicted != content_hash => Some(evicted),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Client for the OpenAI `/v1/embeddings` API and compatible servers (LM Studio, vLLM, ...)
+pub struct OpenAIEmbedder {
+    client: reqwest::blocking::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    dimension: OnceLock<usize>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIResponse {
+    data: Vec<OpenAIEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAIEmbedder {
+    /// `base_url` is the server root (e.g. `http://localhost:1234`), None for OpenAI itself
+    pub fn new(api_key: String, model: String, base_url: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| OPENAI_BASE_URL.to_string());
+
+        let dimension = OnceLock::new();
+        if let Some(known) = openai_dimension(&model) {
+            let _ = dimension.set(known);
+        }
+
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api_key,
+            model,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            dimension,
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+impl Embedder for OpenAIEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_batch(&[text])?
+            .pop()
+            .context("Embedding response has no data")
+    }
+
+    /// One request for all `texts`
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/v1/embeddings", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "model": self.model, "input": texts }))
+            .send()
+            .with_context(|| format!("Failed to reach {}", url))?;
+
+        let mut parsed: OpenAIResponse = read_json(response)?;
+        if parsed.data.len() != texts.len() {
+            anyhow::bail!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                parsed.data.len()
+            );
+        }
+
+        parsed.data.sort_by_key(|item| item.index);
+        let embeddings: Vec<Vec<f32>> =
+            parsed.data.into_iter().map(|item| item.embedding).collect();
+        if let Some(first) = embeddings.first() {
+            let _ = self.dimension.set(first.len());
+        }
+        Ok(embeddings)
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        self.dimension.get().copied()
+    }
+}
+
+/// Published output sizes of OpenAI embedding models
+fn openai_dimension(model: &str) -> Option<usize> {
+    match model {
+        "text-embedding-ada-002" | "text-embedding-3-small" => Some(1536),
+        "text-embedding-3-large" => Some(3072),
+        _ => None,
+    }
+}
+
+/// Client for a local Ollama server's `/api/embeddings` (one prompt per request)
+pub struct OllamaEmbedder {
+    client: reqwest::blocking::Client,
+    model: String,
+    base_url: String,
+    dimension: OnceLock<usize>,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    embedding: Vec<f32>,
+}
+
+impl OllamaEmbedder {
+    /// `model` such as `"nomic-embed-text"`; `base_url` defaults to `OLLAMA_BASE_URL`
+    pub fn new(model: String, base_url: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| OLLAMA_BASE_URL.to_string());
+
+        Self {
+            client: reqwest::blocking::Client::new(),
+            model,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            dimension: OnceLock::new(),
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+impl Embedder for OllamaEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let url = format!("{}/api/embeddings", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&json!({ "model": self.model, "prompt": text }))
+            .send()
+            .with_context(|| format!("Failed to reach {}", url))?;
+
+        let parsed: OllamaResponse = read_json(response)?;
+        if parsed.embedding.is_empty() {
+            anyhow::bail!(
+                "Ollama returned an empty embedding for model {}",
+                self.model
+            );
+        }
+
+        let _ = self.dimension.set(parsed.embedding.len());
+        Ok(parsed.embedding)
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        self.dimension.get().copied()
+    }
+}
+
+/// Decode a JSON body, turning non-2xx statuses into errors carrying the response text
+fn read_json<T: serde::de::DeserializeOwned>(response: reqwest::blocking::Response) -> Result<T> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        anyhow::bail!("Embedding request failed ({}): {}", status, body);
+    }
+
+    response
+        .json()
+        .context("Failed to parse embedding response")
+}
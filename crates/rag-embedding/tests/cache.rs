@@ -0,0 +1,22 @@
+use rag_embedding::EmbeddingCache;
+
+#[test]
+fn embedding_cache_evicts_least_recently_used() {
+    let mut cache = EmbeddingCache::new(2);
+    let a = EmbeddingCache::content_hash("a");
+    let b = EmbeddingCache::content_hash("b");
+    let c = EmbeddingCache::content_hash("c");
+    assert_eq!(a.len(), 64);
+    assert_eq!(a, EmbeddingCache::content_hash("a"));
+
+    assert_eq!(cache.insert(a.clone(), vec![1.0]), None);
+    assert_eq!(cache.insert(b.clone(), vec![2.0]), None);
+    // Touch `a` so `b` becomes the eviction candidate
+    assert_eq!(cache.get(&a), Some(vec![1.0]));
+    assert_eq!(cache.insert(c.clone(), vec![3.0]), Some(b.clone()));
+
+    assert_eq!(cache.get(&b), None);
+    assert_eq!(cache.len(), 2);
+    // Re-inserting an existing key replaces it without evicting
+    assert_eq!(cache.insert(c, vec![4.0]), None);
+}
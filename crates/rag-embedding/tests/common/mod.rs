@@ -0,0 +1,64 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+/// Request seen by `mock_server`
+pub struct RecordedRequest {
+    /// Request line, e.g. `POST /v1/embeddings HTTP/1.1`
+    pub request_line: String,
+    /// Header lines, lowercased names
+    pub headers: Vec<String>,
+    pub body: String,
+}
+
+/// Serve exactly one HTTP request on a free localhost port, answering `200 OK` with
+/// `response_body`. Returns the base URL and a handle yielding the recorded request.
+pub fn mock_server(response_body: &str) -> (String, JoinHandle<RecordedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let response_body = response_body.to_string();
+
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+
+        let mut headers = Vec::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':').unwrap();
+            let name = name.to_lowercase();
+            if name == "content-length" {
+                content_length = value.trim().parse().unwrap();
+            }
+            headers.push(format!("{}:{}", name, value));
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response_body.len(),
+            response_body
+        )
+        .unwrap();
+
+        RecordedRequest {
+            request_line: request_line.trim_end().to_string(),
+            headers,
+            body: String::from_utf8(body).unwrap(),
+        }
+    });
+
+    (url, handle)
+}
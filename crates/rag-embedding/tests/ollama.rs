@@ -0,0 +1,43 @@
+mod common;
+
+use rag_core::config::{EmbedderConfig, EmbedderProvider};
+use rag_embedding::create_embedder;
+use serde_json::{json, Value};
+
+#[test]
+fn ollama_embed_uses_prompt_request_format() -> anyhow::Result<()> {
+    let (url, server) = common::mock_server(r#"{"embedding": [0.25, -0.5, 0.75]}"#);
+    let embedder = create_embedder(&EmbedderConfig {
+        provider: EmbedderProvider::Ollama {
+            base_url: Some(url),
+            model: "nomic-embed-text".to_string(),
+        },
+    })?;
+    assert_eq!(embedder.dimension(), None);
+
+    let embedding = embedder.embed("hello ollama")?;
+    assert_eq!(embedding, vec![0.25, -0.5, 0.75]);
+    assert_eq!(embedder.dimension(), Some(3));
+
+    let request = server.join().unwrap();
+    assert_eq!(request.request_line, "POST /api/embeddings HTTP/1.1");
+    assert!(request
+        .headers
+        .iter()
+        .any(|h| h == "content-type: application/json"));
+    let body: Value = serde_json::from_str(&request.body)?;
+    assert_eq!(
+        body,
+        json!({"model": "nomic-embed-text", "prompt": "hello ollama"})
+    );
+
+    Ok(())
+}
+
+#[test]
+fn create_embedder_rejects_local_models() {
+    let config = EmbedderConfig {
+        provider: EmbedderProvider::Local("/models/bert".into()),
+    };
+    assert!(create_embedder(&config).is_err());
+}
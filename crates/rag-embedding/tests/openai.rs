@@ -0,0 +1,41 @@
+mod common;
+
+use rag_embedding::{Embedder, OpenAIEmbedder};
+use serde_json::Value;
+
+const FIXTURE: &str = r#"{
+    "object": "list",
+    "data": [
+        {"object": "embedding", "index": 1, "embedding": [0.5, -0.25]},
+        {"object": "embedding", "index": 0, "embedding": [0.125, 1.0]}
+    ],
+    "model": "text-embedding-3-small",
+    "usage": {"prompt_tokens": 4, "total_tokens": 4}
+}"#;
+
+#[test]
+fn openai_embed_batch_posts_and_parses_fixture() -> anyhow::Result<()> {
+    let (url, server) = common::mock_server(FIXTURE);
+    let embedder = OpenAIEmbedder::new(
+        "test-key".to_string(),
+        "text-embedding-3-small".to_string(),
+        Some(format!("{}/", url)),
+    );
+
+    let embeddings = embedder.embed_batch(&["first", "second"])?;
+    // Response items arrive out of order; results follow input order
+    assert_eq!(embeddings, vec![vec![0.125, 1.0], vec![0.5, -0.25]]);
+
+    let request = server.join().unwrap();
+    assert_eq!(request.request_line, "POST /v1/embeddings HTTP/1.1");
+    assert!(request
+        .headers
+        .iter()
+        .any(|h| h == "authorization: Bearer test-key"));
+
+    let body: Value = serde_json::from_str(&request.body)?;
+    assert_eq!(body["model"], "text-embedding-3-small");
+    assert_eq!(body["input"], serde_json::json!(["first", "second"]));
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use rag_core::storage::MemoryStore;
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+use rag_embedding::{reindex_embeddings, Embedder};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Embeds each text as `[len]` and counts `embed_batch` calls
+struct LengthEmbedder {
+    batches: AtomicUsize,
+}
+
+impl Embedder for LengthEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        Ok(vec![text.len() as f32])
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.batches.fetch_add(1, Ordering::SeqCst);
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+#[test]
+fn reindex_fills_missing_embeddings() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("rag-embedding-reindex-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    let mut store = MemoryStore::new(dir.join("global.db"))?;
+    let scope = MemoryScope::Global;
+
+    let mut memories: Vec<Memory> = (0..5)
+        .map(|i| Memory::new("x".repeat(i + 1), scope.clone(), MemoryMetadata::default()))
+        .collect();
+    memories[0].embedding = vec![42.0];
+    store.batch_store(memories)?;
+
+    let embedder = LengthEmbedder {
+        batches: AtomicUsize::new(0),
+    };
+    let report = reindex_embeddings(&mut store, &scope, &embedder, 2, false)?;
+    assert_eq!((report.total, report.embedded, report.skipped), (5, 4, 1));
+    assert_eq!(embedder.batches.load(Ordering::SeqCst), 2);
+
+    for memory in store.list_all(&scope)? {
+        let expected = if memory.content.len() == 1 {
+            42.0
+        } else {
+            memory.content.len() as f32
+        };
+        assert_eq!(memory.embedding, vec![expected], "{}", memory.content);
+    }
+
+    let report = reindex_embeddings(&mut store, &scope, &embedder, 2, true)?;
+    assert_eq!(report.embedded, 5);
+    assert!(store
+        .list_all(&scope)?
+        .iter()
+        .all(|m| m.embedding == vec![m.content.len() as f32]));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
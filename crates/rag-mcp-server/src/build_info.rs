@@ -0,0 +1,24 @@
+//! Version, git commit and build time captured by `build.rs`
+
+mod built_info {
+    include!(concat!(env!("OUT_DIR"), "/built.rs"));
+}
+
+pub const VERSION: &str = built_info::PKG_VERSION;
+pub const TARGET: &str = built_info::TARGET;
+pub const BUILD_TIME: &str = built_info::BUILT_TIME_UTC;
+
+/// Short commit hash, `unknown` when built outside a git checkout
+pub fn git_sha() -> &'static str {
+    built_info::GIT_COMMIT_HASH_SHORT.unwrap_or("unknown")
+}
+
+/// Everything above as the JSON printed by `rag-mcp version --json`
+pub fn to_json() -> serde_json::Value {
+    serde_json::json!({
+        "version": VERSION,
+        "git_sha": git_sha(),
+        "build_time": BUILD_TIME,
+        "target": TARGET,
+    })
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::stream::{self, Stream};
+use serde_json::Value;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, error, info, warn};
+
+use crate::mcp::{JsonRpcMessage, JsonRpcResponse, McpErrorCode};
+use crate::server::{timeout_response, McpServer, RequestHandler};
+
+/// Shared state for all HTTP connections: one server instance behind an async lock
+#[derive(Clone)]
+struct HttpState {
+    server: Arc<Mutex<McpServer>>,
+    request_timeout: Duration,
+}
+
+/// Run the MCP server over HTTP:
+/// - `POST /mcp`        JSON-RPC request in body, JSON-RPC response in body
+/// - `GET /mcp/events`  SSE stream of `notifications/*` emitted by tool calls
+/// - `GET /metrics`     Prometheus text exposition
+pub fn serve(server: McpServer, host: &str, port: u16) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start tokio runtime")?;
+    runtime.block_on(serve_async(server, host, port))
+}
+
+async fn serve_async(server: McpServer, host: &str, port: u16) -> Result<()> {
+    let server = Arc::new(Mutex::new(server));
+    let state = HttpState {
+        request_timeout: server.lock().await.request_timeout(),
+        server: server.clone(),
+    };
+
+    let app = Router::new()
+        .route("/mcp", post(handle_rpc))
+        .route("/mcp/events", get(handle_events))
+        .route("/metrics", get(handle_metrics))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind((host, port))
+        .await
+        .with_context(|| format!("Failed to bind {}:{}", host, port))?;
+    info!("MCP HTTP server listening on {}", listener.local_addr()?);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Shutdown signal received, exiting gracefully");
+        })
+        .await?;
+
+    let mut server = server.lock().await;
+    server.shutdown()
+}
+
+async fn handle_rpc(State(state): State<HttpState>, body: String) -> Response {
+    debug!("Received: {}", body);
+
+    let request = match JsonRpcMessage::parse(&body) {
+        Ok(JsonRpcMessage::Request(request)) => request,
+        Ok(JsonRpcMessage::BatchRequest(requests)) => {
+            let responses = run_with_timeout(&state, move |server| server.handle_batch(requests))
+                .await
+                .unwrap_or_else(|| vec![timeout_response(None)]);
+            if responses.is_empty() {
+                return StatusCode::ACCEPTED.into_response();
+            }
+            return Json(responses).into_response();
+        }
+        Err(e) => {
+            error!("Failed to parse request: {}", e);
+            let response = JsonRpcResponse::error(
+                None,
+                McpErrorCode::ParseError,
+                format!("Parse error: {}", e),
+            );
+            return Json(response).into_response();
+        }
+    };
+
+    // Client notifications get an empty 202, mirroring the silent ignore on stdio
+    if request.id.is_none() && request.method.starts_with("notifications/") {
+        debug!("Received notification: {}", request.method);
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    let id = request.id.clone();
+    let response = run_with_timeout(&state, move |server| server.handle_request(request))
+        .await
+        .unwrap_or_else(|| timeout_response(id));
+    Json(response).into_response()
+}
+
+/// Run `handler` on the blocking pool: tool calls block on SQLite and embedding HTTP
+/// calls. None when it outlives the request timeout; it keeps the server locked until
+/// it finishes, so later requests wait (and may time out) behind it.
+async fn run_with_timeout<T, F>(state: &HttpState, handler: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut McpServer) -> T + Send + 'static,
+{
+    let deadline = tokio::time::Instant::now() + state.request_timeout;
+    let mut server = tokio::time::timeout_at(deadline, state.server.clone().lock_owned())
+        .await
+        .ok()?;
+    let task = tokio::task::spawn_blocking(move || handler(&mut server));
+    match tokio::time::timeout_at(deadline, task).await {
+        Ok(Ok(output)) => Some(output),
+        Ok(Err(e)) => {
+            error!("Request handler panicked: {}", e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+async fn handle_events(
+    State(state): State<HttpState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.server.lock().await.subscribe_events();
+
+    let stream = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    let data = serde_json::to_string(&notification)
+                        .unwrap_or_else(|_| Value::Null.to_string());
+                    return Some((Ok(Event::default().data(data)), receiver));
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("SSE client lagged, skipped {} notifications", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn handle_metrics(State(state): State<HttpState>) -> Response {
+    match state.server.lock().await.metrics_text() {
+        Ok(text) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text).into_response(),
+        Err(e) => {
+            error!("Failed to collect metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Prompt sent by `summarize_scope`; the memories follow, separated by `---`
+pub const SUMMARY_PROMPT: &str = "Summarize the following memories in 3-5 sentences:";
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// One user message to an OpenAI-compatible chat completions `endpoint` (the full
+/// URL, e.g. `https://api.openai.com/v1/chat/completions`). Blocking; returns the
+/// first choice's text. Without `api_key` no Authorization header is sent.
+pub fn complete(
+    endpoint: &str,
+    api_key: Option<&str>,
+    model: &str,
+    prompt: &str,
+) -> Result<String> {
+    let mut request = reqwest::blocking::Client::new()
+        .post(endpoint)
+        .json(&json!({
+            "model": model,
+            "messages": [{ "role": "user", "content": prompt }]
+        }));
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let response = request
+        .send()
+        .with_context(|| format!("Failed to reach {}", endpoint))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        anyhow::bail!("LLM request failed ({}): {}", status, body);
+    }
+
+    let parsed: ChatResponse = response.json().context("Failed to parse LLM response")?;
+    parsed
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message.content)
+        .context("LLM response has no choices")
+}
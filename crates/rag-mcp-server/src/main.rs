@@ -1,19 +1,33 @@
+mod build_info;
+mod http;
+mod llm;
 mod mcp;
+mod metrics;
 mod server;
+mod watch;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
-use rag_core::{config::Config, storage::MemoryStore, Memory, MemoryMetadata, MemoryScope};
+use rag_core::config::{Config, LogFormat, ServerConfig};
+use rag_core::storage::{DedupKeepPolicy, MemoryStore};
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
 use rag_search::BM25SearchEngine;
 use server::McpServer;
-use std::path::PathBuf;
-use tracing::{error, info};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 #[derive(Parser)]
 #[command(name = "rag-mcp")]
 #[command(about = "RAG MCP Server for Zed/Claude Code", long_about = None)]
 struct Cli {
+    /// Config file to use instead of the default location
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -21,7 +35,18 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Run MCP server (stdio)
-    Serve,
+    Serve {
+        /// Open databases read-only; tools that write return an error
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Run MCP server over HTTP (POST /mcp, SSE at GET /mcp/events, GET /metrics)
+    ServeHttp {
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        #[arg(long, default_value = "8080")]
+        port: u16,
+    },
     /// Add memory
     Add {
         #[arg(long)]
@@ -42,6 +67,9 @@ enum Commands {
         scope: String,
         #[arg(long)]
         project_path: Option<PathBuf>,
+        /// Open databases read-only
+        #[arg(long)]
+        read_only: bool,
     },
     /// List memories
     List {
@@ -60,68 +88,317 @@ enum Commands {
         #[arg(long)]
         project_path: Option<PathBuf>,
     },
+    /// Check stored records decode correctly, optionally deleting corrupt ones
+    Verify {
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+        /// Delete records that fail verification
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Upgrade a scope's database to the current storage format
+    Migrate {
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+    },
+    /// Rebuild a scope's database to reclaim space left by deletes
+    #[command(alias = "vacuum")]
+    Compact {
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+    },
+    /// Delete memories whose content is stored more than once, keeping one copy each
+    Dedup {
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+        /// Which copy survives: oldest, newest, or highest_importance
+        #[arg(long, default_value = "oldest")]
+        keep: String,
+        /// Report what would be deleted without deleting
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write a consistent copy of a scope's database to a new file
+    Backup {
+        dest: PathBuf,
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+    },
+    /// Replace a scope's memories with a backup
+    Restore {
+        source: PathBuf,
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+        /// Overwrite a scope that still holds memories
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import the memories of a database written by the original SQLite store
+    MigrateFromSqlite {
+        sqlite_path: PathBuf,
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+    },
+    /// Compute embeddings for memories stored before an embedder was configured
+    ReindexEmbeddings {
+        #[arg(long, default_value = "global")]
+        scope: String,
+        #[arg(long)]
+        project_path: Option<PathBuf>,
+        /// Texts per embedding request
+        #[arg(long, default_value_t = 32)]
+        batch_size: usize,
+        /// Re-embed memories that already have an embedding
+        #[arg(long)]
+        force: bool,
+    },
+    /// Watch a directory and re-ingest files as they change
+    Watch {
+        directory: PathBuf,
+        /// Memory scope; project scope uses the watched directory as project path
+        #[arg(long, default_value = "project")]
+        scope: String,
+        /// Only files whose relative path matches one of these globs
+        #[arg(long)]
+        glob: Vec<String>,
+        #[arg(long, default_value_t = 500)]
+        debounce_ms: u64,
+    },
+    /// Check config and global database access; prints JSON, exits 1 on failure
+    Health,
+    /// Print version, git commit, build time and target
+    Version {
+        /// Machine-readable JSON instead of one line of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a commented config file with every default
+    Init {
+        /// Where to write; defaults to --config, then the default config path
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
     /// Show statistics
     Stats {
         #[arg(long, default_value = "global")]
         scope: String,
         #[arg(long)]
         project_path: Option<PathBuf>,
+        /// Also count memories by tag, language, source file and AST node type
+        #[arg(long)]
+        extended: bool,
     },
 }
 
-fn init_tracing(stderr_only: bool) {
-    if stderr_only {
-        // Disable tracing for MCP server to keep stdio clean
-        return;
-    }
+fn init_tracing(config: &ServerConfig, stderr_only: bool) -> Result<()> {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| format!("rag_mcp={}", config.log_level).into());
+
+    // Disable stderr tracing for MCP server to keep stdio clean; a log file still works
+    let stderr = (!stderr_only).then(|| fmt_layer(config.log_format, std::io::stderr, true));
+
+    let file = match &config.log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            Some(fmt_layer(config.log_format, Mutex::new(file), false))
+        }
+        None => None,
+    };
 
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "rag_mcp=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(filter)
+        .with(stderr)
+        .with(file)
         .init();
+
+    Ok(())
+}
+
+fn fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).boxed(),
+    }
 }
 
+/// `--scope project --project-path <path>` or any form `MemoryScope::from_str` accepts
 fn parse_scope(scope: &str, project_path: Option<PathBuf>) -> Result<MemoryScope> {
-    match scope {
-        "session" => Ok(MemoryScope::Session),
-        "global" => Ok(MemoryScope::Global),
-        "project" => {
-            let path = project_path
-                .ok_or_else(|| anyhow::anyhow!("project_path required for project scope"))?;
-            Ok(MemoryScope::Project { path })
-        }
-        _ => anyhow::bail!("Invalid scope: {}. Use session, project, or global", scope),
+    let scope = match (scope, project_path) {
+        ("project", Some(path)) => MemoryScope::Project { path },
+        _ => scope.parse()?,
+    };
+    Ok(scope.normalize())
+}
+
+/// `--config` when given, otherwise the default config location; `RAG_MCP_*`
+/// environment variables override both
+fn load_config(path: Option<&Path>) -> Result<Config> {
+    let mut config = match path {
+        Some(path) => Config::load_from(path)?,
+        None => Config::load()?,
+    };
+    config.apply_env_overrides()?;
+    Ok(config)
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM/SIGHUP on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let (Ok(mut term), Ok(mut hup)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+        ) else {
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+            _ = hup.recv() => {}
+        }
     }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // For serve mode, send logs to stderr to keep stdout clean for JSON-RPC
-    let stderr_only = matches!(cli.command, Commands::Serve);
-    init_tracing(stderr_only);
+    // For serve mode, keep stdio clean for JSON-RPC
+    let stderr_only = matches!(cli.command, Commands::Serve { .. });
+    let config_path = cli.config.as_deref();
+    // `init` must work while the config it replaces is missing or broken
+    let tracing_config = match cli.command {
+        Commands::Init { .. } => Config::default(),
+        _ => load_config(config_path)?,
+    };
+    init_tracing(&tracing_config.server, stderr_only)?;
 
     match cli.command {
-        Commands::Serve => {
+        Commands::Serve { read_only } => {
             info!("MCP server starting, PID: {}", std::process::id());
-            let config = Config::load()?;
+            let config = load_config(config_path)?;
             info!("Config loaded successfully");
-            let mut server = McpServer::new(config)?;
+            let server = if read_only {
+                McpServer::new_read_only(config)?
+            } else {
+                McpServer::new(config)?
+            }
+            .with_config_path(cli.config.clone());
             info!("Server initialized, entering stdio loop");
-            server.run()?;
+            let runtime =
+                tokio::runtime::Runtime::new().context("Failed to start tokio runtime")?;
+            runtime.block_on(async {
+                let (trigger, shutdown) = tokio::sync::oneshot::channel();
+                tokio::spawn(async move {
+                    shutdown_signal().await;
+                    let _ = trigger.send(());
+                });
+                server.run_with_shutdown(shutdown).await
+            })?;
+            info!("Server shutting down normally");
+        }
+        Commands::ServeHttp { host, port } => {
+            info!("MCP HTTP server starting, PID: {}", std::process::id());
+            let config = load_config(config_path)?;
+            let server = McpServer::new(config)?.with_config_path(cli.config.clone());
+            http::serve(server, &host, port)?;
             info!("Server shutting down normally");
         }
+        Commands::ReindexEmbeddings {
+            scope,
+            project_path,
+            batch_size,
+            force,
+        } => {
+            let config = load_config(config_path)?;
+            let embedder_config = config
+                .embedder
+                .as_ref()
+                .context("No [embedder] configured in config.toml")?;
+            let embedder = rag_embedding::create_embedder(embedder_config)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+
+            let report = rag_embedding::reindex_embeddings(
+                &mut store,
+                &scope,
+                embedder.as_ref(),
+                batch_size,
+                force,
+            )?;
+            info!(
+                "Embedded {} of {} memories ({} already had embeddings)",
+                report.embedded, report.total, report.skipped
+            );
+        }
+        Commands::Watch {
+            directory,
+            scope,
+            glob,
+            debounce_ms,
+        } => {
+            let directory = directory
+                .canonicalize()
+                .with_context(|| format!("Not a directory: {}", directory.display()))?;
+            let scope = parse_scope(&scope, Some(directory.clone()))?;
+            let server =
+                McpServer::new(load_config(config_path)?)?.with_config_path(cli.config.clone());
+            watch::watch(
+                server,
+                &directory,
+                &scope,
+                &glob,
+                Duration::from_millis(debounce_ms),
+            )?;
+        }
         Commands::Add {
             content,
             scope,
             tags,
             project_path,
         } => {
-            let config = Config::load()?;
+            let config = load_config(config_path)?;
             let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            store.set_max_content_length(config.storage.max_content_length);
+            store.set_quotas(
+                config.storage.max_project_memories,
+                config.storage.global_memory_quota,
+            );
+            store.check_content_length(&content)?;
+            if let Some(schema) = &config.storage.custom_metadata_schema {
+                store.load_metadata_schema(schema)?;
+            }
             let scope = parse_scope(&scope, project_path)?;
 
             let metadata = MemoryMetadata {
@@ -140,19 +417,23 @@ fn main() -> Result<()> {
             k,
             scope,
             project_path,
+            read_only,
         } => {
-            let config = Config::load()?;
-            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let config = load_config(config_path)?;
             let scope = parse_scope(&scope, project_path)?;
 
-            let memories = store.list_all(&scope)?;
+            let memories = if read_only {
+                MemoryStore::open_readonly(config.storage.global_db_path)?.list_all(&scope)?
+            } else {
+                MemoryStore::new(config.storage.global_db_path)?.list_all(&scope)?
+            };
             let mut search = BM25SearchEngine::new();
 
             for memory in &memories {
                 search.index_memory(memory);
             }
 
-            let results = search.search(&query, &memories, k);
+            let results = search.search(&query, &memories, k, config.search.min_score);
 
             if results.is_empty() {
                 info!("No results found");
@@ -171,7 +452,7 @@ fn main() -> Result<()> {
             limit,
             project_path,
         } => {
-            let config = Config::load()?;
+            let config = load_config(config_path)?;
             let mut store = MemoryStore::new(config.storage.global_db_path)?;
             let scope = parse_scope(&scope, project_path)?;
 
@@ -194,7 +475,7 @@ fn main() -> Result<()> {
             scope,
             project_path,
         } => {
-            let config = Config::load()?;
+            let config = load_config(config_path)?;
             let mut store = MemoryStore::new(config.storage.global_db_path)?;
             let scope = parse_scope(&scope, project_path)?;
 
@@ -205,16 +486,222 @@ fn main() -> Result<()> {
                 error!("Memory {} not found", id);
             }
         }
+        Commands::Verify {
+            scope,
+            project_path,
+            repair,
+        } => {
+            let config = load_config(config_path)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+
+            let report = store.verify_integrity(&scope)?;
+
+            println!("{:<10} {:>8}", "Total", report.total);
+            println!("{:<10} {:>8}", "Valid", report.valid);
+            println!("{:<10} {:>8}", "Corrupt", report.corrupt.len());
+            for id in &report.corrupt {
+                println!("  corrupt: {}", id);
+            }
+
+            if repair && !report.corrupt.is_empty() {
+                let mut removed = 0;
+                for id in &report.corrupt {
+                    if store.force_delete(id, &scope)? {
+                        removed += 1;
+                    }
+                }
+                info!("Deleted {} corrupt records", removed);
+            }
+            if repair {
+                info!("Memory count rebuilt: {}", store.rebuild_counts(&scope)?);
+            }
+        }
+        Commands::Migrate {
+            scope,
+            project_path,
+        } => {
+            let config = load_config(config_path)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+
+            // Opening the database applies schema migrations
+            let version = store.storage_version(&scope)?;
+            let rewritten = store.migrate(&scope)?;
+            info!(
+                "Storage at version {}, rewrote {} records",
+                version, rewritten
+            );
+        }
+        Commands::Compact {
+            scope,
+            project_path,
+        } => {
+            let config = load_config(config_path)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+
+            let report = store.compact(&scope)?;
+            info!(
+                "Compacted {} records: {} -> {} bytes",
+                report.records_copied, report.old_size_bytes, report.new_size_bytes
+            );
+        }
+        Commands::Dedup {
+            scope,
+            project_path,
+            keep,
+            dry_run,
+        } => {
+            let config = load_config(config_path)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+            let keep: DedupKeepPolicy = keep.parse()?;
+
+            let report = if dry_run {
+                store.dedup_preview(&scope, keep)?
+            } else {
+                store.dedup(&scope, keep)?
+            };
+            println!("{:<10} {:>8}", "Groups", report.groups_found);
+            let label = if dry_run { "To delete" } else { "Deleted" };
+            println!("{:<10} {:>8}", label, report.memories_deleted);
+            println!("{:<10} {:>8}", "Bytes", report.bytes_freed);
+        }
+        Commands::Backup {
+            dest,
+            scope,
+            project_path,
+        } => {
+            let config = load_config(config_path)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+
+            let report = store.backup(&scope, &dest)?;
+            info!(
+                "Backed up {} to {} at {}: {} file(s), {} bytes",
+                scope,
+                dest.display(),
+                report.timestamp.to_rfc3339(),
+                report.files_copied,
+                report.total_bytes
+            );
+        }
+        Commands::Restore {
+            source,
+            scope,
+            project_path,
+            force,
+        } => {
+            let config = load_config(config_path)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+
+            let existing = store.count(&scope)?;
+            if existing > 0 && !force {
+                bail!(
+                    "{} holds {} memories; pass --force to replace them",
+                    scope,
+                    existing
+                );
+            }
+            let restored = store.restore(&source, &scope)?;
+            info!("Restored {} memories into {}", restored, scope);
+        }
+        Commands::MigrateFromSqlite {
+            sqlite_path,
+            scope,
+            project_path,
+        } => {
+            let config = load_config(config_path)?;
+            let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            let scope = parse_scope(&scope, project_path)?;
+            let migrated = store.migrate_from_sqlite(&sqlite_path, &scope)?;
+            info!("Migrated {} memories into {}", migrated, scope);
+        }
         Commands::Stats {
             scope,
             project_path,
+            extended,
         } => {
-            let config = Config::load()?;
+            let config = load_config(config_path)?;
             let mut store = MemoryStore::new(config.storage.global_db_path)?;
+            store.set_extended_stats(extended || config.storage.extended_stats_enabled);
             let scope = parse_scope(&scope, project_path)?;
 
             let stats = store.stats(&scope)?;
+            info!("Scope: {}", stats.scope);
             info!("Total memories: {}", stats.total_memories);
+            info!("Total accesses: {}", stats.total_accesses);
+            if let Some(id) = stats.most_accessed_id {
+                info!("Most accessed: {}", id);
+            }
+            if let Some(importance) = stats.importance {
+                info!(
+                    "Importance: min {:.2}, max {:.2}, mean {:.2}, p90 {:.2}",
+                    importance.min, importance.max, importance.mean, importance.percentile_90
+                );
+            }
+            for (id, score) in stats.top_important {
+                info!("Important: {} ({:.2})", id, score);
+            }
+            if let Some(extended) = stats.extended {
+                for line in server::format_extended_stats(&extended).lines() {
+                    info!("{}", line);
+                }
+            }
+        }
+        Commands::Health => {
+            let checked = load_config(config_path).and_then(|config| {
+                let mut store = MemoryStore::new(config.storage.global_db_path)?;
+                store.stats(&MemoryScope::Global)
+            });
+            match checked {
+                Ok(stats) => println!(
+                    "{}",
+                    serde_json::json!({
+                        "status": "ok",
+                        "global_memories": stats.total_memories,
+                    })
+                ),
+                Err(e) => {
+                    println!(
+                        "{}",
+                        serde_json::json!({ "status": "error", "error": e.to_string() })
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Version { json } => {
+            if json {
+                println!("{}", build_info::to_json());
+            } else {
+                println!(
+                    "rag-mcp {} ({} built {}) {}",
+                    build_info::VERSION,
+                    build_info::git_sha(),
+                    build_info::BUILD_TIME,
+                    build_info::TARGET
+                );
+            }
+        }
+        Commands::Init { output, force } => {
+            let path = output
+                .or_else(|| cli.config.clone())
+                .unwrap_or_else(Config::config_path);
+            if path.exists() && !force {
+                bail!(
+                    "{} already exists; pass --force to overwrite it",
+                    path.display()
+                );
+            }
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, Config::default_toml())
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("Wrote {}", path.display());
         }
     }
 
@@ -1,24 +1,118 @@
+use rag_core::Memory;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// JSON-RPC request id: a string or a number, echoed back exactly as received
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RequestId(pub Value);
+
+impl PartialEq for RequestId {
+    /// Strings compare as strings; numbers by value, so `1` equals `1.0` but not `"1"`
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+                (Some(a), Some(b)) => a == b,
+                _ => a.as_f64() == b.as_f64(),
+            },
+            (a, b) => a == b,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
     pub jsonrpc: String,
-    pub id: Option<Value>,
+    pub id: Option<RequestId>,
     pub method: String,
     pub params: Option<Value>,
 }
 
+/// One line (stdio) or body (HTTP) from the client: a request or a JSON-RPC batch
+#[derive(Debug, Clone)]
+pub enum JsonRpcMessage {
+    Request(JsonRpcRequest),
+    BatchRequest(Vec<JsonRpcRequest>),
+}
+
+impl JsonRpcMessage {
+    /// Parse a request or, when the JSON is an array, a batch of requests
+    pub fn parse(text: &str) -> serde_json::Result<Self> {
+        let value: Value = serde_json::from_str(text)?;
+        if value.is_array() {
+            serde_json::from_value(value).map(Self::BatchRequest)
+        } else {
+            serde_json::from_value(value).map(Self::Request)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcResponse {
     pub jsonrpc: String,
-    pub id: Option<Value>,
+    pub id: Option<RequestId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<JsonRpcError>,
 }
 
+/// Server-initiated message without an id; clients must not reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Value,
+}
+
+impl JsonRpcNotification {
+    pub fn new(method: String, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method,
+            params,
+        }
+    }
+}
+
+/// JSON-RPC error codes: the standard ones plus the server-defined -320xx range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum McpErrorCode {
+    ParseError = -32700,
+    InvalidRequest = -32600,
+    MethodNotFound = -32601,
+    InvalidParams = -32602,
+    InternalError = -32603,
+    ToolNotFound = -32000,
+    InvalidScope = -32001,
+    MemoryNotFound = -32002,
+    RateLimited = -32003,
+}
+
+impl From<McpErrorCode> for i32 {
+    fn from(code: McpErrorCode) -> Self {
+        code as i32
+    }
+}
+
+/// Error carrying the code it should be reported with; anything else is an internal error
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct McpError {
+    pub code: McpErrorCode,
+    pub message: String,
+}
+
+impl McpError {
+    pub fn new(code: McpErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcError {
     pub code: i32,
@@ -28,7 +122,7 @@ pub struct JsonRpcError {
 }
 
 impl JsonRpcResponse {
-    pub fn success(id: Option<Value>, result: Value) -> Self {
+    pub fn success(id: Option<RequestId>, result: Value) -> Self {
         Self {
             jsonrpc: "2.0".to_string(),
             id,
@@ -37,13 +131,13 @@ impl JsonRpcResponse {
         }
     }
 
-    pub fn error(id: Option<Value>, code: i32, message: String) -> Self {
+    pub fn error(id: Option<RequestId>, code: McpErrorCode, message: String) -> Self {
         Self {
             jsonrpc: "2.0".to_string(),
             id,
             result: None,
             error: Some(JsonRpcError {
-                code,
+                code: code.into(),
                 message,
                 data: None,
             }),
@@ -68,3 +162,52 @@ pub struct Resource {
     #[serde(rename = "mimeType")]
     pub mime_type: String,
 }
+
+/// Memory as listed in structured tool responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemorySummary {
+    pub id: String,
+    pub content_preview: String,
+    pub tags: Vec<String>,
+    pub pinned: bool,
+}
+
+/// `json` content item of `search_memory`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResultResponse {
+    pub results: Vec<SearchResultItem>,
+    /// Memories in the searched scope
+    pub total_searched: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResultItem {
+    pub id: String,
+    pub score: f32,
+    pub rank: usize,
+    pub content_preview: String,
+    pub tags: Vec<String>,
+}
+
+/// `json` content item of `store_memory`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreMemoryResponse {
+    pub id: String,
+    pub scope: String,
+    /// For `find_by_hash`
+    pub content_hash: String,
+}
+
+/// `json` content item of `list_memories`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListMemoriesResponse {
+    pub memories: Vec<MemorySummary>,
+    /// Memories matching the request across all pages
+    pub total: usize,
+}
+
+/// `json` content item of `get_memory`; `memory` is null when not found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetMemoryResponse {
+    pub memory: Option<Memory>,
+}
@@ -0,0 +1,68 @@
+use prometheus::{
+    register_histogram, register_int_counter_vec, register_int_gauge_vec, Encoder, Histogram,
+    IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use std::sync::LazyLock;
+
+// All metrics live in the prometheus default registry and are rendered by `GET /metrics`
+
+pub static MEMORIES_TOTAL: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "rag_mcp_memories_total",
+        "Stored memories per scope (session, global, project)",
+        &["scope"]
+    )
+    .unwrap()
+});
+
+pub static SEARCH_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "rag_mcp_search_duration_seconds",
+        "Time spent in search_memory"
+    )
+    .unwrap()
+});
+
+pub static STORE_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "rag_mcp_store_duration_seconds",
+        "Time spent in store_memory"
+    )
+    .unwrap()
+});
+
+pub static SEARCH_RESULTS_RETURNED: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "rag_mcp_search_results_returned",
+        "Results returned per search_memory call",
+        vec![0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0]
+    )
+    .unwrap()
+});
+
+pub static REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "rag_mcp_requests_total",
+        "JSON-RPC requests handled, by method",
+        &["method"]
+    )
+    .unwrap()
+});
+
+/// Register every metric so each family shows up before its first observation
+pub fn init() {
+    LazyLock::force(&MEMORIES_TOTAL);
+    LazyLock::force(&SEARCH_DURATION);
+    LazyLock::force(&STORE_DURATION);
+    LazyLock::force(&SEARCH_RESULTS_RETURNED);
+    LazyLock::force(&REQUESTS_TOTAL);
+}
+
+/// Prometheus text exposition of the default registry
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
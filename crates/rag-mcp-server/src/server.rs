@@ -1,145 +1,521 @@
 use anyhow::{Context, Result};
-use rag_core::{config::Config, storage::MemoryStore, Memory, MemoryMetadata, MemoryScope};
-use rag_search::BM25SearchEngine;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use governor::clock::Clock;
+use governor::{DefaultDirectRateLimiter, DefaultKeyedRateLimiter, Quota, RateLimiter};
+use rag_chunking::{detect_content_language, detect_language, SemanticChunker};
+use rag_core::{
+    config::{Config, FramingMode, RateLimitConfig},
+    storage::{
+        DedupKeepPolicy, ExtendedStats, MemorySort, MemoryStore, MemoryStoreError, TagMatchMode,
+    },
+    Memory, MemoryMetadata, MemoryScope, ScopeKey, SearchResult,
+};
+use rag_embedding::{create_embedder, Embedder, EmbeddingCache};
+use rag_search::{highlight_tokens, score_with_decay, BM25SearchEngine};
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use tracing::{debug, error, info};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+use walkdir::WalkDir;
 
-use crate::mcp::{JsonRpcRequest, JsonRpcResponse, Tool};
+use crate::build_info;
+use crate::llm;
+use crate::mcp::{
+    GetMemoryResponse, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    ListMemoriesResponse, McpError, McpErrorCode, MemorySummary, RequestId, SearchResultItem,
+    SearchResultResponse, StoreMemoryResponse, Tool,
+};
+use crate::metrics;
 
-static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// How often the stdio loop wakes without input to check its stop flag
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Capacity of the notification broadcast channel; slow subscribers skip lagged events
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+const DEFAULT_MERGE_SEPARATOR: &str = "\n\n---\n\n";
+
+/// `get_context` block format; placeholders: `{id}`, `{score}`, `{content}`, `{tags}`
+const DEFAULT_CONTEXT_TEMPLATE: &str =
+    "<memory id=\"{id}\" score=\"{score}\">\n{content}\n</memory>";
+
+/// Rough token estimate used by `get_context` truncation
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Memories `summarize_scope` sends to the LLM unless `max_memories` says otherwise
+const DEFAULT_SUMMARY_MEMORIES: usize = 50;
+
+/// Upper bound `set_importance` clamps scores to
+const MAX_IMPORTANCE_SCORE: f32 = 10.0;
+
+/// Resource names are the first characters of the memory content
+const RESOURCE_NAME_CHARS: usize = 60;
+
+/// `content_preview` length in structured tool responses
+const CONTENT_PREVIEW_CHARS: usize = 200;
+
+/// Delivers server-initiated `notifications/*` messages to a transport's client(s).
+/// Payloads: `notifications/memory_stored` `{ id, scope, tags }`,
+/// `notifications/memory_deleted` `{ id, scope }`.
+pub trait NotificationSender: Send {
+    fn send_notification(&self, method: &str, params: Value);
+}
+
+/// SSE: every connected `/mcp/events` stream receives the notification
+impl NotificationSender for broadcast::Sender<JsonRpcNotification> {
+    fn send_notification(&self, method: &str, params: Value) {
+        // send() only fails when nobody is subscribed
+        let _ = self.send(JsonRpcNotification::new(method.to_string(), params));
+    }
+}
+
+/// Stdio: notifications are queued while a request is handled and written to stdout
+/// before that request's response
+#[derive(Clone, Default)]
+pub struct StdioNotifications {
+    queue: Arc<Mutex<Vec<JsonRpcNotification>>>,
+}
+
+impl StdioNotifications {
+    fn take(&self) -> Vec<JsonRpcNotification> {
+        std::mem::take(&mut *self.queue.lock().unwrap())
+    }
+}
+
+impl NotificationSender for StdioNotifications {
+    fn send_notification(&self, method: &str, params: Value) {
+        self.queue
+            .lock()
+            .unwrap()
+            .push(JsonRpcNotification::new(method.to_string(), params));
+    }
+}
+
+/// Work for the stdio request thread: one request or a JSON-RPC batch
+enum Job {
+    Request(JsonRpcRequest),
+    Batch(Vec<JsonRpcRequest>),
+}
+
+/// Owns the server on a dedicated thread so the stdio loop can stop waiting for a
+/// request that outlives the timeout. A timed-out request keeps running; later
+/// requests queue behind it and its late response is dropped.
+struct RequestWorker {
+    jobs: mpsc::Sender<(u64, Job)>,
+    thread: std::thread::JoinHandle<()>,
+    responses: mpsc::Receiver<(u64, Vec<JsonRpcResponse>)>,
+    next_seq: u64,
+    timeout: Duration,
+}
+
+impl RequestWorker {
+    fn spawn(mut server: McpServer, timeout: Duration) -> Self {
+        let (jobs, job_rx) = mpsc::channel::<(u64, Job)>();
+        let (response_tx, responses) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            for (seq, job) in job_rx {
+                let output = match job {
+                    Job::Request(request) => vec![server.handle_request(request)],
+                    Job::Batch(requests) => server.handle_batch(requests),
+                };
+                if response_tx.send((seq, output)).is_err() {
+                    break;
+                }
+            }
+            if let Err(e) = server.shutdown() {
+                error!("Failed to flush storage on shutdown: {}", e);
+            }
+        });
+
+        Self {
+            jobs,
+            thread,
+            responses,
+            next_seq: 0,
+            timeout,
+        }
+    }
+
+    /// Responses for `job`, or None when it did not finish within the timeout
+    fn call(&mut self, job: Job) -> Option<Vec<JsonRpcResponse>> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.jobs.send((seq, job)).ok()?;
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            match self.responses.recv_timeout(remaining) {
+                Ok((done, output)) if done == seq => return Some(output),
+                // Late answer to a request that already timed out
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Stop taking jobs and wait for the server to finish the queued ones and flush
+    fn finish(self) {
+        drop(self.jobs);
+        if self.thread.join().is_err() {
+            error!("Request thread panicked");
+        }
+    }
+}
+
+/// Read messages from `reader` on a separate thread, so the stdio loop can check its
+/// stop flag while no input arrives. The channel ends after EOF or a read error.
+fn spawn_reader(
+    mut reader: impl BufRead + Send + 'static,
+    framing: FramingMode,
+) -> mpsc::Receiver<std::io::Result<Option<String>>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || loop {
+        let message = read_message(&mut reader, framing);
+        let done = !matches!(message, Ok(Some(_)));
+        if tx.send(message).is_err() || done {
+            break;
+        }
+    });
+    rx
+}
+
+/// Transport-independent JSON-RPC dispatch, shared by the stdio and HTTP transports
+pub trait RequestHandler {
+    fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse;
+
+    /// Handle a JSON-RPC batch in order. Requests without an id are notifications:
+    /// they are processed (except client `notifications/*`) but get no response element.
+    fn handle_batch(&mut self, requests: Vec<JsonRpcRequest>) -> Vec<JsonRpcResponse> {
+        if requests.is_empty() {
+            return vec![JsonRpcResponse::error(
+                None,
+                McpErrorCode::InvalidRequest,
+                "Invalid Request: empty batch".to_string(),
+            )];
+        }
+
+        let mut responses = Vec::new();
+        for request in requests {
+            if request.id.is_none() {
+                debug!("Received notification: {}", request.method);
+                if !request.method.starts_with("notifications/") {
+                    self.handle_request(request);
+                }
+                continue;
+            }
+            responses.push(self.handle_request(request));
+        }
+        responses
+    }
+}
+
+/// Token bucket(s) guarding `tools/call`, built from `[server.rate_limit]`
+enum ToolRateLimiter {
+    Global(DefaultDirectRateLimiter),
+    PerTool(DefaultKeyedRateLimiter<String>),
+}
+
+impl ToolRateLimiter {
+    fn new(config: &RateLimitConfig) -> Result<Self> {
+        let rate = config.requests_per_second;
+        if !rate.is_finite() || rate <= 0.0 {
+            anyhow::bail!(
+                "rate_limit.requests_per_second must be positive, got {}",
+                rate
+            );
+        }
+        let burst = config
+            .burst
+            .unwrap_or(rate.ceil() as u64)
+            .clamp(1, u32::MAX as u64);
+        let quota = Quota::with_period(Duration::from_secs_f64(1.0 / rate))
+            .context("rate_limit.requests_per_second is too high")?
+            .allow_burst(NonZeroU32::new(burst as u32).expect("burst is at least 1"));
+
+        Ok(if config.per_tool {
+            Self::PerTool(RateLimiter::keyed(quota))
+        } else {
+            Self::Global(RateLimiter::direct(quota))
+        })
+    }
+
+    /// Take a token for `tool`, or fail with how long until the next one
+    fn check(&self, tool: &str) -> std::result::Result<(), McpError> {
+        let (denied, clock) = match self {
+            Self::Global(limiter) => (limiter.check().err(), limiter.clock()),
+            Self::PerTool(limiter) => (limiter.check_key(&tool.to_string()).err(), limiter.clock()),
+        };
+        match denied {
+            None => Ok(()),
+            Some(not_until) => {
+                let wait = not_until.wait_time_from(clock.now());
+                Err(McpError::new(
+                    McpErrorCode::RateLimited,
+                    format!(
+                        "Rate limit exceeded, retry after {}ms",
+                        wait.as_millis().max(1)
+                    ),
+                ))
+            }
+        }
+    }
+}
 
 pub struct McpServer {
     config: Config,
+    /// Where `configure_search` persists `config`; the default location when None
+    config_path: Option<PathBuf>,
     store: MemoryStore,
-    search: BM25SearchEngine,
+    /// `custom_metadata_schema` as loaded at startup; advertised for `store_memory`'s `custom`
+    custom_schema: Option<Value>,
+    /// Configured, empty engine that per-scope indices start from
+    search_template: BM25SearchEngine,
+    /// BM25 statistics per scope; merged on demand for cross-scope search
+    search_indices: HashMap<ScopeKey, BM25SearchEngine>,
+    /// Indices changed since `save_search_indices` last persisted them
+    unsaved_indices: HashSet<ScopeKey>,
+    /// None when no `[embedder]` is configured: memories are stored without embeddings
+    embedder: Option<Box<dyn Embedder>>,
+    embedding_cache: EmbeddingCache,
+    events: broadcast::Sender<JsonRpcNotification>,
+    /// Where `notify` delivers; the SSE broadcast unless `run` switches to stdio
+    notifier: Box<dyn NotificationSender>,
+    rate_limiter: Option<ToolRateLimiter>,
 }
 
 impl McpServer {
     pub fn new(config: Config) -> Result<Self> {
         let store = MemoryStore::new(config.storage.global_db_path.clone())?;
-        let search = BM25SearchEngine::new();
+        Self::with_store(config, store)
+    }
+
+    /// Server over `MemoryStore::open_readonly`: reads work, tools that write fail
+    pub fn new_read_only(config: Config) -> Result<Self> {
+        let store = MemoryStore::open_readonly(config.storage.global_db_path.clone())?;
+        Self::with_store(config, store.into_inner())
+    }
 
-        Ok(Self {
+    fn with_store(config: Config, mut store: MemoryStore) -> Result<Self> {
+        store.set_regex_max_matches(config.search.regex_max_matches);
+        store.set_regex_timeout(Duration::from_millis(config.search.regex_timeout_ms));
+        store.set_max_content_length(config.storage.max_content_length);
+        store.set_quotas(
+            config.storage.max_project_memories,
+            config.storage.global_memory_quota,
+        );
+        store.set_extended_stats(config.storage.extended_stats_enabled);
+        if let Some(schema) = &config.storage.custom_metadata_schema {
+            store.load_metadata_schema(schema)?;
+        }
+        let custom_schema = store.metadata_schema().cloned();
+        let search_template = BM25SearchEngine::new_with_config(&config.search)?;
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        metrics::init();
+
+        let embedder = config.embedder.as_ref().map(create_embedder).transpose()?;
+        let max_entries = config.storage.embedding_cache_max_entries;
+        let mut embedding_cache = EmbeddingCache::new(max_entries);
+        if embedder.is_some() {
+            for (hash, embedding) in store.load_embedding_cache(max_entries)? {
+                embedding_cache.insert(hash, embedding);
+            }
+        }
+
+        let rate_limiter = config
+            .server
+            .rate_limit
+            .as_ref()
+            .map(ToolRateLimiter::new)
+            .transpose()?;
+
+        let mut server = Self {
             config,
+            config_path: None,
             store,
-            search,
-        })
+            custom_schema,
+            search_template,
+            search_indices: HashMap::new(),
+            unsaved_indices: HashSet::new(),
+            embedder,
+            embedding_cache,
+            notifier: Box::new(events.clone()),
+            events,
+            rate_limiter,
+        };
+        // Global memories are searched from most sessions; warm their index up front
+        server.load_search_index(&MemoryScope::Global)?;
+        Ok(server)
     }
 
-    fn setup_signal_handlers() -> Result<()> {
-        #[cfg(unix)]
-        {
-            // Handle SIGTERM, SIGINT, SIGHUP
-            let signals = [
-                signal_hook::consts::SIGTERM,
-                signal_hook::consts::SIGINT,
-                signal_hook::consts::SIGHUP,
-            ];
-
-            for signal in signals {
-                unsafe {
-                    signal_hook::low_level::register(signal, || {
-                        SHUTDOWN.store(true, Ordering::Relaxed);
-                    })?;
-                }
+    /// Persist config changes to `path` instead of the default location
+    pub fn with_config_path(mut self, path: Option<PathBuf>) -> Self {
+        self.config_path = path;
+        self
+    }
+
+    /// Subscribe to `notifications/*` events emitted by tool calls (used by SSE clients)
+    pub fn subscribe_events(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.events.subscribe()
+    }
+
+    /// Refresh the per-scope memory gauges and render all metrics in Prometheus format
+    pub fn metrics_text(&mut self) -> Result<String> {
+        let mut session = 0;
+        for name in self.store.list_sessions() {
+            session += self.store.count(&MemoryScope::named_session(name))?;
+        }
+        let global = self.store.stats(&MemoryScope::Global)?.total_memories;
+        let mut project = 0;
+        for path in self.store.list_known_projects()? {
+            if MemoryStore::project_db_exists(&path) {
+                project += self
+                    .store
+                    .stats(&MemoryScope::Project { path })?
+                    .total_memories;
             }
         }
 
-        Ok(())
+        for (scope, count) in [
+            ("session", session),
+            ("global", global),
+            ("project", project),
+        ] {
+            metrics::MEMORIES_TOTAL
+                .with_label_values(&[scope])
+                .set(count as i64);
+        }
+
+        Ok(metrics::render())
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        info!("Starting MCP server on stdio");
+    fn notify(&self, method: &str, params: Value) {
+        self.notifier.send_notification(method, params);
+    }
+
+    /// How long a transport waits for `handle_request` before answering with an error
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.config.server.request_timeout_ms)
+    }
+
+    /// Persist unsaved search indices and flush storage; called once the server stops
+    pub fn shutdown(&mut self) -> Result<()> {
+        self.save_search_indices()?;
+        self.store.flush()
+    }
+
+    /// Serve stdio until `shutdown` fires, EOF, or a read error. Waits for the request
+    /// in progress to finish and for storage to be flushed before returning.
+    pub async fn run_with_shutdown(self, shutdown: oneshot::Receiver<()>) -> Result<()> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+        let mut serve = tokio::task::spawn_blocking(move || self.run_until(flag));
 
-        // Setup signal handlers for graceful shutdown
-        Self::setup_signal_handlers()?;
+        let result = tokio::select! {
+            result = &mut serve => result,
+            _ = shutdown => {
+                info!("Shutdown signal received, exiting gracefully");
+                stop.store(true, Ordering::Relaxed);
+                serve.await
+            }
+        };
+        result.context("stdio loop panicked")?
+    }
 
-        let stdin = std::io::stdin();
-        let mut reader = BufReader::new(stdin.lock());
+    /// Serve stdio until `stop` is set, EOF, or a read error
+    pub fn run_until(mut self, stop: Arc<AtomicBool>) -> Result<()> {
+        info!("Starting MCP server on stdio");
+
+        let framing = self.config.server.transport_framing;
+        let messages = spawn_reader(BufReader::new(std::io::stdin()), framing);
         let mut stdout = std::io::stdout();
+        let notifications = StdioNotifications::default();
+        self.notifier = Box::new(notifications.clone());
+        let timeout = self.request_timeout();
+        let mut worker = RequestWorker::spawn(self, timeout);
 
         loop {
-            // Check for shutdown signal
-            if SHUTDOWN.load(Ordering::Relaxed) {
-                info!("Shutdown signal received, exiting gracefully");
+            if stop.load(Ordering::Relaxed) {
+                info!("Stop requested, leaving stdio loop");
                 break;
             }
 
-            let mut line = String::new();
-            match reader.read_line(&mut line) {
-                Ok(0) => {
+            let message = match messages.recv_timeout(STOP_POLL_INTERVAL) {
+                Ok(Ok(Some(message))) => message,
+                Ok(Ok(None)) | Err(mpsc::RecvTimeoutError::Disconnected) => {
                     info!("EOF received, shutting down");
                     break;
                 }
-                Ok(_) => {
-                    let line = line.trim();
-                    if line.is_empty() {
-                        continue;
-                    }
+                Ok(Err(e)) => {
+                    error!("Failed to read message: {}", e);
+                    break;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            };
+            let message = message.trim();
+            if message.is_empty() {
+                continue;
+            }
 
-                    debug!("Received: {}", line);
-
-                    match serde_json::from_str::<JsonRpcRequest>(line) {
-                        Ok(request) => {
-                            // Handle notifications (no response needed)
-                            if request.id.is_none() {
-                                debug!("Received notification: {}", request.method);
-                                if request.method.starts_with("notifications/") {
-                                    // Silently ignore notifications
-                                    continue;
-                                }
-                            }
+            debug!("Received: {}", message);
 
-                            // Handle requests (response needed)
-                            let response = self.handle_request(request);
-                            let response_str = serde_json::to_string(&response)?;
-                            writeln!(stdout, "{}", response_str)?;
-                            stdout.flush()?;
-                        }
-                        Err(e) => {
-                            error!("Failed to parse request: {}", e);
-                            let response =
-                                JsonRpcResponse::error(None, -32700, format!("Parse error: {}", e));
-                            let response_str = serde_json::to_string(&response)?;
-                            writeln!(stdout, "{}", response_str)?;
-                            stdout.flush()?;
+            match JsonRpcMessage::parse(message) {
+                Ok(JsonRpcMessage::BatchRequest(requests)) => {
+                    let responses = worker
+                        .call(Job::Batch(requests))
+                        .unwrap_or_else(|| vec![timeout_response(None)]);
+                    write_notifications(&mut stdout, framing, &notifications)?;
+                    // A batch of notifications only is answered with nothing at all
+                    if !responses.is_empty() {
+                        let responses_str = serde_json::to_string(&responses)?;
+                        write_message(&mut stdout, framing, &responses_str)?;
+                    }
+                }
+                Ok(JsonRpcMessage::Request(request)) => {
+                    // Handle notifications (no response needed)
+                    if request.id.is_none() {
+                        debug!("Received notification: {}", request.method);
+                        if request.method.starts_with("notifications/") {
+                            // Silently ignore notifications
+                            continue;
                         }
                     }
+
+                    // Handle requests (response needed)
+                    let id = request.id.clone();
+                    let response = worker
+                        .call(Job::Request(request))
+                        .and_then(|mut responses| responses.pop())
+                        .unwrap_or_else(|| timeout_response(id));
+                    write_notifications(&mut stdout, framing, &notifications)?;
+                    let response_str = serde_json::to_string(&response)?;
+                    write_message(&mut stdout, framing, &response_str)?;
                 }
                 Err(e) => {
-                    error!("Failed to read line: {}", e);
-                    break;
+                    error!("Failed to parse request: {}", e);
+                    let response = JsonRpcResponse::error(
+                        None,
+                        McpErrorCode::ParseError,
+                        format!("Parse error: {}", e),
+                    );
+                    let response_str = serde_json::to_string(&response)?;
+                    write_message(&mut stdout, framing, &response_str)?;
                 }
             }
         }
 
+        worker.finish();
         Ok(())
     }
 
-    fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
-        debug!("Handling method: {}", request.method);
-
-        let result = match request.method.as_str() {
-            "initialize" => self.handle_initialize(request.params),
-            "tools/list" => self.handle_tools_list(),
-            "tools/call" => self.handle_tools_call(request.params),
-            "resources/list" => self.handle_resources_list(),
-            "resources/read" => self.handle_resources_read(request.params),
-            _ => Err(anyhow::anyhow!("Method not found: {}", request.method)),
-        };
-
-        match result {
-            Ok(value) => JsonRpcResponse::success(request.id, value),
-            Err(e) => {
-                error!("Error handling request: {}", e);
-                JsonRpcResponse::error(request.id, -32603, format!("Internal error: {}", e))
-            }
-        }
-    }
-
     fn handle_initialize(&self, _params: Option<Value>) -> Result<Value> {
         Ok(json!({
             "protocolVersion": "2024-11-05",
@@ -149,13 +525,15 @@ impl McpServer {
             },
             "serverInfo": {
                 "name": "rag-mcp",
-                "version": "0.1.0"
+                "version": build_info::VERSION,
+                "gitSha": build_info::git_sha(),
+                "buildTime": build_info::BUILD_TIME
             }
         }))
     }
 
     fn handle_tools_list(&self) -> Result<Value> {
-        let tools = vec![
+        let mut tools = vec![
             Tool {
                 name: "store_memory".to_string(),
                 description: "Store new memory with metadata".to_string(),
@@ -176,6 +554,18 @@ impl McpServer {
                         "project_path": {
                             "type": "string",
                             "description": "Project path (required for project scope)"
+                        },
+                        "session_name": {
+                            "type": "string",
+                            "description": "Named session for session scope; the default session when omitted"
+                        },
+                        "override_language": {
+                            "type": "string",
+                            "description": "Language to record instead of the auto-detected one"
+                        },
+                        "custom": {
+                            "type": "object",
+                            "description": "Free-form metadata, validated against custom_metadata_schema when configured"
                         }
                     },
                     "required": ["content", "scope"]
@@ -201,6 +591,128 @@ impl McpServer {
                         "project_path": {
                             "type": "string",
                             "description": "Project path (required for project scope)"
+                        },
+                        "session_name": {
+                            "type": "string",
+                            "description": "Named session for session scope; the default session when omitted"
+                        },
+                        "use_importance_boost": {
+                            "type": "boolean",
+                            "description": "Scale scores by importance_score (defaults to config)"
+                        },
+                        "time_decay_override": {
+                            "type": "boolean",
+                            "description": "Enable or disable time decay for this call (defaults to config)"
+                        },
+                        "min_score": {
+                            "type": "number",
+                            "description": "Drop BM25 matches scoring below this (defaults to config)"
+                        },
+                        "filter_tags": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Only memories carrying all of these tags (bm25 mode)"
+                        },
+                        "created_after": {
+                            "type": "string",
+                            "description": "RFC 3339 timestamp; only memories created at or after it (bm25 mode)"
+                        },
+                        "created_before": {
+                            "type": "string",
+                            "description": "RFC 3339 timestamp; only memories created before it (bm25 mode)"
+                        },
+                        "min_importance_score": {
+                            "type": "number",
+                            "description": "Only memories with at least this importance_score (bm25 mode)"
+                        },
+                        "search_mode": {
+                            "type": "string",
+                            "enum": ["bm25", "regex"],
+                            "default": "bm25",
+                            "description": "regex: treat query as a pattern, rank by match count"
+                        },
+                        "mode": {
+                            "type": "string",
+                            "enum": ["bm25", "regex"],
+                            "description": "Alias of search_mode"
+                        }
+                    },
+                    "required": ["query", "scope"]
+                }),
+            },
+            Tool {
+                name: "cross_scope_search".to_string(),
+                description: "Search session, project and global scopes at once and merge results"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {"type": "string", "description": "Search query"},
+                        "k": {
+                            "type": "integer",
+                            "description": "Number of results per scope and in the merged list",
+                            "default": 5
+                        },
+                        "project_path": {
+                            "type": "string",
+                            "description": "Project path (project scope is skipped when absent)"
+                        },
+                        "weights": {
+                            "type": "object",
+                            "description": "Score multipliers per scope (default 1.0)",
+                            "properties": {
+                                "session": {"type": "number"},
+                                "project": {"type": "number"},
+                                "global": {"type": "number"}
+                            }
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            },
+            Tool {
+                name: "search_project_tree".to_string(),
+                description: "Search every project scope at or below a directory".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "root_path": {"type": "string", "description": "Directory containing the projects"},
+                        "query": {"type": "string", "description": "Search query"},
+                        "k": {"type": "integer", "description": "Number of results", "default": 5}
+                    },
+                    "required": ["root_path", "query"]
+                }),
+            },
+            Tool {
+                name: "get_context".to_string(),
+                description:
+                    "Search memories and format the best matches as a prompt-ready context block"
+                        .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {"type": "string", "description": "Search query"},
+                        "scope": {
+                            "type": "string",
+                            "enum": ["session", "project", "global"],
+                            "description": "Memory scope to search"
+                        },
+                        "k": {
+                            "type": "integer",
+                            "description": "Number of memories to include",
+                            "default": 5
+                        },
+                        "project_path": {
+                            "type": "string",
+                            "description": "Project path (required for project scope)"
+                        },
+                        "max_tokens": {
+                            "type": "integer",
+                            "description": "Truncate the context to roughly this many tokens (4 chars per token)"
+                        },
+                        "template": {
+                            "type": "string",
+                            "description": "Per-memory template with {id}, {score}, {content}, {tags} placeholders"
                         }
                     },
                     "required": ["query", "scope"]
@@ -215,14 +727,27 @@ impl McpServer {
                         "scope": {"type": "string", "enum": ["session", "project", "global"]},
                         "limit": {"type": "integer", "default": 50},
                         "offset": {"type": "integer", "default": 0},
+                        "after_id": {
+                            "type": "string",
+                            "description": "Cursor: continue after this ID (next_cursor of the previous page)"
+                        },
+                        "sort_by": {
+                            "type": "string",
+                            "enum": ["created_desc", "access_count_desc", "last_accessed_desc"],
+                            "default": "created_desc"
+                        },
+                        "filter_pinned": {
+                            "type": "boolean",
+                            "description": "Only pinned (true) or only unpinned (false) memories"
+                        },
                         "project_path": {"type": "string"}
                     },
                     "required": ["scope"]
                 }),
             },
             Tool {
-                name: "delete_memory".to_string(),
-                description: "Delete memory by ID".to_string(),
+                name: "get_memory".to_string(),
+                description: "Fetch one memory by ID (counts as an access)".to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -234,194 +759,1863 @@ impl McpServer {
                 }),
             },
             Tool {
-                name: "clear_session".to_string(),
-                description: "Clear all session memories".to_string(),
+                name: "search_by_tag".to_string(),
+                description: "List memories carrying the given tags, newest first (no ranking)"
+                    .to_string(),
                 input_schema: json!({
                     "type": "object",
-                    "properties": {}
+                    "properties": {
+                        "tags": {"type": "array", "items": {"type": "string"}},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "match_mode": {
+                            "type": "string",
+                            "enum": ["all", "any"],
+                            "default": "all",
+                            "description": "Require every tag, or at least one"
+                        },
+                        "limit": {"type": "integer", "default": 50},
+                        "offset": {"type": "integer", "default": 0},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["tags", "scope"]
                 }),
             },
-        ];
-
-        Ok(json!({ "tools": tools }))
-    }
-
-    fn handle_tools_call(&mut self, params: Option<Value>) -> Result<Value> {
-        let params = params.context("Missing params")?;
-        let name = params["name"].as_str().context("Missing tool name")?;
-        let arguments = &params["arguments"];
-
-        match name {
-            "store_memory" => self.tool_store_memory(arguments),
-            "search_memory" => self.tool_search_memory(arguments),
-            "list_memories" => self.tool_list_memories(arguments),
-            "delete_memory" => self.tool_delete_memory(arguments),
-            "clear_session" => self.tool_clear_session(),
-            _ => Err(anyhow::anyhow!("Unknown tool: {}", name)),
-        }
-    }
-
-    fn tool_store_memory(&mut self, args: &Value) -> Result<Value> {
-        let content = args["content"].as_str().context("Missing content")?;
-        let scope_str = args["scope"].as_str().context("Missing scope")?;
-        let tags: Vec<String> = args["tags"]
-            .as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str().map(String::from))
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        let scope = match scope_str {
-            "session" => MemoryScope::Session,
-            "global" => MemoryScope::Global,
-            "project" => {
-                let path = args["project_path"]
-                    .as_str()
-                    .context("Missing project_path for project scope")?;
-                MemoryScope::Project {
-                    path: PathBuf::from(path),
-                }
-            }
-            _ => return Err(anyhow::anyhow!("Invalid scope: {}", scope_str)),
-        };
-
-        let metadata = MemoryMetadata {
-            tags,
-            ..Default::default()
-        };
-
-        let memory = Memory::new(content.to_string(), scope, metadata);
-        let id = memory.id.clone();
-
-        self.search.index_memory(&memory);
-        self.store.store(memory)?;
-
+            Tool {
+                name: "bulk_tag".to_string(),
+                description: "Add and remove tags on every memory matching a query and/or tag filter"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {"type": "string", "description": "BM25 query selecting memories"},
+                        "filter_tags": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Only memories carrying all of these tags"
+                        },
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "add_tags": {"type": "array", "items": {"type": "string"}},
+                        "remove_tags": {"type": "array", "items": {"type": "string"}},
+                        "dry_run": {
+                            "type": "boolean",
+                            "default": false,
+                            "description": "Only list the IDs that would change"
+                        },
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["scope"]
+                }),
+            },
+            Tool {
+                name: "bulk_untag".to_string(),
+                description: "Remove tags from every memory matching a query and/or tag filter"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {"type": "string", "description": "BM25 query selecting memories"},
+                        "filter_tags": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Only memories carrying all of these tags"
+                        },
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "remove_tags": {"type": "array", "items": {"type": "string"}},
+                        "dry_run": {"type": "boolean", "default": false},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["scope", "remove_tags"]
+                }),
+            },
+            Tool {
+                name: "rename_tag".to_string(),
+                description: "Rename a tag on every memory in a scope".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "old_tag": {"type": "string"},
+                        "new_tag": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["old_tag", "new_tag", "scope"]
+                }),
+            },
+            Tool {
+                name: "delete_tag".to_string(),
+                description: "Remove a tag from every memory in a scope".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "tag": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["tag", "scope"]
+                }),
+            },
+            Tool {
+                name: "delete_memory".to_string(),
+                description: "Delete memory by ID".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"},
+                        "force": {
+                            "type": "boolean",
+                            "description": "Delete even if the memory is pinned"
+                        }
+                    },
+                    "required": ["id", "scope"]
+                }),
+            },
+            Tool {
+                name: "pin_memory".to_string(),
+                description: "Pin a memory so it survives clear_session and plain deletes"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["id", "scope"]
+                }),
+            },
+            Tool {
+                name: "set_importance".to_string(),
+                description: "Set a memory's importance score (0-10), which boosts its search ranking"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "importance_score": {"type": "number", "minimum": 0, "maximum": 10},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["id", "scope", "importance_score"]
+                }),
+            },
+            Tool {
+                name: "unpin_memory".to_string(),
+                description: "Remove a memory's pin".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["id", "scope"]
+                }),
+            },
+            Tool {
+                name: "find_by_source".to_string(),
+                description: "List the memories ingested from a file, in chunk order".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "source_file": {"type": "string", "description": "Path as it was ingested"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["source_file", "scope"]
+                }),
+            },
+            Tool {
+                name: "find_by_hash".to_string(),
+                description: "Fetch the memory with the given content hash (the oldest of identical copies)"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "hash": {"type": "string", "description": "Hex SHA-256 of the content, as reported by store_memory"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["hash", "scope"]
+                }),
+            },
+            Tool {
+                name: "random_memories".to_string(),
+                description: "Return a random sample of memories from a scope, e.g. for review"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "k": {"type": "integer", "default": 5, "description": "Sample size"},
+                        "seed": {"type": "integer", "description": "Fixed seed for a reproducible sample"},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["scope"]
+                }),
+            },
+            Tool {
+                name: "get_document".to_string(),
+                description: "Reassemble an ingested document from its chunks, in order".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string", "description": "The chunks' parent_id"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["document_id", "scope"]
+                }),
+            },
+            Tool {
+                name: "get_ancestors".to_string(),
+                description: "Follow parent_id links up from a memory; lists the chain from the root down to it"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["id", "scope"]
+                }),
+            },
+            Tool {
+                name: "get_children".to_string(),
+                description: "List every memory below one through parent_id links, nearest generation first"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string", "description": "Parent memory (or document) ID"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["id", "scope"]
+                }),
+            },
+            Tool {
+                name: "reingest_file".to_string(),
+                description: "Replace a file's chunks with freshly chunked current content".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "file_path": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "tags": {"type": "array", "items": {"type": "string"}},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["file_path", "scope"]
+                }),
+            },
+            Tool {
+                name: "ingest_directory".to_string(),
+                description: "Chunk and store all matching files under a directory".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "directory_path": {"type": "string", "description": "Directory to ingest"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "glob_patterns": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Globs relative to directory_path, e.g. [\"**/*.rs\"] (default: all files)"
+                        },
+                        "tags": {"type": "array", "items": {"type": "string"}},
+                        "recursive": {"type": "boolean", "default": true},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["directory_path", "scope"]
+                }),
+            },
+            Tool {
+                name: "move_memory".to_string(),
+                description: "Move a memory to another scope (e.g. session -> global)".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "from_scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "to_scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["id", "from_scope", "to_scope"]
+                }),
+            },
+            Tool {
+                name: "copy_memory".to_string(),
+                description: "Copy a memory into another scope, keeping the original".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "from_scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "to_scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["id", "from_scope", "to_scope"]
+                }),
+            },
+            Tool {
+                name: "dedup_memories".to_string(),
+                description: "Delete memories whose exact content is stored more than once, keeping one copy (and any pinned ones)"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"},
+                        "keep": {
+                            "type": "string",
+                            "enum": ["oldest", "newest", "highest_importance"],
+                            "default": "oldest"
+                        },
+                        "dry_run": {"type": "boolean", "default": false, "description": "Report what would be deleted without deleting"}
+                    },
+                    "required": ["scope"]
+                }),
+            },
+            Tool {
+                name: "merge_memories".to_string(),
+                description: "Merge 2-10 memories into one, deleting the originals".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "ids": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "minItems": 2,
+                            "maxItems": 10
+                        },
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"},
+                        "merged_content_separator": {
+                            "type": "string",
+                            "default": "\n\n---\n\n"
+                        },
+                        "tags": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Tags added to the union of the sources' tags"
+                        }
+                    },
+                    "required": ["ids", "scope"]
+                }),
+            },
+            Tool {
+                name: "split_memory".to_string(),
+                description: "Split a memory at byte offsets into several memories".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"},
+                        "split_at": {
+                            "type": "array",
+                            "items": {"type": "integer", "minimum": 1},
+                            "description": "Increasing byte offsets inside the content"
+                        }
+                    },
+                    "required": ["id", "scope", "split_at"]
+                }),
+            },
+            Tool {
+                name: "delete_scope".to_string(),
+                description: "Delete ALL memories in a scope (requires confirm: true)".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"},
+                        "confirm": {
+                            "type": "boolean",
+                            "description": "Must be true; guards against accidental wipes"
+                        }
+                    },
+                    "required": ["scope", "confirm"]
+                }),
+            },
+            Tool {
+                name: "get_stats".to_string(),
+                description: "Memory count and access statistics for a scope".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["scope"]
+                }),
+            },
+            Tool {
+                name: "get_extended_stats".to_string(),
+                description: "Memory counts of a scope by tag, language, source file and AST node type (scans every memory)"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["scope"]
+                }),
+            },
+            Tool {
+                name: "list_projects".to_string(),
+                description: "List known project scopes with their memory counts".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
+            Tool {
+                name: "configure_search".to_string(),
+                description: "Change stop words and BM25 parameters at runtime and rebuild the search indices"
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "add_stop_words": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Words to ignore when indexing and searching"
+                        },
+                        "remove_stop_words": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Stop words to make searchable again (built-in ones too)"
+                        },
+                        "set_k1": {"type": "number", "description": "BM25 term frequency saturation"},
+                        "set_b": {"type": "number", "description": "BM25 length normalization (0-1)"},
+                        "persist": {
+                            "type": "boolean",
+                            "description": "Also write the changes to config.toml (default: false)"
+                        }
+                    }
+                }),
+            },
+            Tool {
+                name: "summarize_scope".to_string(),
+                description: "Summarize the most recent memories of a scope with an LLM".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "scope": {"type": "string", "enum": ["session", "project", "global"]},
+                        "max_memories": {"type": "integer", "default": 50},
+                        "llm_endpoint": {
+                            "type": "string",
+                            "description": "OpenAI-compatible chat completions URL (default: server config)"
+                        },
+                        "project_path": {"type": "string"}
+                    },
+                    "required": ["scope"]
+                }),
+            },
+            Tool {
+                name: "ping".to_string(),
+                description:
+                    "Check the server is alive and the global memory database is reachable"
+                        .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
+            Tool {
+                name: "clear_session".to_string(),
+                description: "Clear the memories of one session (pinned ones stay)".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "session_name": {
+                            "type": "string",
+                            "description": "Named session to clear; the default session when omitted"
+                        }
+                    }
+                }),
+            },
+        ];
+
+        if let Some(schema) = &self.custom_schema {
+            if let Some(store_memory) = tools.iter_mut().find(|t| t.name == "store_memory") {
+                store_memory.input_schema["properties"]["custom"] = schema.clone();
+            }
+        }
+
+        Ok(json!({ "tools": tools }))
+    }
+
+    fn handle_tools_call(&mut self, params: Option<Value>) -> Result<Value> {
+        let params =
+            params.ok_or_else(|| McpError::new(McpErrorCode::InvalidParams, "Missing params"))?;
+        let name = params["name"]
+            .as_str()
+            .ok_or_else(|| McpError::new(McpErrorCode::InvalidParams, "Missing tool name"))?;
+        let arguments = &params["arguments"];
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.check(name)?;
+        }
+
+        let result = match name {
+            "store_memory" => self.tool_store_memory(arguments),
+            "search_memory" => self.tool_search_memory(arguments),
+            "cross_scope_search" => self.tool_cross_scope_search(arguments),
+            "search_project_tree" => self.tool_search_project_tree(arguments),
+            "get_context" => self.tool_get_context(arguments),
+            "list_memories" => self.tool_list_memories(arguments),
+            "get_memory" => self.tool_get_memory(arguments),
+            "search_by_tag" => self.tool_search_by_tag(arguments),
+            "bulk_tag" => self.tool_bulk_tag(arguments, false),
+            "bulk_untag" => self.tool_bulk_tag(arguments, true),
+            "rename_tag" => self.tool_rename_tag(arguments),
+            "delete_tag" => self.tool_delete_tag(arguments),
+            "delete_memory" => self.tool_delete_memory(arguments),
+            "pin_memory" => self.tool_set_pinned(arguments, true),
+            "unpin_memory" => self.tool_set_pinned(arguments, false),
+            "set_importance" => self.tool_set_importance(arguments),
+            "move_memory" => self.tool_move_memory(arguments),
+            "copy_memory" => self.tool_copy_memory(arguments),
+            "dedup_memories" => self.tool_dedup_memories(arguments),
+            "merge_memories" => self.tool_merge_memories(arguments),
+            "split_memory" => self.tool_split_memory(arguments),
+            "delete_scope" => self.tool_delete_scope(arguments),
+            "get_stats" => self.tool_get_stats(arguments),
+            "get_extended_stats" => self.tool_get_extended_stats(arguments),
+            "list_projects" => self.tool_list_projects(),
+            "ping" => self.tool_ping(),
+            "summarize_scope" => self.tool_summarize_scope(arguments),
+            "configure_search" => self.tool_configure_search(arguments),
+            "clear_session" => self.tool_clear_session(arguments),
+            "find_by_source" => self.tool_find_by_source(arguments),
+            "find_by_hash" => self.tool_find_by_hash(arguments),
+            "random_memories" => self.tool_random_memories(arguments),
+            "get_document" => self.tool_get_document(arguments),
+            "get_ancestors" => self.tool_get_ancestors(arguments),
+            "get_children" => self.tool_get_children(arguments),
+            "reingest_file" => self.tool_reingest_file(arguments),
+            "ingest_directory" => self.tool_ingest_directory(arguments),
+            _ => Err(McpError::new(
+                McpErrorCode::ToolNotFound,
+                format!("Unknown tool: {}", name),
+            )
+            .into()),
+        };
+        self.save_search_indices()?;
+        result
+    }
+
+    fn tool_store_memory(&mut self, args: &Value) -> Result<Value> {
+        let _timer = metrics::STORE_DURATION.start_timer();
+        let content = args["content"].as_str().context("Missing content")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let tags = string_array(&args["tags"]);
+
+        let scope = parse_scope(scope_str, args)?;
+
+        let language = match args["override_language"].as_str() {
+            Some(language) => Some(language.to_string()),
+            None if self.config.storage.auto_detect_language => {
+                detect_content_language(content, None)
+            }
+            None => None,
+        };
+
+        let custom = match &args["custom"] {
+            Value::Null => HashMap::new(),
+            value => serde_json::from_value(value.clone()).context("custom must be an object")?,
+        };
+        let metadata = MemoryMetadata {
+            tags,
+            language,
+            custom,
+            ..Default::default()
+        };
+
+        // Oversized content, schema violations and full scopes are for the caller to
+        // fix: report them instead of failing the call
+        let checked = self
+            .store
+            .check_content_length(content)
+            .and_then(|()| self.store.validate_metadata(&metadata))
+            .and_then(|()| self.store.check_quota(&scope));
+        if let Err(e) = checked {
+            let hint = match e.downcast_ref::<MemoryStoreError>() {
+                Some(MemoryStoreError::QuotaExceeded { scope, .. }) => format!(
+                    ". Delete old memories or raise storage.{} in the config",
+                    match scope {
+                        MemoryScope::Global => "global_memory_quota",
+                        _ => "max_project_memories",
+                    }
+                ),
+                _ => String::new(),
+            };
+            return Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": format!("Memory not stored: {}{}", e, hint)
+                }],
+                "isError": true
+            }));
+        }
+
+        let mut memory = Memory::new(content.to_string(), scope, metadata);
+        // Embeddings are optional for BM25 search; a provider outage must not block storing
+        memory.embedding = self.embed_content(content).unwrap_or_else(|e| {
+            warn!("Storing memory without embedding: {}", e);
+            Vec::new()
+        });
+        let id = memory.id.clone();
+        let scope = memory.scope.to_string();
+        let content_hash = memory.content_hash();
+        let event = json!({
+            "id": id,
+            "scope": memory.scope,
+            "tags": memory.metadata.tags,
+        });
+
+        self.search_index(&memory.scope)?.index_memory(&memory);
+        self.store.store(memory)?;
+        self.notify("notifications/memory_stored", event);
+
+        self.tool_response(
+            format!(
+                "Memory stored successfully with ID: {} (content hash: {})",
+                id, content_hash
+            ),
+            StoreMemoryResponse {
+                id,
+                scope,
+                content_hash,
+            },
+        )
+    }
+
+    /// BM25 index for `scope`, to be changed: loaded on first use and saved again by
+    /// the next `save_search_indices`
+    fn search_index(&mut self, scope: &MemoryScope) -> Result<&mut BM25SearchEngine> {
+        self.load_search_index(scope)?;
+        let key = ScopeKey::from(scope);
+        self.unsaved_indices.insert(key.clone());
+        Ok(self.search_indices.get_mut(&key).unwrap())
+    }
+
+    /// Bring `scope`'s index into `search_indices` unless it is there: the statistics
+    /// saved with the scope's memories under the configured tuning, or a rebuild from
+    /// the stored memories when nothing usable was saved (never saved, unreadable, or
+    /// out of date because memories were written without the server)
+    fn load_search_index(&mut self, scope: &MemoryScope) -> Result<()> {
+        let key = ScopeKey::from(scope);
+        if self.search_indices.contains_key(&key) {
+            return Ok(());
+        }
+
+        let saved = match self.store.load_search_index(scope) {
+            Ok(data) => data.and_then(|data| {
+                serde_json::from_str::<BM25SearchEngine>(&data)
+                    .inspect_err(|e| warn!("Ignoring unreadable search index of {}: {}", scope, e))
+                    .ok()
+            }),
+            Err(e) => {
+                warn!("Failed to load search index of {}: {}", scope, e);
+                None
+            }
+        };
+        let count = self.store.count(scope)?;
+        let engine = match saved {
+            Some(saved) if saved.document_count() == count => self.search_template.merge(&saved),
+            _ => {
+                let engine = self.build_search_index(scope)?;
+                if !engine.is_empty() {
+                    self.unsaved_indices.insert(key.clone());
+                }
+                engine
+            }
+        };
+        self.search_indices.insert(key, engine);
+        Ok(())
+    }
+
+    /// `search_template` indexed with every memory in `scope`, streamed from the store
+    /// so that a large scope is never listed into memory at once. The scope's database
+    /// must already be open (e.g. by `count`).
+    fn build_search_index(&self, scope: &MemoryScope) -> Result<BM25SearchEngine> {
+        let mut engine = self.search_template.clone();
+        let mut failure = None;
+        engine.reindex_all(
+            self.store
+                .iter_all(scope)?
+                .map_while(|memory| memory.map_err(|e| failure = Some(e)).ok()),
+        );
+        failure.map_or(Ok(engine), Err)
+    }
+
+    /// Persist every index changed since the last call with its scope's memories
+    pub fn save_search_indices(&mut self) -> Result<()> {
+        if self.store.is_read_only() {
+            self.unsaved_indices.clear();
+            return Ok(());
+        }
+        for key in std::mem::take(&mut self.unsaved_indices) {
+            let Some(engine) = self.search_indices.get(&key) else {
+                continue;
+            };
+            let data = serde_json::to_string(engine)?;
+            self.store
+                .save_search_index(&MemoryScope::from(key), &data)?;
+        }
+        Ok(())
+    }
+
+    /// Read-only view of `search_index`; an unindexed scope gets the empty template
+    fn search_engine(&self, scope: &MemoryScope) -> &BM25SearchEngine {
+        self.search_indices
+            .get(&ScopeKey::from(scope))
+            .unwrap_or(&self.search_template)
+    }
+
+    /// Embedding for `content`, served from the cache when the same content was embedded
+    /// before. Empty when no embedder is configured.
+    fn embed_content(&mut self, content: &str) -> Result<Vec<f32>> {
+        let Some(embedder) = &self.embedder else {
+            return Ok(Vec::new());
+        };
+
+        let hash = EmbeddingCache::content_hash(content);
+        if let Some(embedding) = self.embedding_cache.get(&hash) {
+            return Ok(embedding);
+        }
+
+        let embedding = embedder.embed(content)?;
+        if let Some(evicted) = self.embedding_cache.insert(hash.clone(), embedding.clone()) {
+            self.store.evict_cached_embedding(&evicted)?;
+        }
+        self.store.save_cached_embedding(&hash, &embedding)?;
+        Ok(embedding)
+    }
+
+    fn tool_search_memory(&mut self, args: &Value) -> Result<Value> {
+        let _timer = metrics::SEARCH_DURATION.start_timer();
+        let query = args["query"].as_str().context("Missing query")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let k = args["k"]
+            .as_u64()
+            .unwrap_or(self.config.search.default_k as u64) as usize;
+
+        let scope = parse_scope(scope_str, args)?;
+
+        // `mode` is accepted as a shorter alias of `search_mode`
+        let mode = args["search_mode"]
+            .as_str()
+            .or_else(|| args["mode"].as_str())
+            .unwrap_or("bm25");
+        let results = match mode {
+            "bm25" => self.search_bm25(query, &scope, k, args)?,
+            "regex" => self.store.search_regex(&scope, query, k)?,
+            other => anyhow::bail!("Invalid search_mode: {}", other),
+        };
+        metrics::SEARCH_RESULTS_RETURNED.observe(results.len() as f64);
+
+        // Regex queries are patterns, not words to mark
+        let search = &self.config.search;
+        let highlight = (search.highlight_matches && mode == "bm25")
+            .then(|| self.search_engine(&scope).query_words(query));
+        let results_text = if results.is_empty() {
+            "No matching memories found.".to_string()
+        } else {
+            let mut output = format!("Found {} results:\n\n", results.len());
+            for result in &results {
+                let content = match &highlight {
+                    Some(words) => highlight_tokens(
+                        &result.memory.content,
+                        words,
+                        &search.highlight_prefix,
+                        &search.highlight_suffix,
+                    ),
+                    None => result.memory.content.clone(),
+                };
+                output.push_str(&format!(
+                    "Score: {:.2} | ID: {}\n{}\n\n---\n\n",
+                    result.score, result.memory.id, content
+                ));
+            }
+            output
+        };
+
+        let total_searched = self.store.count(&scope)?;
+        self.tool_response(
+            results_text,
+            SearchResultResponse {
+                results: results
+                    .into_iter()
+                    .map(|result| SearchResultItem {
+                        id: result.memory.id,
+                        score: result.score,
+                        rank: result.rank,
+                        content_preview: content_preview(&result.memory.content),
+                        tags: result.memory.metadata.tags,
+                    })
+                    .collect(),
+                total_searched,
+            },
+        )
+    }
+
+    /// Tool result with a single text item. With `structured_responses` on, `data`
+    /// follows as a `{"type": "json"}` item so clients need not parse the text.
+    fn tool_response(&self, text: String, data: impl Serialize) -> Result<Value> {
+        let mut content = vec![json!({"type": "text", "text": text})];
+        if self.config.server.structured_responses {
+            content.push(json!({"type": "json", "json": serde_json::to_value(data)?}));
+        }
+        Ok(json!({ "content": content }))
+    }
+
+    /// BM25 ranking with importance boost and optional time decay
+    fn search_bm25(
+        &mut self,
+        query: &str,
+        scope: &MemoryScope,
+        k: usize,
+        args: &Value,
+    ) -> Result<Vec<SearchResult>> {
+        let use_boost = args["use_importance_boost"]
+            .as_bool()
+            .unwrap_or(self.config.search.use_importance_boost);
+
+        let mut search_config = self.config.search.clone();
+        if let Some(decay) = args["time_decay_override"].as_bool() {
+            search_config.time_decay_enabled = decay;
+        }
+
+        self.load_search_index(scope)?;
+        let all_memories = self.store.list_all(scope)?;
+        // Decay can reorder results, so rank every match before truncating to k
+        let candidates = if search_config.time_decay_enabled {
+            all_memories.len()
+        } else {
+            k
+        };
+        let min_score = args["min_score"]
+            .as_f64()
+            .map_or(self.config.search.min_score, |score| score as f32);
+        let filter_tags = string_array(&args["filter_tags"]);
+        let created_after = timestamp_arg(args, "created_after")?;
+        let created_before = timestamp_arg(args, "created_before")?;
+        let min_importance = args["min_importance_score"].as_f64().map(|s| s as f32);
+        let mut results = self.search_engine(scope).search_with_boost_filtered(
+            query,
+            &all_memories,
+            candidates,
+            min_score,
+            importance_boost(&self.config, use_boost),
+            |memory| {
+                filter_tags.iter().all(|t| memory.metadata.tags.contains(t))
+                    && created_after.is_none_or(|after| memory.created_at >= after)
+                    && created_before.is_none_or(|before| memory.created_at < before)
+                    && min_importance.is_none_or(|min| memory.metadata.importance_score >= min)
+            },
+        );
+
+        if search_config.time_decay_enabled {
+            for result in &mut results {
+                result.score = score_with_decay(result.score, &result.memory, &search_config);
+            }
+            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+            results.truncate(k);
+            for (rank, result) in results.iter_mut().enumerate() {
+                result.rank = rank;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn tool_cross_scope_search(&mut self, args: &Value) -> Result<Value> {
+        let query = args["query"].as_str().context("Missing query")?;
+        let k = args["k"]
+            .as_u64()
+            .unwrap_or(self.config.search.default_k as u64) as usize;
+        let weight = |name: &str| args["weights"][name].as_f64().unwrap_or(1.0) as f32;
+
+        let mut scopes = vec![parse_scope("session", args)?];
+        if let Some(path) = args["project_path"].as_str() {
+            scopes.push(MemoryScope::Project {
+                path: PathBuf::from(path),
+            });
+        }
+        scopes.push(MemoryScope::Global);
+
+        let mut buckets = Vec::with_capacity(scopes.len());
+        for scope in &scopes {
+            self.load_search_index(scope)?;
+            buckets.push(self.store.list_all(scope)?);
+        }
+        let bucket_refs: Vec<&[Memory]> = buckets.iter().map(Vec::as_slice).collect();
+
+        let merged = scopes
+            .iter()
+            .fold(self.search_template.clone(), |merged, scope| {
+                merged.merge(self.search_engine(scope))
+            });
+        let mut results = merged.search_multiple(query, &bucket_refs, k);
+        for result in &mut results {
+            result.score *= match result.memory.scope {
+                MemoryScope::Session { .. } => weight("session"),
+                MemoryScope::Project { .. } => weight("project"),
+                MemoryScope::Global => weight("global"),
+            };
+        }
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+
+        // Identical content stored in several scopes is one result; keep the best-scored copy
+        let mut seen = HashSet::new();
+        results.retain(|result| seen.insert(result.memory.content.clone()));
+        results.truncate(k);
+
+        let text = if results.is_empty() {
+            "No matching memories found.".to_string()
+        } else {
+            let mut output = format!("Found {} results across scopes:\n\n", results.len());
+            for result in &results {
+                output.push_str(&format!(
+                    "Score: {:.2} | Scope: {} | ID: {}\n{}\n\n---\n\n",
+                    result.score, result.memory.scope, result.memory.id, result.memory.content
+                ));
+            }
+            output
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_search_project_tree(&mut self, args: &Value) -> Result<Value> {
+        let root_path = args["root_path"].as_str().context("Missing root_path")?;
+        let query = args["query"].as_str().context("Missing query")?;
+        let k = args["k"]
+            .as_u64()
+            .unwrap_or(self.config.search.default_k as u64) as usize;
+
+        // Registered project paths are normalized, so the root must be too
+        let root = rag_core::normalize_project_path(Path::new(root_path));
+        let memories = self.store.list_by_path_prefix(&root, usize::MAX, 0)?;
+
+        let mut engine = self.search_template.clone();
+        engine.reindex_all(&memories);
+        let results = engine.search_with_boost(
+            query,
+            &memories,
+            k,
+            self.config.search.min_score,
+            importance_boost(&self.config, self.config.search.use_importance_boost),
+        );
+
+        let text = if results.is_empty() {
+            "No matching memories found.".to_string()
+        } else {
+            let mut output = format!(
+                "Found {} results under {}:\n\n",
+                results.len(),
+                root.display()
+            );
+            for result in &results {
+                output.push_str(&format!(
+                    "Score: {:.2} | Scope: {} | ID: {}\n{}\n\n---\n\n",
+                    result.score, result.memory.scope, result.memory.id, result.memory.content
+                ));
+            }
+            output
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_get_context(&mut self, args: &Value) -> Result<Value> {
+        let query = args["query"].as_str().context("Missing query")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let k = args["k"]
+            .as_u64()
+            .unwrap_or(self.config.search.default_k as u64) as usize;
+        let template = args["template"]
+            .as_str()
+            .unwrap_or(DEFAULT_CONTEXT_TEMPLATE);
+        let max_tokens = args["max_tokens"].as_u64().map(|t| t as usize);
+
+        let scope = parse_scope(scope_str, args)?;
+        let results = self.search_bm25(query, &scope, k, args)?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": render_context(&results, template, max_tokens)
+            }]
+        }))
+    }
+
+    fn tool_list_memories(&mut self, args: &Value) -> Result<Value> {
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let limit = args["limit"].as_u64().unwrap_or(50) as usize;
+        let offset = args["offset"].as_u64().unwrap_or(0) as usize;
+
+        let scope = parse_scope(scope_str, args)?;
+
+        let sort = match args["sort_by"].as_str().unwrap_or("created_desc") {
+            "created_desc" => MemorySort::CreatedDesc,
+            "access_count_desc" => MemorySort::AccessCountDesc,
+            "last_accessed_desc" => MemorySort::LastAccessedDesc,
+            other => anyhow::bail!("Invalid sort_by: {}", other),
+        };
+
+        let after_id = args["after_id"].as_str();
+        let filter_pinned = args["filter_pinned"].as_bool();
+
+        // Default ordering pages by cursor, so every page reports next_cursor/has_more
+        if sort == MemorySort::CreatedDesc && filter_pinned.is_none() && offset == 0 {
+            let page = self.store.list_after_cursor(&scope, after_id, limit)?;
+            let total = self.store.count(&scope)?;
+            let mut response = self.list_memories_response(
+                format_memory_list(&page.memories),
+                &page.memories,
+                total,
+            )?;
+            response["total"] = json!(total);
+            response["next_cursor"] = json!(page.next_cursor);
+            response["has_more"] = json!(page.has_more);
+            return Ok(response);
+        }
+        if after_id.is_some() {
+            anyhow::bail!(
+                "after_id only works with sort_by created_desc, no offset or filter_pinned"
+            );
+        }
+
+        let (memories, total) = match filter_pinned {
+            None => self.store.list_with_total(&scope, limit, offset, sort)?,
+            // Filter before paginating so pages stay full
+            Some(pinned) => {
+                let matching: Vec<Memory> = self
+                    .store
+                    .list_sorted(&scope, sort, i64::MAX as usize, 0)?
+                    .into_iter()
+                    .filter(|m| m.pinned == pinned)
+                    .collect();
+                let total = matching.len();
+                let page = matching.into_iter().skip(offset).take(limit).collect();
+                (page, total)
+            }
+        };
+
+        let mut response =
+            self.list_memories_response(format_memory_list(&memories), &memories, total)?;
+        response["total"] = json!(total);
+        Ok(response)
+    }
+
+    fn list_memories_response(
+        &self,
+        text: String,
+        memories: &[Memory],
+        total: usize,
+    ) -> Result<Value> {
+        self.tool_response(
+            text,
+            ListMemoriesResponse {
+                memories: memories.iter().map(memory_summary).collect(),
+                total,
+            },
+        )
+    }
+
+    fn tool_get_memory(&mut self, args: &Value) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let memory = self.store.get(id, &scope)?;
+        let text = match &memory {
+            Some(memory) => format_memory_list(std::slice::from_ref(memory)),
+            None => format!("Memory {} not found", id),
+        };
+
+        self.tool_response(text, GetMemoryResponse { memory })
+    }
+
+    fn tool_search_by_tag(&mut self, args: &Value) -> Result<Value> {
+        let tags = string_array(&args["tags"]);
+        anyhow::ensure!(!tags.is_empty(), "Missing tags");
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let match_mode = match args["match_mode"].as_str().unwrap_or("all") {
+            "all" => TagMatchMode::All,
+            "any" => TagMatchMode::Any,
+            other => anyhow::bail!("Invalid match_mode: {}", other),
+        };
+        let limit = args["limit"].as_u64().unwrap_or(50) as usize;
+        let offset = args["offset"].as_u64().unwrap_or(0) as usize;
+
+        let scope = parse_scope(scope_str, args)?;
+
+        let memories = self
+            .store
+            .find_by_tags(&scope, &tags, match_mode, limit, offset)?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format_memory_list(&memories)
+            }]
+        }))
+    }
+
+    /// `bulk_tag` / `bulk_untag` (which ignores `add_tags`). Memories are selected by
+    /// `query` (BM25 matches), `filter_tags` (all required), or both intersected.
+    fn tool_bulk_tag(&mut self, args: &Value, untag_only: bool) -> Result<Value> {
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let query = args["query"].as_str();
+        let filter_tags = string_array(&args["filter_tags"]);
+        anyhow::ensure!(
+            query.is_some() || !filter_tags.is_empty(),
+            "Provide query and/or filter_tags to select memories"
+        );
+        let add_tags = if untag_only {
+            Vec::new()
+        } else {
+            string_array(&args["add_tags"])
+        };
+        let remove_tags = string_array(&args["remove_tags"]);
+        anyhow::ensure!(
+            !add_tags.is_empty() || !remove_tags.is_empty(),
+            "Nothing to do: add_tags and remove_tags are empty"
+        );
+        let dry_run = args["dry_run"].as_bool().unwrap_or(false);
+
+        let mut selected = match query {
+            Some(query) => {
+                let k = self.store.count(&scope)?;
+                self.search_bm25(query, &scope, k, args)?
+                    .into_iter()
+                    .map(|result| result.memory)
+                    .collect()
+            }
+            None => self.store.list_all(&scope)?,
+        };
+        selected.retain(|memory| filter_tags.iter().all(|t| memory.metadata.tags.contains(t)));
+
+        let (mut added, mut removed) = (0, 0);
+        let mut modified = Vec::new();
+        for mut memory in selected {
+            let add_count = add_tags.iter().filter(|t| memory.add_tag(t)).count();
+            let remove_count = remove_tags.iter().filter(|t| memory.remove_tag(t)).count();
+            if add_count + remove_count == 0 {
+                continue;
+            }
+            added += add_count;
+            removed += remove_count;
+            modified.push(memory.id.clone());
+            if !dry_run {
+                // Tags are scored from metadata at query time; the BM25 index is unaffected
+                let tags = memory.metadata.tags;
+                self.store
+                    .update_metadata(&memory.id, &scope, |metadata| metadata.tags = tags)?;
+            }
+        }
+
+        let mut text = if dry_run {
+            format!(
+                "Dry run: {} memories would be modified ({} tags added, {} removed):\n",
+                modified.len(),
+                added,
+                removed
+            )
+        } else {
+            format!(
+                "Modified {} memories ({} tags added, {} removed):\n",
+                modified.len(),
+                added,
+                removed
+            )
+        };
+        for id in &modified {
+            text.push_str(&format!("{}\n", id));
+        }
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_rename_tag(&mut self, args: &Value) -> Result<Value> {
+        let old_tag = args["old_tag"].as_str().context("Missing old_tag")?;
+        let new_tag = args["new_tag"].as_str().context("Missing new_tag")?;
+        anyhow::ensure!(!new_tag.is_empty(), "new_tag must not be empty");
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let renamed = self.store.rename_tag(&scope, old_tag, new_tag)?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "Renamed tag '{}' to '{}' on {} memories",
+                    old_tag, new_tag, renamed
+                )
+            }]
+        }))
+    }
+
+    fn tool_delete_tag(&mut self, args: &Value) -> Result<Value> {
+        let tag = args["tag"].as_str().context("Missing tag")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let removed = self.store.delete_tag(&scope, tag)?;
+        let untagged = self.store.list_orphaned_memories(&scope)?.len();
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "Removed tag '{}' from {} memories ({} memories in scope have no tags)",
+                    tag, removed, untagged
+                )
+            }]
+        }))
+    }
+
+    fn tool_delete_memory(&mut self, args: &Value) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+
+        let scope = parse_scope(scope_str, args)?;
+        let existing = self.store.get_without_tracking(id, &scope)?;
+
+        let deleted = if args["force"].as_bool() == Some(true) {
+            self.store.force_delete(id, &scope)?
+        } else {
+            self.store.delete(id, &scope)?
+        };
+        if deleted {
+            match &existing {
+                Some(memory) => self
+                    .search_index(&scope)?
+                    .remove_memory_full(id, &memory.content),
+                None => self.search_index(&scope)?.remove_memory(id),
+            }
+            self.notify(
+                "notifications/memory_deleted",
+                json!({ "id": id, "scope": scope }),
+            );
+        }
+
+        let text = if deleted {
+            format!("Memory {} deleted successfully", id)
+        } else {
+            format!("Memory {} not found", id)
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_set_pinned(&mut self, args: &Value, pinned: bool) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let text = if self.store.set_pinned(id, &scope, pinned)? {
+            format!(
+                "Memory {} {}",
+                id,
+                if pinned { "pinned" } else { "unpinned" }
+            )
+        } else {
+            format!("Memory {} not found", id)
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_set_importance(&mut self, args: &Value) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let score = args["importance_score"]
+            .as_f64()
+            .context("Missing importance_score")? as f32;
+        let score = score.clamp(0.0, MAX_IMPORTANCE_SCORE);
+
+        let text = if self.store.update_importance(id, &scope, score)? {
+            format!("Memory {} importance set to {:.2}", id, score)
+        } else {
+            format!("Memory {} not found", id)
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_move_memory(&mut self, args: &Value) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let from_str = args["from_scope"].as_str().context("Missing from_scope")?;
+        let to_str = args["to_scope"].as_str().context("Missing to_scope")?;
+        let from = parse_scope(from_str, args)?;
+        let to = parse_scope(to_str, args)?;
+
+        let moved = self.store.move_memory(id, &from, &to)?;
+        if moved {
+            // Re-index so the BM25 statistics reflect the memory's current state
+            self.search_index(&from)?.remove_memory(id);
+            if let Some(memory) = self.store.get_without_tracking(id, &to)? {
+                self.search_index(&to)?.index_memory(&memory);
+            }
+        }
+
+        let text = if moved {
+            format!("Memory {} moved from {} to {}", id, from, to)
+        } else {
+            format!("Memory {} not found in {}", id, from)
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_copy_memory(&mut self, args: &Value) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let from_str = args["from_scope"].as_str().context("Missing from_scope")?;
+        let to_str = args["to_scope"].as_str().context("Missing to_scope")?;
+        let from = parse_scope(from_str, args)?;
+        let to = parse_scope(to_str, args)?;
+
+        let text = match self.store.copy_memory(id, &from, &to)? {
+            Some(copy) => {
+                self.search_index(&to)?.index_memory(&copy);
+                format!("Memory {} copied to {} with new ID: {}", id, to, copy.id)
+            }
+            None => format!("Memory {} not found in {}", id, from),
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_dedup_memories(&mut self, args: &Value) -> Result<Value> {
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let keep: DedupKeepPolicy = args["keep"].as_str().unwrap_or("oldest").parse()?;
+        let dry_run = args["dry_run"].as_bool().unwrap_or(false);
+
+        let report = if dry_run {
+            self.store.dedup_preview(&scope, keep)?
+        } else {
+            self.store.dedup(&scope, keep)?
+        };
+        if !dry_run {
+            for memory in &report.deleted {
+                self.search_index(&scope)?
+                    .remove_memory_full(&memory.id, &memory.content);
+                self.notify(
+                    "notifications/memory_deleted",
+                    json!({ "id": memory.id, "scope": scope }),
+                );
+            }
+        }
+
+        let verb = if dry_run { "Would delete" } else { "Deleted" };
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "Found {} duplicate groups. {} {} memories ({} bytes)",
+                    report.groups_found, verb, report.memories_deleted, report.bytes_freed
+                )
+            }]
+        }))
+    }
+
+    fn tool_merge_memories(&mut self, args: &Value) -> Result<Value> {
+        let ids = string_array(&args["ids"]);
+        anyhow::ensure!(
+            (2..=10).contains(&ids.len()),
+            "merge_memories takes 2 to 10 ids, got {}",
+            ids.len()
+        );
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let separator = args["merged_content_separator"]
+            .as_str()
+            .unwrap_or(DEFAULT_MERGE_SEPARATOR);
+        let tags = string_array(&args["tags"]);
+
+        let merged = self.store.merge_memories(&ids, &scope, separator, &tags)?;
+
+        for id in &ids {
+            self.search_index(&scope)?.remove_memory(id);
+            self.notify(
+                "notifications/memory_deleted",
+                json!({ "id": id, "scope": scope }),
+            );
+        }
+        self.search_index(&scope)?.index_memory(&merged);
+        self.notify(
+            "notifications/memory_stored",
+            json!({ "id": merged.id, "scope": merged.scope, "tags": merged.metadata.tags }),
+        );
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!("Merged {} memories into new ID: {}", ids.len(), merged.id)
+            }]
+        }))
+    }
+
+    fn tool_split_memory(&mut self, args: &Value) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let split_at = args["split_at"]
+            .as_array()
+            .context("Missing split_at")?
+            .iter()
+            .map(|v| {
+                v.as_u64()
+                    .map(|n| n as usize)
+                    .context("Invalid split offset")
+            })
+            .collect::<Result<Vec<usize>>>()?;
+        anyhow::ensure!(!split_at.is_empty(), "split_at must not be empty");
+
+        let parts = self.store.split_memory(id, &scope, &split_at)?;
+
+        self.search_index(&scope)?.remove_memory(id);
+        self.notify(
+            "notifications/memory_deleted",
+            json!({ "id": id, "scope": scope }),
+        );
+        let mut text = format!("Split memory {} into {} memories:\n", id, parts.len());
+        for part in &parts {
+            self.search_index(&scope)?.index_memory(part);
+            self.notify(
+                "notifications/memory_stored",
+                json!({ "id": part.id, "scope": part.scope, "tags": part.metadata.tags }),
+            );
+            text.push_str(&format!("ID: {}\n", part.id));
+        }
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_delete_scope(&mut self, args: &Value) -> Result<Value> {
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        if args["confirm"].as_bool() != Some(true) {
+            anyhow::bail!("Refusing to delete {} without confirm: true", scope);
+        }
+
+        let key = ScopeKey::from(&scope);
+        self.search_indices.remove(&key);
+        self.unsaved_indices.remove(&key);
+        let deleted = self.store.delete_scope(&scope)?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!("Deleted {} memories from {}", deleted, scope)
+            }]
+        }))
+    }
+
+    fn tool_get_stats(&mut self, args: &Value) -> Result<Value> {
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let stats = self.store.stats(&scope)?;
+
+        let mut text = format!(
+            "Scope: {}\nTotal memories: {}\nTotal accesses: {}\n",
+            stats.scope, stats.total_memories, stats.total_accesses
+        );
+        if let Some(id) = &stats.most_accessed_id {
+            text.push_str(&format!("Most accessed: {}\n", id));
+        }
+        if let Some(importance) = &stats.importance {
+            text.push_str(&format!(
+                "Importance: min {:.2}, max {:.2}, mean {:.2}, p90 {:.2}\n",
+                importance.min, importance.max, importance.mean, importance.percentile_90
+            ));
+        }
+        for (id, score) in &stats.top_important {
+            text.push_str(&format!("Important: {} ({:.2})\n", id, score));
+        }
+        if let Some(extended) = &stats.extended {
+            text.push_str(&format_extended_stats(extended));
+        }
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_get_extended_stats(&mut self, args: &Value) -> Result<Value> {
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let total = self.store.count(&scope)?;
+        let extended = self.store.extended_stats(&scope)?;
+        let text = format!(
+            "Scope: {}\nTotal memories: {}\n{}",
+            scope,
+            total,
+            format_extended_stats(&extended)
+        );
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    fn tool_list_projects(&mut self) -> Result<Value> {
+        let projects = self.store.list_known_projects()?;
+
+        let mut text = format!("Found {} known projects:\n\n", projects.len());
+        for path in projects {
+            // Stats on a missing database would recreate it, so check the file first
+            if MemoryStore::project_db_exists(&path) {
+                let stats = self
+                    .store
+                    .stats(&MemoryScope::Project { path: path.clone() })?;
+                text.push_str(&format!(
+                    "{} | Memories: {}\n",
+                    path.display(),
+                    stats.total_memories
+                ));
+            } else {
+                text.push_str(&format!("{} | STALE (database missing)\n", path.display()));
+            }
+        }
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }]
+        }))
+    }
+
+    /// Apply search overrides to the live config, then rebuild every loaded index
+    /// with the new settings. The config file changes only with `persist: true`.
+    fn tool_configure_search(&mut self, args: &Value) -> Result<Value> {
+        let add = string_array(&args["add_stop_words"]);
+        let remove = string_array(&args["remove_stop_words"]);
+        let search = &mut self.config.search;
+
+        if let Some(k1) = args["set_k1"].as_f64() {
+            anyhow::ensure!(k1 >= 0.0, "set_k1 must not be negative");
+            search.bm25_k1 = k1 as f32;
+        }
+        if let Some(b) = args["set_b"].as_f64() {
+            anyhow::ensure!((0.0..=1.0).contains(&b), "set_b must be between 0 and 1");
+            search.bm25_b = b as f32;
+        }
+        for word in add.iter().map(|w| w.trim().to_lowercase()) {
+            search.removed_stop_words.retain(|w| *w != word);
+            let stop_words = search.stop_words.get_or_insert_with(Vec::new);
+            if !stop_words.contains(&word) {
+                stop_words.push(word);
+            }
+        }
+        for word in remove.iter().map(|w| w.trim().to_lowercase()) {
+            if let Some(stop_words) = &mut search.stop_words {
+                stop_words.retain(|w| *w != word);
+            }
+            if !search.removed_stop_words.contains(&word) {
+                search.removed_stop_words.push(word);
+            }
+        }
+
+        self.search_template = BM25SearchEngine::new_with_config(&self.config.search)?;
+        let scopes: Vec<ScopeKey> = self.search_indices.keys().cloned().collect();
+        for key in scopes {
+            let engine = self.build_search_index(&MemoryScope::from(key.clone()))?;
+            self.unsaved_indices.insert(key.clone());
+            self.search_indices.insert(key, engine);
+        }
+
+        let persist = args["persist"].as_bool().unwrap_or(false);
+        if persist {
+            match &self.config_path {
+                Some(path) => self.config.save_to(path)?,
+                None => self.config.save()?,
+            }
+        }
+
+        let search = &self.config.search;
+        let text = format!(
+            "Search configured: k1={}, b={}, added stop words: [{}], removed stop words: [{}]{}",
+            search.bm25_k1,
+            search.bm25_b,
+            add.join(", "),
+            remove.join(", "),
+            if persist { " (saved to config)" } else { "" }
+        );
+
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": format!("Memory stored successfully with ID: {}", id)
+                "text": text
             }]
         }))
     }
 
-    fn tool_search_memory(&mut self, args: &Value) -> Result<Value> {
-        let query = args["query"].as_str().context("Missing query")?;
+    /// Newest `max_memories` of a scope, summarized by the configured chat completions LLM
+    fn tool_summarize_scope(&mut self, args: &Value) -> Result<Value> {
         let scope_str = args["scope"].as_str().context("Missing scope")?;
-        let k = args["k"]
+        let scope = parse_scope(scope_str, args)?;
+        let max_memories = args["max_memories"]
             .as_u64()
-            .unwrap_or(self.config.search.default_k as u64) as usize;
+            .unwrap_or(DEFAULT_SUMMARY_MEMORIES as u64) as usize;
+        let endpoint = args["llm_endpoint"]
+            .as_str()
+            .map(String::from)
+            .or_else(|| self.config.server.default_llm_endpoint.clone())
+            .context("No llm_endpoint given and no default_llm_endpoint configured")?;
 
-        let scope = match scope_str {
-            "session" => MemoryScope::Session,
-            "global" => MemoryScope::Global,
-            "project" => {
-                let path = args["project_path"]
-                    .as_str()
-                    .context("Missing project_path for project scope")?;
-                MemoryScope::Project {
-                    path: PathBuf::from(path),
-                }
-            }
-            _ => return Err(anyhow::anyhow!("Invalid scope: {}", scope_str)),
-        };
+        let memories = self.store.list(&scope, max_memories, 0)?;
+        if memories.is_empty() {
+            return Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": "No memories to summarize."
+                }]
+            }));
+        }
 
-        let all_memories = self.store.list_all(&scope)?;
-        let results = self.search.search(query, &all_memories, k);
+        let mut prompt = llm::SUMMARY_PROMPT.to_string();
+        for memory in &memories {
+            prompt.push_str("\n\n---\n\n");
+            prompt.push_str(&memory.content);
+        }
+        let api_key = std::env::var(&self.config.server.llm_api_key_env).ok();
+        let summary = llm::complete(
+            &endpoint,
+            api_key.as_deref(),
+            &self.config.server.llm_model,
+            &prompt,
+        )?;
 
-        let results_text = if results.is_empty() {
-            "No matching memories found.".to_string()
-        } else {
-            let mut output = format!("Found {} results:\n\n", results.len());
-            for result in &results {
-                output.push_str(&format!(
-                    "Score: {:.2} | ID: {}\n{}\n\n---\n\n",
-                    result.score, result.memory.id, result.memory.content
-                ));
-            }
-            output
-        };
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": summary
+            }]
+        }))
+    }
+
+    /// Liveness check that never searches: reports whether the global DB answers
+    fn tool_ping(&self) -> Result<Value> {
+        let status = json!({
+            "status": "ok",
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "memories_reachable": self.store.global_db_reachable(),
+        });
 
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": results_text
+                "text": status.to_string()
             }]
         }))
     }
 
-    fn tool_list_memories(&mut self, args: &Value) -> Result<Value> {
+    /// Clear the session named by `session_name`, the default session when absent
+    fn tool_clear_session(&mut self, args: &Value) -> Result<Value> {
+        let scope = parse_scope("session", args)?;
+        // Pinned memories survive; everything else leaves the store and the BM25 index
+        let cleared: Vec<Memory> = self
+            .store
+            .list_all(&scope)?
+            .into_iter()
+            .filter(|memory| !memory.pinned)
+            .collect();
+        let refs: Vec<&str> = cleared.iter().map(|memory| memory.id.as_str()).collect();
+        self.store.batch_delete(&refs, &scope)?;
+
+        let index = self.search_index(&scope)?;
+        for memory in &cleared {
+            index.remove_memory_full(&memory.id, &memory.content);
+        }
+        info!("Cleared {} memories of {}", cleared.len(), scope);
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": "Session memories cleared successfully"
+            }]
+        }))
+    }
+
+    fn tool_find_by_source(&mut self, args: &Value) -> Result<Value> {
+        let source_file = args["source_file"]
+            .as_str()
+            .context("Missing source_file")?;
         let scope_str = args["scope"].as_str().context("Missing scope")?;
-        let limit = args["limit"].as_u64().unwrap_or(50) as usize;
-        let offset = args["offset"].as_u64().unwrap_or(0) as usize;
+        let scope = parse_scope(scope_str, args)?;
 
-        let scope = match scope_str {
-            "session" => MemoryScope::Session,
-            "global" => MemoryScope::Global,
-            "project" => {
-                let path = args["project_path"]
-                    .as_str()
-                    .context("Missing project_path for project scope")?;
-                MemoryScope::Project {
-                    path: PathBuf::from(path),
-                }
-            }
-            _ => return Err(anyhow::anyhow!("Invalid scope: {}", scope_str)),
-        };
+        let memories = self
+            .store
+            .find_by_source_file(&scope, Path::new(source_file))?;
 
-        let memories = self.store.list(&scope, limit, offset)?;
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format_memory_list(&memories)
+            }]
+        }))
+    }
 
-        let text = if memories.is_empty() {
-            "No memories found.".to_string()
-        } else {
-            let mut output = format!("Found {} memories:\n\n", memories.len());
-            for memory in &memories {
-                output.push_str(&format!(
-                    "ID: {} | Tags: {}\n{}\n\n---\n\n",
-                    memory.id,
-                    memory.metadata.tags.join(", "),
-                    memory.content
-                ));
-            }
-            output
+    fn tool_find_by_hash(&mut self, args: &Value) -> Result<Value> {
+        let hash = args["hash"].as_str().context("Missing hash")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let memory = self.store.get_by_content_hash(&scope, hash)?;
+        let text = match &memory {
+            Some(memory) => format_memory_list(std::slice::from_ref(memory)),
+            None => format!("No memory with content hash {}", hash),
         };
 
+        self.tool_response(text, GetMemoryResponse { memory })
+    }
+
+    fn tool_random_memories(&mut self, args: &Value) -> Result<Value> {
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let k = args["k"].as_u64().unwrap_or(5) as usize;
+        let seed = args["seed"].as_u64();
+
+        let memories = self.store.reservoir_sample(&scope, k, seed)?;
+
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": text
+                "text": format_memory_list(&memories)
             }]
         }))
     }
 
-    fn tool_delete_memory(&mut self, args: &Value) -> Result<Value> {
+    fn tool_get_ancestors(&mut self, args: &Value) -> Result<Value> {
         let id = args["id"].as_str().context("Missing id")?;
         let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
 
-        let scope = match scope_str {
-            "session" => MemoryScope::Session,
-            "global" => MemoryScope::Global,
-            "project" => {
-                let path = args["project_path"]
-                    .as_str()
-                    .context("Missing project_path for project scope")?;
-                MemoryScope::Project {
-                    path: PathBuf::from(path),
-                }
-            }
-            _ => return Err(anyhow::anyhow!("Invalid scope: {}", scope_str)),
-        };
+        let chain = self.store.get_ancestors(id, &scope)?;
 
-        let deleted = self.store.delete(id, &scope)?;
-        if deleted {
-            self.search.remove_memory(id);
-        }
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format_memory_list(&chain)
+            }]
+        }))
+    }
 
-        let text = if deleted {
-            format!("Memory {} deleted successfully", id)
+    fn tool_get_children(&mut self, args: &Value) -> Result<Value> {
+        let id = args["id"].as_str().context("Missing id")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let descendants = self.store.get_descendants(id, &scope)?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format_memory_list(&descendants)
+            }]
+        }))
+    }
+
+    fn tool_get_document(&mut self, args: &Value) -> Result<Value> {
+        let document_id = args["document_id"]
+            .as_str()
+            .context("Missing document_id")?;
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+
+        let chunks = self.store.list_chunks(&scope, document_id)?;
+        let text = if chunks.is_empty() {
+            format!("Document {} not found", document_id)
         } else {
-            format!("Memory {} not found", id)
+            let source = chunks[0]
+                .metadata
+                .source_file
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "unknown source".to_string());
+            // Overlap prefixes repeat the previous chunk; drop them to rebuild the original
+            let content: String = chunks
+                .iter()
+                .map(|chunk| {
+                    chunk
+                        .content
+                        .get(chunk.metadata.context_prefix_bytes..)
+                        .unwrap_or(&chunk.content)
+                })
+                .collect();
+            format!(
+                "Document {} ({} chunks from {}):\n\n{}",
+                document_id,
+                chunks.len(),
+                source,
+                content
+            )
         };
 
         Ok(json!({
@@ -432,22 +2626,573 @@ impl McpServer {
         }))
     }
 
-    fn tool_clear_session(&mut self) -> Result<Value> {
-        self.store.clear_session();
+    fn tool_reingest_file(&mut self, args: &Value) -> Result<Value> {
+        let file_path = PathBuf::from(args["file_path"].as_str().context("Missing file_path")?);
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let tags = string_array(&args["tags"]);
+
+        if !file_path.is_file() {
+            anyhow::bail!("Not a file: {}", file_path.display());
+        }
+
+        let replaced = self.store.find_by_source_file(&scope, &file_path)?.len();
+        let stored = self.reingest_file(&file_path, &scope, &tags)?;
 
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": "Session memories cleared successfully"
+                "text": format!(
+                    "Re-ingested {}: replaced {} chunks with {}",
+                    file_path.display(),
+                    replaced,
+                    stored
+                )
+            }]
+        }))
+    }
+
+    fn tool_ingest_directory(&mut self, args: &Value) -> Result<Value> {
+        let directory = PathBuf::from(
+            args["directory_path"]
+                .as_str()
+                .context("Missing directory_path")?,
+        );
+        let scope_str = args["scope"].as_str().context("Missing scope")?;
+        let scope = parse_scope(scope_str, args)?;
+        let tags = string_array(&args["tags"]);
+        let patterns = string_array(&args["glob_patterns"]);
+        let recursive = args["recursive"].as_bool().unwrap_or(true);
+
+        if !directory.is_dir() {
+            anyhow::bail!("Not a directory: {}", directory.display());
+        }
+
+        let globs = build_globset(&patterns)?;
+
+        let mut walker = WalkDir::new(&directory);
+        if !recursive {
+            walker = walker.max_depth(1);
+        }
+
+        let mut files_processed = 0;
+        let mut memories = Vec::new();
+        let mut errors = Vec::new();
+
+        // Hidden entries (.git, .rag-mcp, ...) are never ingested
+        let entries = walker.into_iter().filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        });
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    errors.push(json!({ "path": e.path(), "error": e.to_string() }));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path();
+            let relative = path.strip_prefix(&directory).unwrap_or(path);
+            if globs
+                .as_ref()
+                .is_some_and(|globs| !globs.is_match(relative))
+            {
+                continue;
+            }
+
+            match self.chunk_file(path, &scope, &tags) {
+                Ok(chunks) => {
+                    files_processed += 1;
+                    memories.extend(chunks);
+                }
+                Err(e) => {
+                    debug!("Skipping {}: {}", path.display(), e);
+                    errors.push(json!({ "path": path, "error": e.to_string() }));
+                }
+            }
+        }
+
+        let index = self.search_index(&scope)?;
+        for memory in &memories {
+            index.index_memory(memory);
+        }
+        let chunks_created = self.store.batch_store(memories)?;
+
+        info!(
+            "Ingested {} files ({} chunks) from {}",
+            files_processed,
+            chunks_created,
+            directory.display()
+        );
+
+        let summary = json!({
+            "directory": directory,
+            "files_processed": files_processed,
+            "chunks_created": chunks_created,
+            "errors": errors,
+        });
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": serde_json::to_string_pretty(&summary)?
+            }]
+        }))
+    }
+
+    /// Replace every chunk of `path` in `scope` with a fresh chunking of the file.
+    /// Returns the number of chunks stored.
+    pub fn reingest_file(
+        &mut self,
+        path: &Path,
+        scope: &MemoryScope,
+        tags: &[String],
+    ) -> Result<usize> {
+        let memories = self.chunk_file(path, scope, tags)?;
+        self.forget_file(path, scope)?;
+
+        let index = self.search_index(scope)?;
+        for memory in &memories {
+            index.index_memory(memory);
+        }
+        self.store.batch_store(memories)
+    }
+
+    /// Delete every chunk ingested from `path`; returns the number removed
+    pub fn forget_file(&mut self, path: &Path, scope: &MemoryScope) -> Result<usize> {
+        let memories = self.store.find_by_source_file(scope, path)?;
+        let ids: Vec<&str> = memories.iter().map(|memory| memory.id.as_str()).collect();
+        self.store.batch_delete(&ids, scope)?;
+
+        let index = self.search_index(scope)?;
+        for memory in &memories {
+            index.remove_memory_full(&memory.id, &memory.content);
+        }
+        Ok(memories.len())
+    }
+
+    /// Read and chunk one file into memories sharing a fresh document id as `parent_id`
+    fn chunk_file(&self, path: &Path, scope: &MemoryScope, tags: &[String]) -> Result<Vec<Memory>> {
+        let size = std::fs::metadata(path)?.len();
+        let limit = self.config.storage.max_ingest_file_size_bytes;
+        if size > limit as u64 {
+            anyhow::bail!("File too large ({} bytes, limit {})", size, limit);
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        let language = detect_language(path);
+        let chunker =
+            SemanticChunker::from_config(&self.config.chunking).with_language(language.clone());
+        let document_id = Uuid::new_v4().to_string();
+
+        let memories = chunker
+            .chunk(&text)
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                self.store.check_content_length(&chunk.content)?;
+                Ok(chunk.to_memory(
+                    scope.clone(),
+                    &document_id,
+                    index,
+                    Some(path.to_path_buf()),
+                    language.clone(),
+                    tags.to_vec(),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(memories)
+    }
+
+    /// Every memory of the session, global and known project scopes as a resource
+    fn handle_resources_list(&mut self) -> Result<Value> {
+        let mut scopes: Vec<MemoryScope> = self
+            .store
+            .list_sessions()
+            .into_iter()
+            .map(MemoryScope::named_session)
+            .collect();
+        scopes.push(MemoryScope::Global);
+        scopes.extend(
+            self.store
+                .list_known_projects()?
+                .into_iter()
+                .map(|path| MemoryScope::Project { path }),
+        );
+
+        let mut resources = Vec::new();
+        for scope in &scopes {
+            for memory in self.store.list_all(scope)? {
+                resources.push(json!({
+                    "uri": memory_uri(&memory),
+                    "name": memory.content.chars().take(RESOURCE_NAME_CHARS).collect::<String>(),
+                    "description": memory.metadata.tags.join(", "),
+                    "mimeType": "text/plain"
+                }));
+            }
+        }
+
+        Ok(json!({ "resources": resources }))
+    }
+
+    fn handle_resources_read(&mut self, params: Option<Value>) -> Result<Value> {
+        let uri = params
+            .as_ref()
+            .and_then(|params| params["uri"].as_str())
+            .ok_or_else(|| McpError::new(McpErrorCode::InvalidParams, "Missing uri"))?;
+        let (scope, id) = uri
+            .rsplit_once('/')
+            .and_then(|(prefix, id)| Some((MemoryScope::from_uri_prefix(prefix)?, id)))
+            .ok_or_else(|| {
+                McpError::new(
+                    McpErrorCode::InvalidParams,
+                    format!("Invalid memory URI: {}", uri),
+                )
+            })?;
+
+        let memory = self
+            .store
+            .get(id, &scope)?
+            .ok_or_else(|| MemoryStoreError::MemoryNotFound(id.to_string()))?;
+
+        Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "text/plain",
+                "text": memory.content
             }]
         }))
     }
+}
+
+/// `memory://{scope}/{id}` resource URI of a memory
+fn memory_uri(memory: &Memory) -> String {
+    format!("{}/{}", memory.scope.uri_prefix(), memory.id)
+}
+
+/// First `CONTENT_PREVIEW_CHARS` characters of `content`
+fn content_preview(content: &str) -> String {
+    content.chars().take(CONTENT_PREVIEW_CHARS).collect()
+}
+
+fn memory_summary(memory: &Memory) -> MemorySummary {
+    MemorySummary {
+        id: memory.id.clone(),
+        content_preview: content_preview(&memory.content),
+        tags: memory.metadata.tags.clone(),
+        pinned: memory.pinned,
+    }
+}
+
+/// Text body shared by `list_memories` and `search_by_tag`
+fn format_memory_list(memories: &[Memory]) -> String {
+    if memories.is_empty() {
+        return "No memories found.".to_string();
+    }
+
+    let mut output = format!("Found {} memories:\n\n", memories.len());
+    for memory in memories {
+        output.push_str(&format!("ID: {}", memory.id));
+        if memory.pinned {
+            output.push_str(" [PINNED]");
+        }
+        output.push_str(&format!(" | Tags: {}", memory.metadata.tags.join(", ")));
+        if let Some(source) = &memory.metadata.source_file {
+            output.push_str(&format!(" | Source: {}", source.display()));
+        }
+        if let Some(parent_id) = &memory.metadata.parent_id {
+            output.push_str(&format!(" | Parent: {}", parent_id));
+        }
+        output.push_str(&format!("\n{}\n\n---\n\n", memory.content));
+    }
+    output
+}
+
+/// Fill `template` once per result, join blocks with blank lines, then cut to
+/// `max_tokens * CHARS_PER_TOKEN` bytes (on a char boundary) when a budget is given
+fn render_context(results: &[SearchResult], template: &str, max_tokens: Option<usize>) -> String {
+    let mut context = results
+        .iter()
+        .map(|result| {
+            template
+                .replace("{id}", &result.memory.id)
+                .replace("{score}", &format!("{:.2}", result.score))
+                .replace("{tags}", &result.memory.metadata.tags.join(", "))
+                .replace("{content}", &result.memory.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    if let Some(max_tokens) = max_tokens {
+        let mut limit = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+        if context.len() > limit {
+            while !context.is_char_boundary(limit) {
+                limit -= 1;
+            }
+            context.truncate(limit);
+        }
+    }
+
+    context
+}
+
+/// One `<kind> <value>: <count>` line per entry, most frequent first then by value
+pub fn format_extended_stats(extended: &ExtendedStats) -> String {
+    fn lines<K: std::fmt::Display>(
+        text: &mut String,
+        kind: &str,
+        counts: impl Iterator<Item = (K, usize)>,
+    ) {
+        let mut counts: Vec<(String, usize)> = counts
+            .map(|(key, count)| (key.to_string(), count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        for (key, count) in counts {
+            text.push_str(&format!("{} {}: {}\n", kind, key, count));
+        }
+    }
+
+    let mut text = String::new();
+    lines(
+        &mut text,
+        "Tag",
+        extended.by_tag.iter().map(|(k, v)| (k, *v)),
+    );
+    lines(
+        &mut text,
+        "Language",
+        extended.by_language.iter().map(|(k, v)| (k, *v)),
+    );
+    lines(
+        &mut text,
+        "Source file",
+        extended
+            .by_source_file
+            .iter()
+            .map(|(k, v)| (k.display(), *v)),
+    );
+    lines(
+        &mut text,
+        "AST node type",
+        extended.by_ast_node_type.iter().map(|(k, v)| (k, *v)),
+    );
+    text
+}
+
+/// Compile glob patterns into one matcher; None when no patterns are given
+pub fn build_globset(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob: {}", pattern))?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Importance boost factor for a search, 0.0 when boosting is switched off
+fn importance_boost(config: &Config, enabled: bool) -> f32 {
+    if enabled {
+        config.search.importance_boost_factor
+    } else {
+        0.0
+    }
+}
+
+/// Resolve a scope name (+ `project_path` argument for project scope, optional
+/// `session_name` for session scope) from tool arguments
+fn parse_scope(scope_str: &str, args: &Value) -> Result<MemoryScope> {
+    match scope_str {
+        "session" => Ok(MemoryScope::Session {
+            name: args["session_name"].as_str().map(str::to_string),
+        }
+        .normalize()),
+        "global" => Ok(MemoryScope::Global),
+        "project" => {
+            let path = args["project_path"]
+                .as_str()
+                .context("Missing project_path for project scope")?;
+            Ok(MemoryScope::Project {
+                path: PathBuf::from(path),
+            }
+            .normalize())
+        }
+        _ => Err(McpError::new(
+            McpErrorCode::InvalidScope,
+            format!("Invalid scope: {}", scope_str),
+        )
+        .into()),
+    }
+}
+
+/// Error answer for a request that outlived `request_timeout_ms`
+pub fn timeout_response(id: Option<RequestId>) -> JsonRpcResponse {
+    warn!("Request timed out");
+    JsonRpcResponse::error(
+        id,
+        McpErrorCode::InternalError,
+        "Request timed out".to_string(),
+    )
+}
+
+/// Write the notifications queued while handling a request, one JSON line each
+fn write_notifications(
+    stdout: &mut impl Write,
+    framing: FramingMode,
+    notifications: &StdioNotifications,
+) -> Result<()> {
+    for notification in notifications.take() {
+        write_message(stdout, framing, &serde_json::to_string(&notification)?)?;
+    }
+    Ok(())
+}
+
+/// Next message from `reader` in `framing`; None at end of input. Content-Length
+/// framing reads header lines up to the blank separator (ignoring headers other than
+/// `Content-Length`), then exactly that many bytes.
+fn read_message(
+    reader: &mut impl BufRead,
+    framing: FramingMode,
+) -> std::io::Result<Option<String>> {
+    let mut line = String::new();
+    if framing == FramingMode::LineDelimited {
+        return Ok((reader.read_line(&mut line)? > 0).then_some(line));
+    }
+
+    let mut length = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            if length.is_none() {
+                return Ok(None);
+            }
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            // Blank lines between messages are not a separator yet
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let value = value.trim().parse::<usize>().map_err(|e| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid Content-Length {:?}: {}", value.trim(), e),
+                    )
+                })?;
+                length = Some(value);
+            }
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Write one message in `framing` and flush
+fn write_message(stdout: &mut impl Write, framing: FramingMode, message: &str) -> Result<()> {
+    match framing {
+        FramingMode::LineDelimited => writeln!(stdout, "{}", message)?,
+        FramingMode::ContentLength => write!(
+            stdout,
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        )?,
+    }
+    stdout.flush()?;
+    Ok(())
+}
 
-    fn handle_resources_list(&self) -> Result<Value> {
-        Ok(json!({ "resources": [] }))
+/// JSON-RPC code and message for a failed request: coded errors keep their own code,
+/// everything else is reported as an internal error
+fn error_code(e: &anyhow::Error) -> (McpErrorCode, String) {
+    if let Some(mcp_error) = e.downcast_ref::<McpError>() {
+        return (mcp_error.code, mcp_error.message.clone());
     }
+    if let Some(err @ MemoryStoreError::MemoryNotFound(_)) = e.downcast_ref::<MemoryStoreError>() {
+        return (McpErrorCode::MemoryNotFound, err.to_string());
+    }
+    (
+        McpErrorCode::InternalError,
+        format!("Internal error: {}", e),
+    )
+}
+
+/// Optional RFC 3339 timestamp argument `name`
+fn timestamp_arg(args: &Value, name: &str) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+    args[name]
+        .as_str()
+        .map(|s| {
+            chrono::DateTime::parse_from_rfc3339(s)
+                .map(|t| t.with_timezone(&chrono::Utc))
+                .with_context(|| format!("Invalid {}: {}", name, s))
+        })
+        .transpose()
+}
+
+/// Collect string items of an optional JSON array argument
+fn string_array(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl RequestHandler for McpServer {
+    fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        debug!("Handling method: {}", request.method);
+
+        let result = match request.method.as_str() {
+            "initialize" => self.handle_initialize(request.params),
+            "tools/list" => self.handle_tools_list(),
+            "tools/call" => self.handle_tools_call(request.params),
+            "resources/list" => self.handle_resources_list(),
+            "resources/read" => self.handle_resources_read(request.params),
+            _ => Err(McpError::new(
+                McpErrorCode::MethodNotFound,
+                format!("Method not found: {}", request.method),
+            )
+            .into()),
+        };
+
+        // Unknown methods share one label to keep cardinality bounded
+        let method_label = match request.method.as_str() {
+            "initialize" | "tools/list" | "tools/call" | "resources/list" | "resources/read" => {
+                request.method.as_str()
+            }
+            _ => "unknown",
+        };
+        metrics::REQUESTS_TOTAL
+            .with_label_values(&[method_label])
+            .inc();
 
-    fn handle_resources_read(&self, _params: Option<Value>) -> Result<Value> {
-        Err(anyhow::anyhow!("No resources available"))
+        match result {
+            Ok(value) => JsonRpcResponse::success(request.id, value),
+            Err(e) => {
+                error!("Error handling request: {}", e);
+                let (code, message) = error_code(&e);
+                JsonRpcResponse::error(request.id, code, message)
+            }
+        }
     }
 }
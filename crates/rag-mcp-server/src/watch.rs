@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use globset::GlobSet;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use rag_core::MemoryScope;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::server::{build_globset, McpServer};
+
+/// Keep `scope` in sync with the files under `directory` (expected canonical):
+/// created or modified files are re-chunked and re-stored, removed files are forgotten.
+/// Events arriving less than `debounce` apart are coalesced, so each file is synced
+/// once per burst of writes. Blocks until the watcher channel closes.
+pub fn watch(
+    mut server: McpServer,
+    directory: &Path,
+    scope: &MemoryScope,
+    patterns: &[String],
+    debounce: Duration,
+) -> Result<()> {
+    let globs = build_globset(patterns)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to create file watcher")?;
+    watcher
+        .watch(directory, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", directory.display()))?;
+    info!("Watching {} for changes", directory.display());
+
+    while let Ok(event) = rx.recv() {
+        let mut pending = BTreeSet::new();
+        collect_paths(event, &mut pending);
+        while let Ok(event) = rx.recv_timeout(debounce) {
+            collect_paths(event, &mut pending);
+        }
+
+        for path in pending {
+            if is_watched(directory, &path, globs.as_ref()) {
+                sync_file(&mut server, &path, scope);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_paths(event: notify::Result<Event>, pending: &mut BTreeSet<PathBuf>) {
+    match event {
+        Ok(event) => {
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                pending.extend(event.paths);
+            }
+        }
+        Err(e) => warn!("Watch error: {}", e),
+    }
+}
+
+/// Same filter as `ingest_directory`: no hidden entries, globs match the relative path
+fn is_watched(directory: &Path, path: &Path, globs: Option<&GlobSet>) -> bool {
+    let Ok(relative) = path.strip_prefix(directory) else {
+        return false;
+    };
+    if relative
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    {
+        return false;
+    }
+    globs.is_none_or(|globs| globs.is_match(relative))
+}
+
+/// The file's current state decides the action, so a burst ending in a delete forgets it
+fn sync_file(server: &mut McpServer, path: &Path, scope: &MemoryScope) {
+    if path.is_file() {
+        match server.reingest_file(path, scope, &[]) {
+            Ok(chunks) => info!("Ingested {} ({} chunks)", path.display(), chunks),
+            Err(e) => error!("Failed to ingest {}: {}", path.display(), e),
+        }
+    } else if !path.exists() {
+        match server.forget_file(path, scope) {
+            Ok(0) => {}
+            Ok(removed) => info!("Removed {} ({} chunks)", path.display(), removed),
+            Err(e) => error!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+    if let Err(e) = server.save_search_indices() {
+        warn!("Failed to save search index: {}", e);
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Run `rag-mcp --config <config> health` with an empty default config dir
+fn health_with_config(root: &Path, config: &Path) -> Result<Output> {
+    Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+        .arg("--config")
+        .arg(config)
+        .arg("health")
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("RAG_MCP_DB_PATH", root.join("default-db"))
+        .env_remove("RUST_LOG")
+        .output()
+        .context("Failed to run rag-mcp health")
+}
+
+#[test]
+fn test_config_flag_overrides_default_location() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-config-flag-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root)?;
+
+    let custom_db = root.join("custom").join("global.db");
+    std::fs::create_dir_all(root.join("custom"))?;
+    let config = root.join("ci.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[server]\nlog_level = \"warn\"\n\n[search]\n\n[chunking]\nmax_chunk_size = 128\n\n[storage]\nglobal_db_path = {:?}\n",
+            custom_db.to_str().unwrap()
+        ),
+    )?;
+
+    let output = health_with_config(&root, &config)?;
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let report: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(report["status"], "ok");
+    assert!(custom_db.exists(), "database opened at the configured path");
+    assert!(!root.join("default-db").join("global.db").exists());
+
+    // An explicit config file that does not exist is an error, not the defaults
+    let output = health_with_config(&root, &root.join("missing.toml"))?;
+    assert_ne!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.toml"));
+
+    std::fs::remove_dir_all(&root)?;
+    Ok(())
+}
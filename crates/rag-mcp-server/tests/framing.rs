@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn framed(message: &Value) -> String {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// Split `Content-Length` framed output into message bodies, checking each length
+fn unframe(mut output: &[u8]) -> Result<Vec<Value>> {
+    let mut messages = Vec::new();
+    while !output.is_empty() {
+        let separator = output
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .context("Missing header separator")?;
+        let header = std::str::from_utf8(&output[..separator])?;
+        let length: usize = header
+            .strip_prefix("Content-Length: ")
+            .with_context(|| format!("Unexpected header {:?}", header))?
+            .parse()?;
+        let body = &output[separator + 4..];
+        anyhow::ensure!(body.len() >= length, "Body shorter than Content-Length");
+        messages.push(serde_json::from_slice(&body[..length])?);
+        output = &body[length..];
+    }
+    Ok(messages)
+}
+
+#[test]
+fn test_content_length_framing_round_trip() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-framing-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let config_dir = root.join("config").join("rag-mcp");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(
+        config_dir.join("config.toml"),
+        "[server]\ntransport_framing = \"content_length\"\n[search]\n[chunking]\n[storage]\n",
+    )?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+        .arg("serve")
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("RAG_MCP_DB_PATH", root.join("db"))
+        .env_remove("RUST_LOG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to spawn MCP server")?;
+
+    let mut stdin = child.stdin.take().context("Failed to take stdin")?;
+    // Non-ASCII content makes the byte count differ from the character count
+    let requests = [
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": {
+                "name": "store_memory",
+                "arguments": {"content": "Größe über alles ✓", "scope": "session"}
+            }
+        }),
+    ];
+    for request in &requests {
+        stdin.write_all(framed(request).as_bytes())?;
+    }
+    // Extra headers are ignored
+    let body = json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"}).to_string();
+    write!(
+        stdin,
+        "Content-Type: application/vscode-jsonrpc; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    drop(stdin);
+
+    let output = child.wait_with_output()?;
+    let messages = unframe(&output.stdout)?;
+    // Event notifications are framed like responses
+    let (notifications, responses): (Vec<Value>, Vec<Value>) =
+        messages.into_iter().partition(|m| m["id"].is_null());
+    assert_eq!(notifications.len(), 1, "Got: {:?}", notifications);
+    assert_eq!(notifications[0]["method"], "notifications/memory_stored");
+    assert_eq!(responses.len(), 3, "Got: {:?}", responses);
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[0]["result"]["serverInfo"]["name"], "rag-mcp");
+    assert_eq!(responses[1]["id"], 2);
+    let text = responses[1]["result"]["content"][0]["text"]
+        .as_str()
+        .unwrap();
+    assert!(text.contains("Memory stored successfully"), "Got: {}", text);
+    assert_eq!(responses[2]["id"], 3);
+    assert!(responses[2]["result"]["tools"].is_array());
+
+    std::fs::remove_dir_all(&root)?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Run `rag-mcp health` with an isolated config dir and database directory
+fn health(root: &Path, db_dir: &Path) -> Result<(Output, Value)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+        .arg("health")
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("RAG_MCP_DB_PATH", db_dir)
+        .env_remove("RUST_LOG")
+        .output()
+        .context("Failed to run rag-mcp health")?;
+    let report = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("health did not print JSON: {:?}", output))?;
+    Ok((output, report))
+}
+
+#[test]
+fn test_health_reports_database_access() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-health-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+
+    let db_dir = root.join("db");
+    std::fs::create_dir_all(&db_dir)?;
+    let (output, report) = health(&root, &db_dir)?;
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(report["status"], "ok");
+    assert_eq!(report["global_memories"], 0);
+
+    // A directory where the database file should be cannot be opened
+    let broken_dir = root.join("broken");
+    std::fs::create_dir_all(broken_dir.join("global.db"))?;
+    let (output, report) = health(&root, &broken_dir)?;
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(report["status"], "error");
+
+    std::fs::remove_dir_all(&root)?;
+    Ok(())
+}
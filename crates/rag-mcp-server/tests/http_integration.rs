@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use serial_test::serial;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Minimal HTTP MCP client - speaks raw HTTP/1.1 to `rag-mcp serve-http`
+///
+/// 1. Spawns the server on a free localhost port
+/// 2. Sends each JSON-RPC request as `POST /mcp` with `Connection: close`
+/// 3. Opens `GET /mcp/events` as a long-lived SSE stream when needed
+struct HttpMcpClient {
+    child: Child,
+    port: u16,
+    request_id: u64,
+}
+
+impl HttpMcpClient {
+    /// Spawn HTTP server and wait until the port accepts connections
+    fn spawn() -> Result<Self> {
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+
+        let test_db_dir =
+            std::env::temp_dir().join(format!("rag-mcp-http-test-{}-{}", std::process::id(), port));
+        std::fs::create_dir_all(&test_db_dir)?;
+
+        let child = Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+            .args(["serve-http", "--port", &port.to_string()])
+            .env("RAG_MCP_DB_PATH", test_db_dir.to_str().unwrap())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to spawn MCP HTTP server")?;
+
+        let client = Self {
+            child,
+            port,
+            request_id: 0,
+        };
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            if Instant::now() > deadline {
+                anyhow::bail!("HTTP server did not start on port {}", port);
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        Ok(client)
+    }
+
+    /// POST a raw body to /mcp, return (status code, response body)
+    fn post(&self, body: &str) -> Result<(u16, String)> {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port))?;
+        write!(
+            stream,
+            "POST /mcp HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+
+        Self::read_response(stream)
+    }
+
+    /// GET a path, return (status code, response body)
+    fn get(&self, path: &str) -> Result<(u16, String)> {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port))?;
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        )?;
+
+        Self::read_response(stream)
+    }
+
+    fn read_response(mut stream: TcpStream) -> Result<(u16, String)> {
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw)?;
+
+        let (head, body) = raw
+            .split_once("\r\n\r\n")
+            .context("Malformed HTTP response")?;
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .context("Missing HTTP status")?;
+
+        Ok((status, body.to_string()))
+    }
+
+    /// Send JSON-RPC request and return its `result`
+    fn send_request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.request_id += 1;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.request_id,
+            "method": method,
+            "params": params,
+        });
+
+        let (status, body) = self.post(&request.to_string())?;
+        anyhow::ensure!(status == 200, "Unexpected HTTP status {}", status);
+
+        let response: Value =
+            serde_json::from_str(&body).context(format!("Failed to parse response: {}", body))?;
+        assert_eq!(response["id"], json!(self.request_id));
+
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("MCP error: {}", error);
+        }
+
+        response
+            .get("result")
+            .cloned()
+            .context("No result in response")
+    }
+
+    fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value> {
+        self.send_request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+    }
+
+    /// Open SSE stream; returns a reader positioned after the response headers
+    fn open_events(&self) -> Result<BufReader<TcpStream>> {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        write!(
+            stream,
+            "GET /mcp/events HTTP/1.1\r\nHost: localhost\r\nAccept: text/event-stream\r\n\r\n"
+        )?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            if line == "\r\n" || line.is_empty() {
+                break;
+            }
+        }
+        Ok(reader)
+    }
+}
+
+impl Drop for HttpMcpClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[test]
+#[serial]
+fn test_http_initialize_and_tools_call() -> Result<()> {
+    let mut client = HttpMcpClient::spawn()?;
+
+    let init = client.send_request(
+        "initialize",
+        json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "http-test-client", "version": "0.0.0"}
+        }),
+    )?;
+    assert_eq!(init["protocolVersion"], "2024-11-05");
+
+    // Client notifications are accepted without a JSON-RPC response
+    let (status, body) = client
+        .post(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}).to_string())?;
+    assert_eq!(status, 202);
+    assert!(body.is_empty());
+
+    client.call_tool(
+        "store_memory",
+        json!({
+            "content": "HTTP transport stores memories too",
+            "scope": "session",
+            "tags": ["http"]
+        }),
+    )?;
+
+    let result = client.call_tool(
+        "search_memory",
+        json!({"query": "transport", "scope": "session", "k": 5}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 1 results"), "Got: {}", text);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_http_sse_memory_stored_event() -> Result<()> {
+    let mut client = HttpMcpClient::spawn()?;
+    let mut events = client.open_events()?;
+
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Broadcast me", "scope": "session", "tags": ["sse"]}),
+    )?;
+
+    // SSE frames arrive chunk-encoded; scan lines until the data payload appears
+    let mut line = String::new();
+    let event = loop {
+        line.clear();
+        anyhow::ensure!(events.read_line(&mut line)? > 0, "SSE stream closed");
+        if let Some(data) = line.trim().strip_prefix("data: ") {
+            break serde_json::from_str::<Value>(data)?;
+        }
+    };
+
+    assert_eq!(event["method"], "notifications/memory_stored");
+    assert_eq!(event["params"]["tags"], json!(["sse"]));
+    assert!(event["params"]["id"].is_string());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_http_metrics_endpoint() -> Result<()> {
+    let mut client = HttpMcpClient::spawn()?;
+
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Observable memory", "scope": "session", "tags": []}),
+    )?;
+    client.call_tool(
+        "search_memory",
+        json!({"query": "observable", "scope": "session"}),
+    )?;
+
+    let (status, body) = client.get("/metrics")?;
+    assert_eq!(status, 200);
+    for name in [
+        "rag_mcp_memories_total{scope=\"session\"} 1",
+        "rag_mcp_search_duration_seconds",
+        "rag_mcp_store_duration_seconds",
+        "rag_mcp_search_results_returned",
+        "rag_mcp_requests_total{method=\"tools/call\"} 2",
+    ] {
+        assert!(body.contains(name), "Missing {} in:\n{}", name, body);
+    }
+
+    Ok(())
+}
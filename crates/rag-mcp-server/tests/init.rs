@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use rag_core::config::Config;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn init(output: &Path, force: bool) -> Result<Output> {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rag-mcp"));
+    command.arg("init").arg("--output").arg(output);
+    if force {
+        command.arg("--force");
+    }
+    command
+        .env_remove("RAG_MCP_DB_PATH")
+        .env_remove("RUST_LOG")
+        .output()
+        .context("Failed to run rag-mcp init")
+}
+
+#[test]
+fn test_init_writes_defaults_and_refuses_to_overwrite() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-init-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let path = root.join("nested").join("config.toml");
+
+    let output = init(&path, false)?;
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+
+    let written = std::fs::read_to_string(&path)?;
+    assert!(written.contains("# "), "template carries comments");
+    let loaded = Config::load_from(&path)?;
+    assert_eq!(
+        serde_json::to_value(&loaded)?,
+        serde_json::to_value(Config::default())?
+    );
+
+    // A second run must not clobber edits
+    std::fs::write(&path, "[server]\n[search]\n[chunking]\n[storage]\n")?;
+    let output = init(&path, false)?;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        "[server]\n[search]\n[chunking]\n[storage]\n"
+    );
+
+    let output = init(&path, true)?;
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(std::fs::read_to_string(&path)?, written);
+
+    std::fs::remove_dir_all(&root)?;
+    Ok(())
+}
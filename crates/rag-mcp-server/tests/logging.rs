@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::process::Command;
+
+/// Run `rag-mcp add` against a throwaway config dir with `log_format = "json"`
+/// Returns (stderr, log file contents)
+fn run_with_json_logging() -> Result<(String, String)> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-logging-test-{}", std::process::id()));
+    let config_dir = root.join("rag-mcp");
+    std::fs::create_dir_all(&config_dir)?;
+
+    let log_file = root.join("rag-mcp.log");
+    let _ = std::fs::remove_file(&log_file);
+    std::fs::write(
+        config_dir.join("config.toml"),
+        format!(
+            "[server]\nlog_format = \"json\"\nlog_file = {:?}\n\n[search]\n\n[chunking]\n\n[storage]\nglobal_db_path = {:?}\n",
+            log_file,
+            root.join("db")
+        ),
+    )?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+        .args([
+            "add",
+            "--content",
+            "Structured logs are parseable",
+            "--scope",
+            "global",
+        ])
+        .env("XDG_CONFIG_HOME", &root)
+        .env("RAG_MCP_DB_PATH", root.join("db"))
+        .env_remove("RUST_LOG")
+        .output()
+        .context("Failed to run rag-mcp add")?;
+    anyhow::ensure!(output.status.success(), "rag-mcp add failed: {:?}", output);
+
+    let stderr = String::from_utf8(output.stderr)?;
+    let file = std::fs::read_to_string(&log_file).context("Log file was not written")?;
+    Ok((stderr, file))
+}
+
+fn assert_json_lines(logs: &str) {
+    let lines: Vec<&str> = logs.lines().filter(|l| !l.trim().is_empty()).collect();
+    assert!(!lines.is_empty(), "No log lines emitted");
+
+    for line in lines {
+        let entry: Value = serde_json::from_str(line)
+            .unwrap_or_else(|e| panic!("Invalid JSON log line {:?}: {}", line, e));
+        for field in ["timestamp", "level", "target", "message"] {
+            assert!(entry.get(field).is_some(), "Missing {} in {}", field, line);
+        }
+    }
+}
+
+#[test]
+fn test_json_log_format_to_stderr_and_file() -> Result<()> {
+    let (stderr, file) = run_with_json_logging()?;
+
+    assert_json_lines(&stderr);
+    assert_json_lines(&file);
+    assert!(file.contains("Memory stored with ID"), "Got: {}", file);
+
+    Ok(())
+}
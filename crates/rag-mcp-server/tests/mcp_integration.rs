@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
 use serde_json::{json, Value};
 use serial_test::serial;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Mock Zed/Claude Code client - accurately simulates MCP protocol over stdio
 ///
@@ -19,11 +21,18 @@ struct ZedMcpClient {
     child: Child,
     request_id: u64,
     reader: Arc<Mutex<BufReader<std::process::ChildStdout>>>,
+    /// The server's `RAG_MCP_DB_PATH`, holding `global.db`
+    db_dir: std::path::PathBuf,
 }
 
 impl ZedMcpClient {
     /// Spawn MCP server and perform initialization handshake
     fn spawn() -> Result<Self> {
+        Self::spawn_with_config(None)
+    }
+
+    /// Like `spawn`, but with `config_toml` as the server's config file when given
+    fn spawn_with_config(config_toml: Option<&str>) -> Result<Self> {
         // Use test-specific database directory to avoid conflicts with running servers
         // Use random ID for uniqueness across concurrent instances
         use std::sync::atomic::{AtomicU64, Ordering};
@@ -37,7 +46,15 @@ impl ZedMcpClient {
         ));
         std::fs::create_dir_all(&test_db_dir)?;
 
-        let mut child = Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rag-mcp"));
+        if let Some(config_toml) = config_toml {
+            let config_home = test_db_dir.join("config");
+            std::fs::create_dir_all(config_home.join("rag-mcp"))?;
+            std::fs::write(config_home.join("rag-mcp").join("config.toml"), config_toml)?;
+            command.env("XDG_CONFIG_HOME", config_home);
+        }
+
+        let mut child = command
             .arg("serve")
             .env("RAG_MCP_DB_PATH", test_db_dir.to_str().unwrap())
             .stdin(Stdio::piped())
@@ -53,10 +70,8 @@ impl ZedMcpClient {
         if let Some(stderr) = child.stderr.take() {
             thread::spawn(move || {
                 let reader = BufReader::new(stderr);
-                for line in reader.lines() {
-                    if let Ok(line) = line {
-                        eprintln!("[MCP SERVER] {}", line);
-                    }
+                for line in reader.lines().map_while(Result::ok) {
+                    eprintln!("[MCP SERVER] {}", line);
                 }
             });
         }
@@ -68,6 +83,7 @@ impl ZedMcpClient {
             child,
             request_id: 0,
             reader,
+            db_dir: test_db_dir,
         };
 
         // Perform MCP initialization handshake
@@ -115,15 +131,25 @@ impl ZedMcpClient {
     /// Send JSON-RPC request and wait for response
     fn send_request(&mut self, method: &str, params: Option<Value>) -> Result<Value> {
         self.request_id += 1;
+        self.send_request_with_id(json!(self.request_id), method, params)
+    }
+
+    /// Send a JSON-RPC request with an explicit id, which may be a string or a number
+    fn send_request_with_id(
+        &mut self,
+        id: Value,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Value> {
         let request = json!({
             "jsonrpc": "2.0",
-            "id": self.request_id,
+            "id": id,
             "method": method,
             "params": params.unwrap_or(json!({})),
         });
 
         self.write_message(&request)?;
-        self.read_response(self.request_id)
+        self.read_response(&id)
     }
 
     /// Send JSON-RPC notification (no response expected)
@@ -151,20 +177,27 @@ impl ZedMcpClient {
     }
 
     /// Read response from server stdout (blocking, with simple timeout via channel)
-    fn read_response(&mut self, expected_id: u64) -> Result<Value> {
+    fn read_response(&mut self, expected_id: &Value) -> Result<Value> {
         // Simple blocking read - server should respond quickly
         let mut reader = self.reader.lock().unwrap();
-        let mut line = String::new();
-        reader
-            .read_line(&mut line)
-            .context("Failed to read response from server")?;
-
-        let response: Value = serde_json::from_str(line.trim())
-            .context(format!("Failed to parse response: {}", line.trim()))?;
+        let response = loop {
+            let mut line = String::new();
+            reader
+                .read_line(&mut line)
+                .context("Failed to read response from server")?;
+
+            let message: Value = serde_json::from_str(line.trim())
+                .context(format!("Failed to parse response: {}", line.trim()))?;
+            // Skip server notifications (method, no id), as Zed does
+            if message.get("method").is_none() {
+                break message;
+            }
+        };
 
         // Verify this is the response we're waiting for
+        // String ids must come back as the same string, never coerced to a number
         if let Some(id) = response.get("id") {
-            if id.as_u64() != Some(expected_id) {
+            if id != expected_id {
                 anyhow::bail!("Response ID mismatch: expected {}, got {}", expected_id, id);
             }
         }
@@ -391,6 +424,7 @@ fn test_list_memories_with_pagination() -> Result<()> {
         text.contains("Found 3 memories"),
         "Expected 3 memories in first page"
     );
+    assert_eq!(result["total"], 5);
 
     // List with offset
     let result = client.call_tool(
@@ -407,6 +441,7 @@ fn test_list_memories_with_pagination() -> Result<()> {
         text.contains("Found 2 memories"),
         "Expected 2 memories in second page"
     );
+    assert_eq!(result["total"], 5);
 
     Ok(())
 }
@@ -514,6 +549,48 @@ fn test_clear_session_scope() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[serial]
+fn test_named_sessions_are_isolated() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for (session_name, content) in [
+        ("alice", "Alice prefers tabs for indentation"),
+        ("bob", "Bob prefers spaces for indentation"),
+    ] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "session", "session_name": session_name}),
+        )?;
+    }
+
+    let search = |client: &mut ZedMcpClient, session_name: Option<&str>| -> Result<String> {
+        let mut args = json!({"query": "indentation", "scope": "session"});
+        if let Some(name) = session_name {
+            args["session_name"] = json!(name);
+        }
+        let result = client.call_tool("search_memory", args)?;
+        Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+    };
+
+    let alice = search(&mut client, Some("alice"))?;
+    assert!(
+        alice.contains("tabs") && !alice.contains("spaces"),
+        "{}",
+        alice
+    );
+    let bob = search(&mut client, Some("bob"))?;
+    assert!(bob.contains("spaces") && !bob.contains("tabs"), "{}", bob);
+    let default = search(&mut client, None)?;
+    assert!(!default.contains("prefers"), "{}", default);
+
+    client.call_tool("clear_session", json!({"session_name": "alice"}))?;
+    assert!(!search(&mut client, Some("alice"))?.contains("tabs"));
+    assert!(search(&mut client, Some("bob"))?.contains("spaces"));
+
+    Ok(())
+}
+
 #[test]
 #[serial]
 fn test_tags_storage_and_display() -> Result<()> {
@@ -709,3 +786,1837 @@ fn test_bm25_stop_words_filtering() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[serial]
+fn test_ingest_directory_source_file_metadata() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    client.call_tool("clear_session", json!({}))?;
+
+    let dir = std::env::temp_dir().join(format!("rag-mcp-ingest-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src"))?;
+    std::fs::write(
+        dir.join("src/main.rs"),
+        "fn main() {\n    println!(\"hi\");\n}\n",
+    )?;
+    std::fs::write(
+        dir.join("src/lib.rs"),
+        "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n",
+    )?;
+    std::fs::write(
+        dir.join("README.md"),
+        "# Ingest test\n\nSome documentation.\n",
+    )?;
+    std::fs::write(dir.join("notes.txt"), "Not matched by any glob")?;
+
+    let result = client.call_tool(
+        "ingest_directory",
+        json!({
+            "directory_path": dir.to_str().unwrap(),
+            "scope": "session",
+            "glob_patterns": ["**/*.rs", "**/*.md"],
+            "tags": ["ingested"],
+            "recursive": true
+        }),
+    )?;
+
+    let summary: Value = serde_json::from_str(result["content"][0]["text"].as_str().unwrap())?;
+    assert_eq!(summary["files_processed"], 3, "Summary: {}", summary);
+    assert_eq!(summary["chunks_created"], 3, "Summary: {}", summary);
+    assert!(summary["errors"].as_array().unwrap().is_empty());
+
+    let list = client.call_tool(
+        "list_memories",
+        json!({"scope": "session", "limit": 10, "offset": 0}),
+    )?;
+    let text = list["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 3 memories"), "Got: {}", text);
+    for file in ["src/main.rs", "src/lib.rs", "README.md"] {
+        let expected = format!("Source: {}", dir.join(file).display());
+        assert!(
+            text.contains(&expected),
+            "Missing {}. Got: {}",
+            expected,
+            text
+        );
+    }
+    assert!(!text.contains("notes.txt"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_cross_scope_search_dedups_across_scopes() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    client.call_tool("clear_session", json!({}))?;
+
+    for scope in ["session", "global"] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": "Rust ownership rules", "scope": scope, "tags": []}),
+        )?;
+    }
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Rust borrow checker explained", "scope": "global", "tags": []}),
+    )?;
+
+    let result = client.call_tool(
+        "cross_scope_search",
+        json!({"query": "rust", "k": 5, "weights": {"global": 2.0}}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+
+    assert!(text.contains("Found 2 results"), "Got: {}", text);
+    assert_eq!(
+        text.matches("Rust ownership rules").count(),
+        1,
+        "Got: {}",
+        text
+    );
+    // Global weight doubles the score, so the global copy wins the dedup
+    assert!(!text.contains("Scope: session"), "Got: {}", text);
+    assert_eq!(text.matches("Scope: global").count(), 2, "Got: {}", text);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_get_context_formats_and_truncates() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    client.call_tool("clear_session", json!({}))?;
+
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "Context blocks wrap memory content", "scope": "session", "tags": ["ctx"]}),
+    )?;
+    let id = stored_id(&stored)?;
+
+    let result = client.call_tool(
+        "get_context",
+        json!({"query": "context", "scope": "session"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.starts_with(&format!("<memory id=\"{}\" score=\"", id)),
+        "Got: {}",
+        text
+    );
+    assert!(
+        text.ends_with("\">\nContext blocks wrap memory content\n</memory>"),
+        "Got: {}",
+        text
+    );
+
+    let result = client.call_tool(
+        "get_context",
+        json!({"query": "context", "scope": "session", "template": "[{tags}] {content}"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert_eq!(text, "[ctx] Context blocks wrap memory content");
+
+    let result = client.call_tool(
+        "get_context",
+        json!({"query": "context", "scope": "session", "max_tokens": 5}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert_eq!(text.len(), 20, "Got: {}", text);
+    assert!(text.starts_with("<memory id="), "Got: {}", text);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_list_memories_cursor_pagination() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    client.call_tool("clear_session", json!({}))?;
+
+    let mut stored = Vec::new();
+    for i in 0..10 {
+        let result = client.call_tool(
+            "store_memory",
+            json!({"content": format!("Page item {}", i), "scope": "session", "tags": []}),
+        )?;
+        stored.push(stored_id(&result)?);
+    }
+
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut pages = 0;
+    loop {
+        let mut args = json!({"scope": "session", "limit": 3});
+        if let Some(cursor) = &cursor {
+            args["after_id"] = json!(cursor);
+        }
+        let result = client.call_tool("list_memories", args)?;
+        let text = result["content"][0]["text"].as_str().unwrap();
+        seen.extend(
+            text.lines()
+                .filter_map(|line| line.strip_prefix("ID: "))
+                .map(|rest| rest.split_whitespace().next().unwrap().to_string()),
+        );
+        pages += 1;
+
+        if !result["has_more"].as_bool().unwrap() {
+            assert!(result["next_cursor"].is_null());
+            break;
+        }
+        cursor = Some(result["next_cursor"].as_str().unwrap().to_string());
+        assert_eq!(seen.last(), cursor.as_ref());
+    }
+
+    assert_eq!(pages, 4);
+    assert_eq!(seen.len(), 10, "Got: {:?}", seen);
+    seen.sort();
+    seen.dedup();
+    stored.sort();
+    assert_eq!(seen, stored);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_store_memory_reuses_cached_embedding() -> Result<()> {
+    // Ollama stand-in: answers every request with the same embedding and counts them
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            counter.fetch_add(1, Ordering::SeqCst);
+            let response = r#"{"embedding": [0.1, 0.2, 0.3]}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    });
+
+    let config = format!(
+        "[server]\n[search]\n[chunking]\n[storage]\n\n[embedder.provider.ollama]\nbase_url = \"{}\"\nmodel = \"test-embed\"\n",
+        base_url
+    );
+    let mut client = ZedMcpClient::spawn_with_config(Some(&config))?;
+
+    for _ in 0..2 {
+        client.call_tool(
+            "store_memory",
+            json!({"content": "Embed me once", "scope": "session", "tags": []}),
+        )?;
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Different content", "scope": "session", "tags": []}),
+    )?;
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_store_memory_validates_custom_metadata() -> Result<()> {
+    let schema_path =
+        std::env::temp_dir().join(format!("rag-mcp-test-schema-{}.json", std::process::id()));
+    std::fs::write(
+        &schema_path,
+        r#"{"type": "object", "required": ["author"], "properties": {"author": {"type": "string"}}}"#,
+    )?;
+    let config = format!(
+        "[server]\n[search]\n[chunking]\n[storage]\ncustom_metadata_schema = {:?}\n",
+        schema_path
+    );
+    let mut client = ZedMcpClient::spawn_with_config(Some(&config))?;
+
+    let rejected = client.call_tool(
+        "store_memory",
+        json!({"content": "Anonymous note", "scope": "session", "custom": {"topic": "x"}}),
+    )?;
+    assert_eq!(rejected["isError"], true);
+    let text = rejected["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("author"), "{}", text);
+
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "Signed note", "scope": "session", "custom": {"author": "ada"}}),
+    )?;
+    assert!(stored_id(&stored).is_ok());
+
+    std::fs::remove_file(&schema_path)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_tools_list_advertises_custom_metadata_schema() -> Result<()> {
+    let schema = json!({
+        "type": "object",
+        "required": ["author"],
+        "properties": {"author": {"type": "string", "description": "Who wrote it"}}
+    });
+    let schema_path = std::env::temp_dir().join(format!(
+        "rag-mcp-test-tools-schema-{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&schema_path, serde_json::to_string(&schema)?)?;
+    let config = format!(
+        "[server]\n[search]\n[chunking]\n[storage]\ncustom_metadata_schema = {:?}\n",
+        schema_path
+    );
+    let mut client = ZedMcpClient::spawn_with_config(Some(&config))?;
+
+    let tools = client.list_tools()?;
+    let store_memory = tools
+        .iter()
+        .find(|t| t["name"] == "store_memory")
+        .context("store_memory not listed")?;
+    assert_eq!(store_memory["inputSchema"]["properties"]["custom"], schema);
+
+    std::fs::remove_file(&schema_path)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_structured_responses_add_json_item() -> Result<()> {
+    let mut plain = ZedMcpClient::spawn()?;
+    let stored = plain.call_tool(
+        "store_memory",
+        json!({"content": "Plain response", "scope": "session"}),
+    )?;
+    assert_eq!(stored["content"].as_array().unwrap().len(), 1);
+    drop(plain);
+
+    let config = "[server]\nstructured_responses = true\n[search]\n[chunking]\n[storage]\n";
+    let mut client = ZedMcpClient::spawn_with_config(Some(config))?;
+
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "Structured lifetimes note", "scope": "session", "tags": ["rust"]}),
+    )?;
+    let id = stored_id(&stored)?;
+    assert_eq!(stored["content"][1]["type"], "json");
+    assert_eq!(stored["content"][1]["json"]["id"], id.as_str());
+
+    let found = client.call_tool(
+        "search_memory",
+        json!({"query": "lifetimes", "scope": "session"}),
+    )?;
+    assert_eq!(found["content"][0]["type"], "text");
+    let data = &found["content"][1]["json"];
+    assert_eq!(data["total_searched"], 1);
+    assert_eq!(data["results"][0]["id"], id.as_str());
+    assert_eq!(data["results"][0]["rank"], 0);
+    assert_eq!(data["results"][0]["tags"], json!(["rust"]));
+
+    let listed = client.call_tool("list_memories", json!({"scope": "session"}))?;
+    let data = &listed["content"][1]["json"];
+    assert_eq!(data["total"], 1);
+    assert_eq!(
+        data["memories"][0]["content_preview"],
+        "Structured lifetimes note"
+    );
+
+    let fetched = client.call_tool("get_memory", json!({"id": id, "scope": "session"}))?;
+    assert_eq!(fetched["content"][1]["json"]["memory"]["id"], id.as_str());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_search_memory_highlights_query_terms() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Rust ownership rules keep rust code safe", "scope": "session"}),
+    )?;
+
+    let found = client.call_tool(
+        "search_memory",
+        json!({"query": "rust", "scope": "session"}),
+    )?;
+    let text = found["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains("**Rust** ownership rules keep **rust** code safe"),
+        "Got: {}",
+        text
+    );
+    drop(client);
+
+    let config = "[server]\n[search]\nhighlight_prefix = \"<mark>\"\nhighlight_suffix = \"</mark>\"\n[chunking]\n[storage]\n";
+    let mut client = ZedMcpClient::spawn_with_config(Some(config))?;
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Rust ownership rules", "scope": "session"}),
+    )?;
+    let found = client.call_tool(
+        "search_memory",
+        json!({"query": "OWNERSHIP", "scope": "session"}),
+    )?;
+    let text = found["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains("Rust <mark>ownership</mark> rules"),
+        "Got: {}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_store_memory_reports_quota_exceeded() -> Result<()> {
+    let config = "[server]\n[search]\n[chunking]\n[storage]\nglobal_memory_quota = 3\n";
+    let mut client = ZedMcpClient::spawn_with_config(Some(config))?;
+
+    for i in 0..3 {
+        let stored = client.call_tool(
+            "store_memory",
+            json!({"content": format!("Quota note {}", i), "scope": "global"}),
+        )?;
+        stored_id(&stored)?;
+    }
+
+    let rejected = client.call_tool(
+        "store_memory",
+        json!({"content": "One note too many", "scope": "global"}),
+    )?;
+    assert_eq!(rejected["isError"], true);
+    let text = rejected["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("quota 3"), "Got: {}", text);
+    assert!(text.contains("global_memory_quota"), "Got: {}", text);
+
+    // Sessions have no quota
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "One note too many", "scope": "session"}),
+    )?;
+    stored_id(&stored)?;
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_find_by_hash_returns_stored_content() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let content = "Content-addressed note about WAL checkpoints";
+    for scope in ["session", "global"] {
+        let stored =
+            client.call_tool("store_memory", json!({"content": content, "scope": scope}))?;
+        let id = stored_id(&stored)?;
+        let hash = stored["content"][0]["text"]
+            .as_str()
+            .and_then(|text| text.split("content hash: ").nth(1))
+            .and_then(|rest| rest.strip_suffix(')'))
+            .context("Failed to extract content hash")?;
+        assert_eq!(hash.len(), 64);
+
+        let found = client.call_tool("find_by_hash", json!({"hash": hash, "scope": scope}))?;
+        let text = found["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains(&format!("ID: {}", id)), "Got: {}", text);
+        assert!(text.contains(content), "Got: {}", text);
+    }
+
+    let missing = client.call_tool(
+        "find_by_hash",
+        json!({"hash": "0".repeat(64), "scope": "global"}),
+    )?;
+    let text = missing["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains("No memory with content hash"),
+        "Got: {}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_random_memories_is_reproducible_with_seed() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for i in 0..20 {
+        client.call_tool(
+            "store_memory",
+            json!({"content": format!("Random sample candidate {}", i), "scope": "session"}),
+        )?;
+    }
+
+    let sample = |client: &mut ZedMcpClient, seed: u64| -> Result<String> {
+        let result = client.call_tool(
+            "random_memories",
+            json!({"scope": "session", "k": 5, "seed": seed}),
+        )?;
+        Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+    };
+    let first = sample(&mut client, 42)?;
+    assert_eq!(
+        first.matches("Random sample candidate").count(),
+        5,
+        "{}",
+        first
+    );
+    assert_eq!(first, sample(&mut client, 42)?);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_dedup_memories_keeps_oldest_copy() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let content = "Duplicate note about connection pooling";
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let stored = client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "session"}),
+        )?;
+        ids.push(stored_id(&stored)?);
+    }
+
+    let preview = client.call_tool(
+        "dedup_memories",
+        json!({"scope": "session", "dry_run": true}),
+    )?;
+    let text = preview["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Would delete 2 memories"), "Got: {}", text);
+
+    let result = client.call_tool("dedup_memories", json!({"scope": "session"}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 1 duplicate groups"), "Got: {}", text);
+    assert!(text.contains("Deleted 2 memories"), "Got: {}", text);
+
+    let kept = client.call_tool("get_memory", json!({"id": ids[0], "scope": "session"}))?;
+    let text = kept["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains(content), "Got: {}", text);
+
+    let search = client.call_tool(
+        "search_memory",
+        json!({"query": "connection pooling", "scope": "session"}),
+    )?;
+    let text = search["content"][0]["text"].as_str().unwrap();
+    assert_eq!(text.matches(&ids[0]).count(), 1, "Got: {}", text);
+    assert!(
+        !text.contains(&ids[1]) && !text.contains(&ids[2]),
+        "Got: {}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_get_extended_stats_counts_tags_and_languages() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for (content, tag, language) in [
+        ("fn main() {}", "entry", "rust"),
+        ("fn helper() {}", "util", "rust"),
+        ("def helper(): pass", "util", "python"),
+    ] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "session", "tags": [tag], "override_language": language}),
+        )?;
+    }
+
+    let result = client.call_tool("get_extended_stats", json!({"scope": "session"}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Total memories: 3"), "Got: {}", text);
+    assert!(text.contains("Tag util: 2"), "Got: {}", text);
+    assert!(text.contains("Tag entry: 1"), "Got: {}", text);
+    assert!(text.contains("Language rust: 2"), "Got: {}", text);
+    assert!(text.contains("Language python: 1"), "Got: {}", text);
+
+    // Off by default in get_stats
+    let stats = client.call_tool("get_stats", json!({"scope": "session"}))?;
+    let text = stats["content"][0]["text"].as_str().unwrap();
+    assert!(!text.contains("Tag util"), "Got: {}", text);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_batch_request_returns_array_of_responses() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    client.write_message(&json!([
+        {
+            "jsonrpc": "2.0",
+            "id": 100,
+            "method": "initialize",
+            "params": {"protocolVersion": "2024-11-05", "capabilities": {}, "clientInfo": {"name": "batch", "version": "1"}}
+        },
+        {"jsonrpc": "2.0", "method": "notifications/initialized", "params": {}},
+        {"jsonrpc": "2.0", "id": 101, "method": "tools/list", "params": {}}
+    ]))?;
+
+    let mut line = String::new();
+    client.reader.lock().unwrap().read_line(&mut line)?;
+    let responses: Value = serde_json::from_str(line.trim())?;
+    let responses = responses
+        .as_array()
+        .context("batch response is not an array")?;
+
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["id"], 100);
+    assert!(responses[0]["result"]["serverInfo"].is_object());
+    assert!(responses[0]["result"]["serverInfo"]["gitSha"].is_string());
+    assert!(responses[0]["result"]["serverInfo"]["buildTime"].is_string());
+    assert_eq!(responses[1]["id"], 101);
+    assert!(responses[1]["result"]["tools"].is_array());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_error_codes_name_the_failure() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let mut error_code = |name: &str, arguments: Value| -> Result<Value> {
+        client.write_message(&json!({
+            "jsonrpc": "2.0",
+            "id": 200,
+            "method": "tools/call",
+            "params": {"name": name, "arguments": arguments}
+        }))?;
+        let mut line = String::new();
+        client.reader.lock().unwrap().read_line(&mut line)?;
+        let response: Value = serde_json::from_str(line.trim())?;
+        Ok(response["error"]["code"].clone())
+    };
+
+    assert_eq!(error_code("no_such_tool", json!({}))?, -32000);
+    assert_eq!(
+        error_code("search_memory", json!({"query": "x", "scope": "galaxy"}))?,
+        -32001
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_string_request_id_is_echoed() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    client.write_message(&json!({
+        "jsonrpc": "2.0",
+        "id": "abc",
+        "method": "tools/list",
+        "params": {}
+    }))?;
+    let mut line = String::new();
+    client.reader.lock().unwrap().read_line(&mut line)?;
+    let response: Value = serde_json::from_str(line.trim())?;
+    assert_eq!(response["id"], json!("abc"));
+
+    let tools = client.send_request_with_id(json!("req-abc"), "tools/list", None)?;
+    assert!(tools["tools"].is_array());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_store_and_delete_push_notifications_before_response() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    let read_message = |client: &mut ZedMcpClient| -> Result<Value> {
+        let mut line = String::new();
+        client.reader.lock().unwrap().read_line(&mut line)?;
+        Ok(serde_json::from_str(line.trim())?)
+    };
+
+    client.write_message(&json!({
+        "jsonrpc": "2.0",
+        "id": 300,
+        "method": "tools/call",
+        "params": {
+            "name": "store_memory",
+            "arguments": {"content": "Notify me", "scope": "session", "tags": ["ui"]}
+        }
+    }))?;
+    let notification = read_message(&mut client)?;
+    assert_eq!(notification["method"], "notifications/memory_stored");
+    assert!(notification.get("id").is_none());
+    assert_eq!(notification["params"]["tags"], json!(["ui"]));
+    let response = read_message(&mut client)?;
+    assert_eq!(response["id"], 300);
+    let id = stored_id(&response["result"])?;
+    assert_eq!(notification["params"]["id"], json!(id));
+
+    client.write_message(&json!({
+        "jsonrpc": "2.0",
+        "id": 301,
+        "method": "tools/call",
+        "params": {"name": "delete_memory", "arguments": {"id": id, "scope": "session"}}
+    }))?;
+    let notification = read_message(&mut client)?;
+    assert_eq!(notification["method"], "notifications/memory_deleted");
+    assert_eq!(notification["params"]["id"], json!(id));
+    assert_eq!(read_message(&mut client)?["id"], 301);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_resources_list_and_read_memories() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let content = "Resource body that is definitely longer than sixty characters in total";
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": content, "scope": "session", "tags": ["a", "b"]}),
+    )?;
+    let id = stored_id(&stored)?;
+
+    let listed = client.send_request("resources/list", None)?;
+    let resources = listed["resources"].as_array().context("No resources")?;
+    let uri = format!("memory://session/{}", id);
+    let resource = resources
+        .iter()
+        .find(|r| r["uri"] == uri.as_str())
+        .context("Stored memory not listed")?;
+    assert_eq!(
+        resource["name"],
+        content.chars().take(60).collect::<String>()
+    );
+    assert_eq!(resource["description"], "a, b");
+    assert_eq!(resource["mimeType"], "text/plain");
+
+    let read = client.send_request("resources/read", Some(json!({"uri": uri})))?;
+    assert_eq!(read["contents"][0]["text"], content);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_ping_reports_ok() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let result = client.call_tool("ping", json!({}))?;
+    let status: Value = serde_json::from_str(result["content"][0]["text"].as_str().unwrap())?;
+    assert_eq!(status["status"], "ok");
+    assert_eq!(status["memories_reachable"], true);
+    assert!(status["timestamp"].is_string());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_slow_request_times_out() -> Result<()> {
+    // Embedding service that hangs far longer than the request timeout
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { break };
+            thread::sleep(Duration::from_secs(3));
+            drop(stream);
+        }
+    });
+
+    let config = format!(
+        "[server]\nrequest_timeout_ms = 300\n[search]\n[chunking]\n[storage]\n\n[embedder.provider.ollama]\nbase_url = \"{}\"\nmodel = \"slow\"\n",
+        base_url
+    );
+    let mut client = ZedMcpClient::spawn_with_config(Some(&config))?;
+
+    let started = Instant::now();
+    let err = client
+        .call_tool(
+            "store_memory",
+            json!({"content": "Never embedded in time", "scope": "session"}),
+        )
+        .unwrap_err();
+    let elapsed = started.elapsed();
+
+    assert!(err.to_string().contains("Request timed out"), "{}", err);
+    assert!(err.to_string().contains("-32603"), "{}", err);
+    // Twice the timeout, well below the mock's 3 s hang
+    assert!(elapsed < Duration::from_millis(600), "took {:?}", elapsed);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_configure_search_adds_stop_words() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    client.call_tool(
+        "store_memory",
+        json!({"content": "TODO rewrite the parser", "scope": "session"}),
+    )?;
+    let search = |client: &mut ZedMcpClient| -> Result<String> {
+        let result = client.call_tool(
+            "search_memory",
+            json!({"query": "TODO", "scope": "session"}),
+        )?;
+        Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+    };
+    assert!(search(&mut client)?.contains("rewrite the parser"));
+
+    let configured = client.call_tool("configure_search", json!({"add_stop_words": ["TODO"]}))?;
+    assert!(configured["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("TODO"));
+    assert!(search(&mut client)?.contains("No matching memories found"));
+
+    client.call_tool("configure_search", json!({"remove_stop_words": ["todo"]}))?;
+    assert!(search(&mut client)?.contains("rewrite the parser"));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_search_memory_filters() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let tagged = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "Tokio runtime notes", "scope": "session", "tags": ["rust"]}),
+    )?)?;
+    let untagged = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "Tokio runtime tuning", "scope": "session"}),
+    )?)?;
+
+    let search = |client: &mut ZedMcpClient, extra: Value| -> Result<String> {
+        let mut args = json!({"query": "tokio runtime", "scope": "session"});
+        args.as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        let result = client.call_tool("search_memory", args)?;
+        Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+    };
+
+    let text = search(&mut client, json!({"filter_tags": ["rust"]}))?;
+    assert!(text.contains(tagged.as_str()), "{}", text);
+    assert!(!text.contains(untagged.as_str()), "{}", text);
+
+    let text = search(
+        &mut client,
+        json!({"created_after": "2000-01-01T00:00:00Z"}),
+    )?;
+    assert!(text.contains(tagged.as_str()) && text.contains(untagged.as_str()));
+    let text = search(
+        &mut client,
+        json!({"created_before": "2000-01-01T00:00:00Z"}),
+    )?;
+    assert!(text.contains("No matching memories found"), "{}", text);
+
+    let text = search(&mut client, json!({"min_importance_score": 5.0}))?;
+    assert!(text.contains("No matching memories found"), "{}", text);
+
+    let invalid = client.call_tool(
+        "search_memory",
+        json!({"query": "tokio", "scope": "session", "created_after": "yesterday"}),
+    );
+    assert!(invalid.is_err());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_bulk_tag_dry_run_and_live() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let mut drafts = Vec::new();
+    for content in ["Parser draft one", "Parser draft two"] {
+        let stored = client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "session", "tags": ["draft"]}),
+        )?;
+        drafts.push(stored_id(&stored)?);
+    }
+    let other = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "Unrelated note", "scope": "session", "tags": ["misc"]}),
+    )?)?;
+
+    let tagged = |client: &mut ZedMcpClient, tag: &str| -> Result<String> {
+        let result =
+            client.call_tool("search_by_tag", json!({"tags": [tag], "scope": "session"}))?;
+        Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+    };
+
+    let dry = client.call_tool(
+        "bulk_tag",
+        json!({"scope": "session", "filter_tags": ["draft"], "add_tags": ["review"], "dry_run": true}),
+    )?;
+    let text = dry["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Dry run: 2 memories"), "{}", text);
+    assert!(drafts.iter().all(|id| text.contains(id.as_str())));
+    assert!(!text.contains(other.as_str()));
+    assert!(tagged(&mut client, "review")?.contains("No memories found"));
+
+    client.call_tool(
+        "bulk_tag",
+        json!({"scope": "session", "filter_tags": ["draft"], "add_tags": ["review"]}),
+    )?;
+    let reviewed = tagged(&mut client, "review")?;
+    assert!(reviewed.contains("Found 2 memories"), "{}", reviewed);
+
+    let untagged = client.call_tool(
+        "bulk_untag",
+        json!({"scope": "session", "query": "parser", "remove_tags": ["draft"]}),
+    )?;
+    let text = untagged["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.starts_with("Modified 2 memories (0 tags added, 2 removed)"),
+        "{}",
+        text
+    );
+    assert!(tagged(&mut client, "draft")?.contains("No memories found"));
+    assert!(tagged(&mut client, "misc")?.contains(other.as_str()));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_metadata_updates_leave_the_saved_index_alone() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let mut ids = Vec::new();
+    for content in [
+        "Parser handles nested blocks",
+        "Parser reports line numbers",
+    ] {
+        let stored = client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "global", "tags": ["draft"]}),
+        )?;
+        ids.push(stored_id(&stored)?);
+    }
+
+    // BM25 statistics of the global index as the server last saved it
+    let saved_stats = |client: &ZedMcpClient| -> Result<(usize, usize)> {
+        let mut store = rag_core::storage::MemoryStore::new(client.db_dir.join("global.db"))?;
+        let data = store
+            .load_search_index(&rag_core::MemoryScope::Global)?
+            .context("Global index was never saved")?;
+        let engine: rag_search::BM25SearchEngine = serde_json::from_str(&data)?;
+        Ok((engine.document_count(), engine.document_frequency("parser")))
+    };
+    let before = saved_stats(&client)?;
+    assert_eq!(before, (2, 2));
+
+    client.call_tool(
+        "set_importance",
+        json!({"id": ids[0], "scope": "global", "importance_score": 5.0}),
+    )?;
+    assert_eq!(saved_stats(&client)?, before);
+
+    let tagged = client.call_tool(
+        "bulk_tag",
+        json!({"scope": "global", "query": "parser", "add_tags": ["review"]}),
+    )?;
+    let text = tagged["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Modified 2 memories"), "{}", text);
+    assert_eq!(saved_stats(&client)?, before);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_summarize_scope_sends_memories_to_llm() -> Result<()> {
+    // Chat completions stand-in: keeps the request body and returns a fixed summary
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let endpoint = format!("http://{}/v1/chat/completions", listener.local_addr()?);
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&requests);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            recorded
+                .lock()
+                .unwrap()
+                .push(String::from_utf8(body).unwrap());
+
+            let response = r#"{"choices": [{"message": {"role": "assistant", "content": "The project uses SQLite."}}]}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    });
+
+    let config = format!(
+        "[server]\ndefault_llm_endpoint = \"{}\"\nllm_api_key_env = \"TOTALRECALL_TEST_UNSET_KEY\"\n[search]\n[chunking]\n[storage]\n",
+        endpoint
+    );
+    let mut client = ZedMcpClient::spawn_with_config(Some(&config))?;
+
+    for content in ["Storage moved to SQLite", "Sessions are kept in memory"] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "session"}),
+        )?;
+    }
+
+    let result = client.call_tool("summarize_scope", json!({"scope": "session"}))?;
+    assert_eq!(
+        result["content"][0]["text"].as_str().unwrap(),
+        "The project uses SQLite."
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    let body: Value = serde_json::from_str(&requests[0])?;
+    let prompt = body["messages"][0]["content"].as_str().unwrap();
+    assert!(prompt.starts_with("Summarize the following memories in 3-5 sentences:"));
+    assert!(prompt.contains("Storage moved to SQLite"));
+    assert!(prompt.contains("Sessions are kept in memory"));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_rate_limit_rejects_calls_after_burst() -> Result<()> {
+    let config = "[server]\n[server.rate_limit]\nrequests_per_second = 1.0\nburst = 5\n[search]\n[chunking]\n[storage]\n";
+    let mut client = ZedMcpClient::spawn_with_config(Some(config))?;
+
+    let mut limited = Vec::new();
+    for i in 0..100 {
+        if let Err(e) = client.call_tool("ping", json!({})) {
+            limited.push((i, e.to_string()));
+        }
+    }
+
+    // The first calls drain the burst; nearly all of the rest are rejected
+    assert_eq!(limited.first().map(|(i, _)| *i), Some(5));
+    assert!(limited.len() >= 90, "only {} calls limited", limited.len());
+    for (_, err) in &limited {
+        assert!(err.contains("-32003"), "{}", err);
+        assert!(err.contains("Rate limit exceeded, retry after"), "{}", err);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_per_tool_rate_limit_keeps_other_tools_available() -> Result<()> {
+    let config = "[server]\n[server.rate_limit]\nrequests_per_second = 1.0\nburst = 2\nper_tool = true\n[search]\n[chunking]\n[storage]\n";
+    let mut client = ZedMcpClient::spawn_with_config(Some(config))?;
+
+    client.call_tool("ping", json!({}))?;
+    client.call_tool("ping", json!({}))?;
+    let err = client.call_tool("ping", json!({})).unwrap_err();
+    assert!(err.to_string().contains("-32003"), "{}", err);
+
+    client.call_tool("list_memories", json!({"scope": "session"}))?;
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_rename_tag_updates_every_memory() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for tags in [json!(["rust"]), json!(["rust", "rust-lang"])] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": "Tagged note", "scope": "session", "tags": tags}),
+        )?;
+    }
+
+    let result = client.call_tool(
+        "rename_tag",
+        json!({"old_tag": "rust", "new_tag": "rust-lang", "scope": "session"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("on 2 memories"), "{}", text);
+
+    let result = client.call_tool(
+        "search_by_tag",
+        json!({"tags": ["rust"], "scope": "session"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(!text.contains("Tagged note"), "{}", text);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_delete_tag_reports_untagged_memories() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for tags in [json!(["obsolete"]), json!(["obsolete", "keep"])] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": "Tagged note", "scope": "session", "tags": tags}),
+        )?;
+    }
+
+    let result = client.call_tool("delete_tag", json!({"tag": "obsolete", "scope": "session"}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("from 2 memories"), "{}", text);
+    assert!(
+        text.contains("1 memories in scope have no tags"),
+        "{}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_set_importance_clamps_and_shows_in_stats() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "Critical deployment note", "scope": "session"}),
+    )?;
+    let id = stored_id(&stored)?;
+
+    let result = client.call_tool(
+        "set_importance",
+        json!({"id": id, "scope": "session", "importance_score": 42.0}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("importance set to 10.00"), "{}", text);
+
+    let stats = client.call_tool("get_stats", json!({"scope": "session"}))?;
+    let text = stats["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("max 10.00"), "{}", text);
+    assert!(
+        text.contains(&format!("Important: {} (10.00)", id)),
+        "{}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_reingest_file_replaces_old_chunks() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let dir = std::env::temp_dir().join(format!("rag-mcp-reingest-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let file = dir.join("notes.md");
+    let file_str = file.to_str().unwrap();
+    std::fs::write(&file, "Original notes about the cache layer\n")?;
+
+    let args = json!({"file_path": file_str, "scope": "session"});
+    client.call_tool("reingest_file", args.clone())?;
+    let found = client.call_tool(
+        "find_by_source",
+        json!({"source_file": file_str, "scope": "session"}),
+    )?;
+    let old_id = stored_id(&found)?;
+
+    std::fs::write(&file, "Rewritten notes about the queue\n")?;
+    let result = client.call_tool("reingest_file", args)?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("replaced 1 chunks with 1"), "{}", text);
+
+    let found = client.call_tool(
+        "find_by_source",
+        json!({"source_file": file_str, "scope": "session"}),
+    )?;
+    let text = found["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Rewritten notes about the queue"), "{}", text);
+    assert!(!text.contains("Original notes"), "{}", text);
+    assert!(!text.contains(&old_id), "{}", text);
+
+    let old = client.call_tool("get_memory", json!({"id": old_id, "scope": "session"}))?;
+    let text = old["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("not found"), "{}", text);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_get_children_and_ancestors_follow_parent_links() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let id = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "alpha beta gamma", "scope": "session"}),
+    )?)?;
+    let split = client.call_tool(
+        "split_memory",
+        json!({"id": id, "scope": "session", "split_at": [6]}),
+    )?;
+    let part_ids: Vec<String> = split["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("ID: "))
+        .map(String::from)
+        .collect();
+    assert_eq!(part_ids.len(), 2);
+
+    // The split memory is gone, but its id still links the parts
+    let children = client.call_tool("get_children", json!({"id": id, "scope": "session"}))?;
+    let text = children["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Found 2 memories"), "Got: {}", text);
+    let first = text.find(&part_ids[0]).context("First part missing")?;
+    let second = text.find(&part_ids[1]).context("Second part missing")?;
+    assert!(first < second, "Got: {}", text);
+
+    let ancestors = client.call_tool(
+        "get_ancestors",
+        json!({"id": part_ids[1], "scope": "session"}),
+    )?;
+    let text = ancestors["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Found 1 memories"), "Got: {}", text);
+    assert!(text.contains("gamma"), "Got: {}", text);
+
+    let missing = client.call_tool(
+        "get_ancestors",
+        json!({"id": "missing", "scope": "session"}),
+    );
+    assert!(missing.is_err());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_get_document_reassembles_chunks_in_order() -> Result<()> {
+    let config =
+        "[server]\n[search]\n[chunking]\nmax_chunk_size = 40\nchunk_overlap = 10\n[storage]\n";
+    let mut client = ZedMcpClient::spawn_with_config(Some(config))?;
+
+    let dir = std::env::temp_dir().join(format!("rag-mcp-get-document-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let file = dir.join("notes.txt");
+    let file_str = file.to_str().unwrap();
+    let original =
+        "The cache keeps hot rows.\n\nThe queue batches writes.\n\nThe index is rebuilt nightly.\n";
+    std::fs::write(&file, original)?;
+
+    client.call_tool(
+        "reingest_file",
+        json!({"file_path": file_str, "scope": "session"}),
+    )?;
+    let found = client.call_tool(
+        "find_by_source",
+        json!({"source_file": file_str, "scope": "session"}),
+    )?;
+    let text = found["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Found 3 memories"), "{}", text);
+    let parent_id = text
+        .split("Parent: ")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .context("No parent id in find_by_source output")?;
+
+    let document = client.call_tool(
+        "get_document",
+        json!({"document_id": parent_id, "scope": "session"}),
+    )?;
+    let text = document["content"][0]["text"].as_str().unwrap();
+    let (header, content) = text.split_once("\n\n").unwrap();
+    assert!(header.contains("3 chunks"), "{}", header);
+    assert!(header.contains("notes.txt"), "{}", header);
+    assert_eq!(content, original);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_search_project_tree_covers_sub_projects() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let dir = std::env::temp_dir().join(format!("rag-mcp-project-tree-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (name, content) in [
+        ("frontend", "Deployment of the frontend bundle"),
+        ("backend", "Deployment of the backend service"),
+    ] {
+        let project = dir.join("proj").join(name);
+        std::fs::create_dir_all(&project)?;
+        client.call_tool(
+            "store_memory",
+            json!({
+                "content": content,
+                "scope": "project",
+                "project_path": project.to_str().unwrap()
+            }),
+        )?;
+    }
+
+    let result = client.call_tool(
+        "search_project_tree",
+        json!({"root_path": dir.join("proj").to_str().unwrap(), "query": "deployment"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Found 2 results"), "{}", text);
+    assert!(text.contains("frontend bundle"), "{}", text);
+    assert!(text.contains("backend service"), "{}", text);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_store_memory_rejects_oversized_content() -> Result<()> {
+    let config = "[server]\n[search]\n[chunking]\n[storage]\nmax_content_length = 100\n";
+    let mut client = ZedMcpClient::spawn_with_config(Some(config))?;
+
+    let rejected = client.call_tool(
+        "store_memory",
+        json!({"content": "x".repeat(101), "scope": "session"}),
+    )?;
+    assert_eq!(rejected["isError"], true);
+    let text = rejected["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("101 bytes"), "{}", text);
+    assert!(text.contains("limit 100"), "{}", text);
+
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "x".repeat(100), "scope": "session"}),
+    )?;
+    stored_id(&stored)?;
+    Ok(())
+}
+
+/// Extract the memory ID from a `store_memory` response text
+fn stored_id(result: &Value) -> Result<String> {
+    result["content"][0]["text"]
+        .as_str()
+        .and_then(|text| text.split("ID: ").nth(1))
+        .and_then(|s| s.split_whitespace().next())
+        .map(String::from)
+        .context("Failed to extract memory ID")
+}
+
+#[test]
+#[serial]
+fn test_move_memory_between_scopes() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    client.call_tool("clear_session", json!({}))?;
+
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "Graduate this note to global", "scope": "session", "tags": []}),
+    )?;
+    let id = stored_id(&stored)?;
+
+    let moved = client.call_tool(
+        "move_memory",
+        json!({"id": id, "from_scope": "session", "to_scope": "global"}),
+    )?;
+    let text = moved["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains("moved from session to global"),
+        "Got: {}",
+        text
+    );
+
+    let session = client.call_tool(
+        "list_memories",
+        json!({"scope": "session", "limit": 10, "offset": 0}),
+    )?;
+    assert!(session["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("No memories found"));
+
+    let global = client.call_tool(
+        "list_memories",
+        json!({"scope": "global", "limit": 10, "offset": 0}),
+    )?;
+    let text = global["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains(&id), "Got: {}", text);
+    assert!(text.contains("Graduate this note to global"));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_copy_memory_preserves_original() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+    client.call_tool("clear_session", json!({}))?;
+
+    let stored = client.call_tool(
+        "store_memory",
+        json!({"content": "Copy me somewhere else", "scope": "session", "tags": ["copy"]}),
+    )?;
+    let id = stored_id(&stored)?;
+
+    let copied = client.call_tool(
+        "copy_memory",
+        json!({"id": id, "from_scope": "session", "to_scope": "global"}),
+    )?;
+    let copy_id = stored_id(&copied)?;
+    assert_ne!(copy_id, id);
+
+    // Original untouched in its scope
+    let session = client.call_tool(
+        "list_memories",
+        json!({"scope": "session", "limit": 10, "offset": 0}),
+    )?;
+    let text = session["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 1 memories"), "Got: {}", text);
+    assert!(
+        text.contains(&format!("ID: {} | Tags: copy\n", id)),
+        "Got: {}",
+        text
+    );
+
+    // Copy has its own ID and points back at the original
+    let global = client.call_tool(
+        "list_memories",
+        json!({"scope": "global", "limit": 10, "offset": 0}),
+    )?;
+    let text = global["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains(&format!("ID: {} | Tags: copy | Parent: {}", copy_id, id)),
+        "Got: {}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_delete_scope_requires_confirm() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for i in 0..2 {
+        client.call_tool(
+            "store_memory",
+            json!({"content": format!("Global memory {}", i), "scope": "global", "tags": []}),
+        )?;
+    }
+
+    // Without confirmation nothing is deleted
+    let refused = client.call_tool("delete_scope", json!({"scope": "global", "confirm": false}));
+    assert!(refused.is_err(), "Expected error without confirm");
+
+    let list = client.call_tool(
+        "list_memories",
+        json!({"scope": "global", "limit": 10, "offset": 0}),
+    )?;
+    assert!(list["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Found 2 memories"));
+
+    let result = client.call_tool("delete_scope", json!({"scope": "global", "confirm": true}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains("Deleted 2 memories from global"),
+        "Got: {}",
+        text
+    );
+
+    let list = client.call_tool(
+        "list_memories",
+        json!({"scope": "global", "limit": 10, "offset": 0}),
+    )?;
+    assert!(list["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("No memories found"));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_list_projects_registry() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let project = std::env::temp_dir().join(format!("rag-mcp-projects-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&project);
+    std::fs::create_dir_all(&project)?;
+    let project_path = project.to_str().unwrap();
+
+    client.call_tool(
+        "store_memory",
+        json!({
+            "content": "Project-scoped note",
+            "scope": "project",
+            "project_path": project_path,
+            "tags": []
+        }),
+    )?;
+
+    let result = client.call_tool("list_projects", json!({}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains(&format!("{} | Memories: 1", project_path)),
+        "Got: {}",
+        text
+    );
+
+    // Removing the database leaves the registry entry behind, marked stale
+    client.call_tool(
+        "delete_scope",
+        json!({"scope": "project", "project_path": project_path, "confirm": true}),
+    )?;
+
+    let result = client.call_tool("list_projects", json!({}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.contains(&format!("{} | STALE", project_path)),
+        "Got: {}",
+        text
+    );
+
+    std::fs::remove_dir_all(&project)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_search_by_tag_match_modes() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for (content, tags) in [
+        ("Tagged rust and async", json!(["rust", "async"])),
+        ("Tagged rust only", json!(["rust"])),
+        ("Tagged python only", json!(["python"])),
+    ] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "session", "tags": tags}),
+        )?;
+    }
+
+    let result = client.call_tool(
+        "search_by_tag",
+        json!({"tags": ["rust", "async"], "scope": "session"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 1 memories"), "Got: {}", text);
+    assert!(text.contains("Tagged rust and async"));
+
+    let result = client.call_tool(
+        "search_by_tag",
+        json!({"tags": ["async", "python"], "scope": "session", "match_mode": "any"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 2 memories"), "Got: {}", text);
+    assert!(!text.contains("Tagged rust only"));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_search_memory_regex_mode() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    for content in [
+        "Request 3f2b9c1e-8d4a-4b7f-9e21-0c5d6a7b8e9f failed with timeout",
+        "Retried 11111111-2222-4333-8444-555555555555 and a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d",
+        "No identifiers in this one",
+    ] {
+        client.call_tool(
+            "store_memory",
+            json!({"content": content, "scope": "session", "tags": []}),
+        )?;
+    }
+
+    let result = client.call_tool(
+        "search_memory",
+        json!({
+            "query": r"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}",
+            "scope": "session",
+            "search_mode": "regex",
+            "k": 10
+        }),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 2 results"), "Got: {}", text);
+    assert!(!text.contains("No identifiers"));
+    // Two UUIDs outrank one
+    assert!(
+        text.starts_with("Found 2 results:\n\nScore: 2.00"),
+        "Got: {}",
+        text
+    );
+
+    let invalid = client.call_tool(
+        "search_memory",
+        json!({"query": "(unclosed", "scope": "session", "search_mode": "regex"}),
+    );
+    assert!(invalid.is_err());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_access_tracking_and_stats() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let popular = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "Frequently read note", "scope": "global", "tags": []}),
+    )?)?;
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Rarely read note", "scope": "global", "tags": []}),
+    )?;
+
+    for _ in 0..3 {
+        let result = client.call_tool("get_memory", json!({"id": popular, "scope": "global"}))?;
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("Frequently read note"), "Got: {}", text);
+    }
+
+    // Searching does not count as an access
+    client.call_tool(
+        "search_memory",
+        json!({"query": "note", "scope": "global", "k": 5}),
+    )?;
+
+    let result = client.call_tool("get_stats", json!({"scope": "global"}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Total memories: 2"), "Got: {}", text);
+    assert!(text.contains("Total accesses: 3"), "Got: {}", text);
+    assert!(
+        text.contains(&format!("Most accessed: {}", popular)),
+        "Got: {}",
+        text
+    );
+
+    let result = client.call_tool(
+        "list_memories",
+        json!({"scope": "global", "sort_by": "access_count_desc"}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(
+        text.starts_with(&format!("Found 2 memories:\n\nID: {}", popular)),
+        "Got: {}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_pinned_memory_survives_clear_and_delete() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let pinned = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "Architecture decision: use SQLite", "scope": "session", "tags": []}),
+    )?)?;
+    client.call_tool(
+        "store_memory",
+        json!({"content": "Scratch note", "scope": "session", "tags": []}),
+    )?;
+    client.call_tool("pin_memory", json!({"id": pinned, "scope": "session"}))?;
+
+    let list = client.call_tool(
+        "list_memories",
+        json!({"scope": "session", "filter_pinned": true}),
+    )?;
+    let text = list["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 1 memories"), "Got: {}", text);
+    assert!(
+        text.contains(&format!("ID: {} [PINNED]", pinned)),
+        "Got: {}",
+        text
+    );
+
+    client.call_tool("clear_session", json!({}))?;
+    let list = client.call_tool("list_memories", json!({"scope": "session"}))?;
+    let text = list["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("Found 1 memories"), "Got: {}", text);
+    assert!(text.contains("Architecture decision"));
+
+    let refused = client.call_tool("delete_memory", json!({"id": pinned, "scope": "session"}));
+    assert!(refused.is_err(), "Pinned memory must not be deleted");
+
+    let result = client.call_tool(
+        "delete_memory",
+        json!({"id": pinned, "scope": "session", "force": true}),
+    )?;
+    assert!(result["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("deleted successfully"));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_merge_memories_concatenates_in_order() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let second = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "Second part", "scope": "session", "tags": ["notes", "b"]}),
+    )?)?;
+    let first = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": "First part", "scope": "session", "tags": ["a", "notes"]}),
+    )?)?;
+
+    let merged = stored_id(&client.call_tool(
+        "merge_memories",
+        json!({
+            "ids": [first, second],
+            "scope": "session",
+            "merged_content_separator": " + ",
+            "tags": ["merged", "a"]
+        }),
+    )?)?;
+
+    let result = client.call_tool("get_memory", json!({"id": merged, "scope": "session"}))?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("First part + Second part"), "Got: {}", text);
+    assert!(
+        text.contains("Tags: a, notes, b, merged |"),
+        "Got: {}",
+        text
+    );
+    assert!(
+        text.contains(&format!("Parent: {}", first)),
+        "Got: {}",
+        text
+    );
+
+    let list = client.call_tool("list_memories", json!({"scope": "session"}))?;
+    assert!(list["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Found 1 memories"));
+
+    let too_few = client.call_tool(
+        "merge_memories",
+        json!({"ids": [merged], "scope": "session"}),
+    );
+    assert!(too_few.is_err());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_split_memory_at_byte_offsets() -> Result<()> {
+    let mut client = ZedMcpClient::spawn()?;
+
+    let original = "alpha beta gamma delta";
+    let id = stored_id(&client.call_tool(
+        "store_memory",
+        json!({"content": original, "scope": "session", "tags": ["split"]}),
+    )?)?;
+
+    let result = client.call_tool(
+        "split_memory",
+        json!({"id": id, "scope": "session", "split_at": [6, 11]}),
+    )?;
+    let text = result["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("into 3 memories"), "Got: {}", text);
+
+    let part_ids: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("ID: "))
+        .collect();
+    assert_eq!(part_ids.len(), 3);
+
+    let mut contents = Vec::new();
+    for part_id in &part_ids {
+        let result = client.call_tool("get_memory", json!({"id": part_id, "scope": "session"}))?;
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains(&format!("Parent: {}", id)), "Got: {}", text);
+        // Body sits between the "ID: ..." line and the trailing separator
+        let body = text
+            .strip_prefix("Found 1 memories:\n\n")
+            .and_then(|rest| rest.split_once('\n'))
+            .and_then(|(_, body)| body.strip_suffix("\n\n---\n\n"))
+            .context("Unexpected get_memory output")?;
+        contents.push(body.to_string());
+    }
+    assert_eq!(contents, vec!["alpha ", "beta ", "gamma delta"]);
+    assert_eq!(contents.concat(), original);
+
+    let out_of_bounds = client.call_tool(
+        "split_memory",
+        json!({"id": part_ids[0], "scope": "session", "split_at": [100]}),
+    );
+    assert!(out_of_bounds.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+#![cfg(unix)]
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_sigint_stops_server_with_open_stdin() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-shutdown-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let config_dir = root.join("config").join("rag-mcp");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(
+        config_dir.join("config.toml"),
+        "[server]\n[search]\n[chunking]\n[storage]\n",
+    )?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+        .arg("serve")
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("RAG_MCP_DB_PATH", root.join("db"))
+        .env_remove("RUST_LOG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to spawn MCP server")?;
+
+    // Stdin stays open for the whole test, so EOF cannot be what stops the server
+    let mut stdin = child.stdin.take().context("Failed to take stdin")?;
+    let mut stdout = BufReader::new(child.stdout.take().context("Failed to take stdout")?);
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {
+            "name": "store_memory",
+            "arguments": {"content": "Flushed on shutdown", "scope": "global"}
+        }
+    });
+    writeln!(stdin, "{}", request)?;
+    stdin.flush()?;
+
+    // Skip notifications until the response shows the server is up
+    let mut line = String::new();
+    loop {
+        line.clear();
+        anyhow::ensure!(stdout.read_line(&mut line)? > 0, "Server closed stdout");
+        let message: Value = serde_json::from_str(&line)?;
+        if message["id"] == 1 {
+            assert!(message.get("error").is_none(), "{}", message);
+            break;
+        }
+    }
+
+    std::thread::sleep(Duration::from_millis(100));
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()?;
+    assert!(status.success());
+
+    let deadline = Instant::now() + Duration::from_millis(500);
+    let exit = loop {
+        if let Some(exit) = child.try_wait()? {
+            break exit;
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            anyhow::bail!("Server did not exit within 500ms of SIGINT");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert!(exit.success(), "{:?}", exit);
+
+    // The WAL was checkpointed into the database file on the way out
+    let wal = root.join("db").join("global.db-wal");
+    let wal_len = std::fs::metadata(&wal).map(|m| m.len()).unwrap_or(0);
+    assert_eq!(wal_len, 0);
+
+    drop(stdin);
+    let _ = std::fs::remove_dir_all(&root);
+    Ok(())
+}
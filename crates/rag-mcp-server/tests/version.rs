@@ -0,0 +1,27 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::process::Command;
+
+#[test]
+fn test_version_json_has_build_metadata() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-version-test-{}", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_rag-mcp"))
+        .args(["version", "--json"])
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("RAG_MCP_DB_PATH", root.join("db"))
+        .env_remove("RUST_LOG")
+        .output()
+        .context("Failed to run rag-mcp version")?;
+    assert!(output.status.success(), "{:?}", output);
+
+    let info: Value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("version did not print JSON: {:?}", output))?;
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    for field in ["git_sha", "build_time", "target"] {
+        let value = info[field].as_str().unwrap_or_default();
+        assert!(!value.is_empty(), "missing {}: {}", field, info);
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+    Ok(())
+}
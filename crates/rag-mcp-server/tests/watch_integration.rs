@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// `rag-mcp watch` process with an isolated database and config directory
+struct Watcher {
+    child: Child,
+    root: PathBuf,
+}
+
+impl Watcher {
+    /// Spawn the watcher on `directory` and wait for its "Watching" log line
+    fn spawn(root: &Path, directory: &Path) -> Result<Self> {
+        let mut child = Self::command(root)
+            .args(["watch", directory.to_str().unwrap()])
+            .args(["--scope", "global", "--debounce-ms", "100"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to spawn watcher")?;
+
+        let mut stderr = BufReader::new(child.stderr.take().unwrap());
+        let mut line = String::new();
+        loop {
+            line.clear();
+            anyhow::ensure!(stderr.read_line(&mut line)? > 0, "Watcher exited early");
+            if line.contains("Watching") {
+                break;
+            }
+        }
+        // Keep draining so the watcher never blocks on a full pipe
+        thread::spawn(move || for _ in stderr.lines() {});
+
+        Ok(Self {
+            child,
+            root: root.to_path_buf(),
+        })
+    }
+
+    fn command(root: &Path) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rag-mcp"));
+        command
+            .env("RAG_MCP_DB_PATH", root.join("db"))
+            .env("XDG_CONFIG_HOME", root.join("config"))
+            .env_remove("RUST_LOG");
+        command
+    }
+
+    /// Stdout of `rag-mcp list --scope global`
+    fn list_global(&self) -> Result<String> {
+        let output = Self::command(&self.root)
+            .args(["list", "--scope", "global"])
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Poll the store until `check` holds for the listing, or fail after a timeout
+    fn wait_for(&self, check: impl Fn(&str) -> bool) -> Result<String> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let listing = self.list_global()?;
+            if check(&listing) {
+                return Ok(listing);
+            }
+            anyhow::ensure!(
+                Instant::now() < deadline,
+                "Timed out, last listing:\n{}",
+                listing
+            );
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn test_watch_reingests_modified_and_removed_files() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("rag-mcp-watch-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let directory = root.join("project");
+    std::fs::create_dir_all(&directory)?;
+    let file = directory.join("notes.txt");
+    std::fs::write(&file, "first draft of the notes\n")?;
+
+    let watcher = Watcher::spawn(&root, &directory)?;
+
+    std::fs::write(&file, "second draft replaces the first\n")?;
+    let listing = watcher.wait_for(|l| l.contains("second draft replaces the first"))?;
+    assert!(!listing.contains("first draft"), "Got: {}", listing);
+
+    std::fs::write(&file, "third draft\n")?;
+    let listing = watcher.wait_for(|l| l.contains("third draft"))?;
+    assert!(!listing.contains("second draft"), "Got: {}", listing);
+
+    std::fs::remove_file(&file)?;
+    watcher.wait_for(|l| !l.contains("third draft"))?;
+
+    Ok(())
+}
@@ -1,8 +1,52 @@
-use rag_core::{Memory, SearchResult};
+use anyhow::{Context, Result};
+use rag_core::{
+    config::{ScoringMode, SearchConfig},
+    Memory, SearchResult,
+};
 use regex::Regex;
-use std::collections::HashMap;
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use unicode_segmentation::UnicodeSegmentation;
 
+/// One part of a search query: a single term, or a quoted phrase whose terms must
+/// appear consecutively to earn the phrase bonus
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryClause {
+    Term(String),
+    Phrase(Vec<String>),
+}
+
+/// Query split into term and phrase clauses, tokenized like indexed content
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedQuery {
+    pub clauses: Vec<QueryClause>,
+}
+
+impl ParsedQuery {
+    /// Every term from every clause, in query order
+    pub fn terms(&self) -> Vec<String> {
+        self.clauses
+            .iter()
+            .flat_map(|clause| match clause {
+                QueryClause::Term(term) => vec![term.clone()],
+                QueryClause::Phrase(terms) => terms.clone(),
+            })
+            .collect()
+    }
+
+    fn phrases(&self) -> impl Iterator<Item = &[String]> {
+        self.clauses.iter().filter_map(|clause| match clause {
+            QueryClause::Phrase(terms) => Some(terms.as_slice()),
+            QueryClause::Term(_) => None,
+        })
+    }
+}
+
+/// Serializes with its index statistics, so an index can be persisted and reloaded
+/// instead of rebuilt
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BM25SearchEngine {
     k1: f32,
     b: f32,
@@ -11,6 +55,19 @@ pub struct BM25SearchEngine {
     doc_lengths: HashMap<String, usize>,
     term_doc_freq: HashMap<String, usize>,
     stop_words: Vec<String>,
+    importance_boost_factor: f32,
+    /// Stemmer applied to index and query tokens alike, so both sides normalize the
+    /// same way. The algorithm rather than a `Stemmer`, which can be neither cloned
+    /// nor serialized.
+    stemming: Option<Algorithm>,
+    phrase_bonus: f32,
+    field_boost: f32,
+    scoring_mode: ScoringMode,
+    /// Characters per ngram; None when ngram matching is off
+    ngram_size: Option<usize>,
+    ngram_weight: f32,
+    /// Document frequencies of content ngrams, kept apart from whole-token terms
+    ngram_term_doc_freq: HashMap<String, usize>,
 }
 
 impl BM25SearchEngine {
@@ -23,7 +80,102 @@ impl BM25SearchEngine {
             doc_lengths: HashMap::new(),
             term_doc_freq: HashMap::new(),
             stop_words: Self::default_stop_words(),
+            importance_boost_factor: 1.0,
+            stemming: None,
+            phrase_bonus: 2.0,
+            field_boost: 2.0,
+            scoring_mode: ScoringMode::BM25,
+            ngram_size: None,
+            ngram_weight: 0.3,
+            ngram_term_doc_freq: HashMap::new(),
+        }
+    }
+
+    /// Engine tuned from `SearchConfig`: BM25 parameters, importance boost and stop words.
+    /// Configured stop words are merged with the defaults unless
+    /// `replace_default_stop_words` is set.
+    pub fn new_with_config(config: &SearchConfig) -> Result<Self> {
+        let mut engine = Self::new();
+        engine.k1 = config.bm25_k1;
+        engine.b = config.bm25_b;
+        engine.phrase_bonus = config.phrase_bonus;
+        engine.field_boost = config.field_boost;
+        engine.scoring_mode = config.scoring_mode;
+        engine.ngram_weight = config.ngram_weight;
+        if config.ngram_enabled {
+            anyhow::ensure!(config.ngram_size > 0, "ngram_size must be positive");
+            engine.ngram_size = Some(config.ngram_size);
+        }
+        engine.importance_boost_factor = if config.use_importance_boost {
+            config.importance_boost_factor
+        } else {
+            0.0
+        };
+
+        if config.stemming_enabled {
+            let algorithm = stemming_algorithm(&config.stemming_language)?;
+            engine.stemming = Some(algorithm);
+        }
+
+        if config.replace_default_stop_words {
+            engine.stop_words.clear();
         }
+        for word in config.stop_words.iter().flatten() {
+            engine.add_stop_word(word.clone());
+        }
+        if let Some(path) = &config.stop_words_file {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read stop words file {:?}", path))?;
+            for line in contents.lines() {
+                engine.add_stop_word(line.to_string());
+            }
+        }
+        for word in &config.removed_stop_words {
+            engine.remove_stop_word(word);
+        }
+
+        Ok(engine)
+    }
+
+    /// Add a stop word (case-insensitive). Already indexed memories keep their
+    /// term statistics until `reindex_all`.
+    pub fn add_stop_word(&mut self, word: String) {
+        let word = word.trim().to_lowercase();
+        if !word.is_empty() && !self.stop_words.contains(&word) {
+            self.stop_words.push(word);
+        }
+    }
+
+    pub fn remove_stop_word(&mut self, word: &str) {
+        let word = word.trim().to_lowercase();
+        self.stop_words.retain(|w| *w != word);
+    }
+
+    /// Scale how strongly `MemoryMetadata::importance_score` re-ranks results:
+    /// 1.0 multiplies scores by the importance as-is, 0.0 disables boosting
+    pub fn with_importance_boost(mut self, factor: f32) -> Self {
+        self.importance_boost_factor = factor;
+        self
+    }
+
+    /// Weight of tag and AST node type matches relative to content; 0.0 ignores them
+    pub fn with_field_boost(mut self, boost: f32) -> Self {
+        self.field_boost = boost;
+        self
+    }
+
+    /// Score with TF-IDF instead of BM25; the same index serves both
+    pub fn with_scoring_mode(mut self, mode: ScoringMode) -> Self {
+        self.scoring_mode = mode;
+        self
+    }
+
+    /// Also index and match character ngrams of `size` characters, adding `weight`
+    /// times the ngram score; `size` 0 disables it. Takes effect on the next reindex.
+    pub fn with_ngrams(mut self, size: usize, weight: f32) -> Self {
+        self.ngram_size = (size > 0).then_some(size);
+        self.ngram_weight = weight;
+        self
     }
 
     fn default_stop_words() -> Vec<String> {
@@ -39,6 +191,19 @@ impl BM25SearchEngine {
     }
 
     fn tokenize(&self, text: &str) -> Vec<String> {
+        let stemmer = self.stemming.map(Stemmer::create);
+
+        self.words(text)
+            .into_iter()
+            .map(|w| match &stemmer {
+                Some(stemmer) => stemmer.stem(&w).into_owned(),
+                None => w,
+            })
+            .collect()
+    }
+
+    /// Lowercased words of `text` without stop words, before stemming
+    fn words(&self, text: &str) -> Vec<String> {
         let re = Regex::new(r"[^\w\s]").unwrap();
         let cleaned = re.replace_all(text, " ");
 
@@ -49,6 +214,33 @@ impl BM25SearchEngine {
             .collect()
     }
 
+    /// The words of `query` that take part in matching, as typed rather than stemmed,
+    /// for `highlight_tokens`
+    pub fn query_words(&self, query: &str) -> Vec<String> {
+        let mut words = self.words(query);
+        let mut seen = HashSet::new();
+        words.retain(|w| seen.insert(w.clone()));
+        words
+    }
+
+    /// Character ngrams of every token, e.g. "async" -> "asy", "syn", "ync". Tokens
+    /// shorter than the ngram size yield none; they only match as whole tokens.
+    fn ngrams(&self, tokens: &[String]) -> Vec<String> {
+        let Some(size) = self.ngram_size else {
+            return Vec::new();
+        };
+        tokens
+            .iter()
+            .flat_map(|token| {
+                let chars: Vec<char> = token.chars().collect();
+                chars
+                    .windows(size)
+                    .map(|window| window.iter().collect::<String>())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     pub fn index_memory(&mut self, memory: &Memory) {
         let tokens = self.tokenize(&memory.content);
         let doc_len = tokens.len();
@@ -65,21 +257,95 @@ impl BM25SearchEngine {
             *self.term_doc_freq.entry(term).or_insert(0) += 1;
         }
 
+        let unique_ngrams: HashSet<String> = self.ngrams(&tokens).into_iter().collect();
+        for ngram in unique_ngrams {
+            *self.ngram_term_doc_freq.entry(ngram).or_insert(0) += 1;
+        }
+
         let total_length: usize = self.doc_lengths.values().sum();
         self.avg_doc_length = total_length as f32 / self.doc_count as f32;
     }
 
-    pub fn search(&self, query: &str, memories: &[Memory], k: usize) -> Vec<SearchResult> {
-        let query_tokens = self.tokenize(query);
+    /// Top `k` matches scoring at least `min_score` (after the importance boost)
+    pub fn search(
+        &self,
+        query: &str,
+        memories: &[Memory],
+        k: usize,
+        min_score: f32,
+    ) -> Vec<SearchResult> {
+        self.search_with_boost(query, memories, k, min_score, self.importance_boost_factor)
+    }
+
+    /// `search` with a per-call importance boost factor instead of the engine default
+    pub fn search_with_boost(
+        &self,
+        query: &str,
+        memories: &[Memory],
+        k: usize,
+        min_score: f32,
+        importance_boost_factor: f32,
+    ) -> Vec<SearchResult> {
+        self.search_with_boost_filtered(
+            query,
+            memories,
+            k,
+            min_score,
+            importance_boost_factor,
+            |_| true,
+        )
+    }
+
+    /// Top `k` matches among the memories `filter` accepts. Rejected memories are never
+    /// scored, so they can't take a top-`k` slot.
+    pub fn search_with_filter<F>(
+        &self,
+        query: &str,
+        memories: &[Memory],
+        k: usize,
+        filter: F,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(&Memory) -> bool,
+    {
+        self.search_with_boost_filtered(
+            query,
+            memories,
+            k,
+            0.0,
+            self.importance_boost_factor,
+            filter,
+        )
+    }
+
+    /// `search_with_boost` restricted to the memories `filter` accepts
+    pub fn search_with_boost_filtered<F>(
+        &self,
+        query: &str,
+        memories: &[Memory],
+        k: usize,
+        min_score: f32,
+        importance_boost_factor: f32,
+        filter: F,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(&Memory) -> bool,
+    {
+        let parsed = self.parse_query(query);
         let mut scores: Vec<(usize, f32)> = Vec::new();
 
-        for (idx, memory) in memories.iter().enumerate() {
-            let score = self.score_document(memory, &query_tokens);
+        for (idx, memory) in memories.iter().enumerate().filter(|(_, m)| filter(m)) {
+            let score = self.score_document(memory, &parsed);
             if score > 0.0 {
-                scores.push((idx, score));
+                let boost = importance_multiplier(
+                    memory.metadata.importance_score,
+                    importance_boost_factor,
+                );
+                scores.push((idx, score * boost));
             }
         }
 
+        scores.retain(|&(_, score)| score >= min_score);
         scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
         scores
@@ -94,7 +360,51 @@ impl BM25SearchEngine {
             .collect()
     }
 
-    fn score_document(&self, memory: &Memory, query_tokens: &[String]) -> f32 {
+    /// Search several memory sets (e.g. one per scope) and merge their top-k lists by score.
+    /// Returns up to `k * buckets.len()` results; callers blend, dedup and truncate.
+    pub fn search_multiple(
+        &self,
+        query: &str,
+        buckets: &[&[Memory]],
+        k: usize,
+    ) -> Vec<SearchResult> {
+        let mut merged: Vec<SearchResult> = buckets
+            .iter()
+            .flat_map(|memories| self.search(query, memories, k, 0.0))
+            .collect();
+
+        merged.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        for (rank, result) in merged.iter_mut().enumerate() {
+            result.rank = rank;
+        }
+
+        merged
+    }
+
+    /// Split `query` on double quotes: quoted segments become phrase clauses, the rest
+    /// individual terms. A quoted segment with a single term is just a term.
+    pub fn parse_query(&self, query: &str) -> ParsedQuery {
+        let mut clauses = Vec::new();
+
+        for (i, segment) in query.split('"').enumerate() {
+            let tokens = self.tokenize(segment);
+            // Odd segments sit between a pair of quotes
+            if i % 2 == 1 && tokens.len() > 1 {
+                clauses.push(QueryClause::Phrase(tokens));
+            } else {
+                clauses.extend(tokens.into_iter().map(QueryClause::Term));
+            }
+        }
+
+        ParsedQuery { clauses }
+    }
+
+    /// Content score (times the phrase bonus per matched phrase) plus `field_boost`
+    /// times the score of the memory's tags and AST node type, plus `ngram_weight` times
+    /// the content's ngram score when ngrams are on, each computed with the engine's
+    /// `ScoringMode`. Importance boost is not included.
+    pub fn score_document(&self, memory: &Memory, query: &ParsedQuery) -> f32 {
+        let query_tokens = query.terms();
         let doc_tokens = self.tokenize(&memory.content);
         let doc_len = self
             .doc_lengths
@@ -102,29 +412,117 @@ impl BM25SearchEngine {
             .copied()
             .unwrap_or(doc_tokens.len());
 
-        let mut term_freq: HashMap<String, usize> = HashMap::new();
-        for token in &doc_tokens {
-            *term_freq.entry(token.clone()).or_insert(0) += 1;
+        let norm = 1.0 - self.b + self.b * (doc_len as f32 / self.avg_doc_length.max(1.0));
+        let mut score = match self.scoring_mode {
+            ScoringMode::BM25 => self.bm25(&doc_tokens, &query_tokens, norm, &self.term_doc_freq),
+            ScoringMode::TfIdf => {
+                self.tf_idf(&doc_tokens, &query_tokens, doc_len, &self.term_doc_freq)
+            }
+        };
+
+        if score > 0.0 {
+            let positions = positional_index(&doc_tokens);
+            for phrase in query.phrases() {
+                if contains_phrase(&positions, phrase) {
+                    score *= self.phrase_bonus;
+                }
+            }
         }
 
-        let mut score = 0.0;
+        if self.field_boost > 0.0 {
+            score += self.field_boost * self.field_score(memory, &query_tokens);
+        }
 
-        for query_term in query_tokens {
-            let tf = *term_freq.get(query_term).unwrap_or(&0) as f32;
+        if self.ngram_size.is_some() && self.ngram_weight > 0.0 {
+            let doc_ngrams = self.ngrams(&doc_tokens);
+            let query_ngrams = self.ngrams(&query_tokens);
+            let freq = &self.ngram_term_doc_freq;
+            score += self.ngram_weight
+                * match self.scoring_mode {
+                    ScoringMode::BM25 => self.bm25(&doc_ngrams, &query_ngrams, norm, freq),
+                    ScoringMode::TfIdf => {
+                        self.tf_idf(&doc_ngrams, &query_ngrams, doc_ngrams.len(), freq)
+                    }
+                };
+        }
+
+        score
+    }
+
+    /// BM25 of the "fields" pseudo-document: tags and AST node type, with `_` and `-`
+    /// separating words so `struct_item` matches `struct`. Fields are short labels, so
+    /// no length normalization is applied.
+    fn field_score(&self, memory: &Memory, query_tokens: &[String]) -> f32 {
+        let fields = format!(
+            "{} {}",
+            memory.metadata.tags.join(" "),
+            memory.metadata.ast_node_type.as_deref().unwrap_or("")
+        )
+        .replace(['_', '-'], " ");
+        let field_tokens = self.tokenize(&fields);
+        let freq = &self.term_doc_freq;
+        match self.scoring_mode {
+            ScoringMode::BM25 => self.bm25(&field_tokens, query_tokens, 1.0, freq),
+            ScoringMode::TfIdf => {
+                self.tf_idf(&field_tokens, query_tokens, field_tokens.len(), freq)
+            }
+        }
+    }
+
+    /// Sum of per-term BM25 weights of `query_tokens` in `doc_tokens`, with IDF from
+    /// `doc_freq` and `norm` the document length normalization factor
+    fn bm25(
+        &self,
+        doc_tokens: &[String],
+        query_tokens: &[String],
+        norm: f32,
+        doc_freq: &HashMap<String, usize>,
+    ) -> f32 {
+        let mut term_freq: HashMap<&str, usize> = HashMap::new();
+        for token in doc_tokens {
+            *term_freq.entry(token.as_str()).or_insert(0) += 1;
+        }
 
+        let mut score = 0.0;
+        for query_term in query_tokens {
+            let tf = *term_freq.get(query_term.as_str()).unwrap_or(&0) as f32;
             if tf == 0.0 {
                 continue;
             }
 
-            let df = *self.term_doc_freq.get(query_term).unwrap_or(&0) as f32;
+            let df = *doc_freq.get(query_term).unwrap_or(&0) as f32;
             let idf = ((self.doc_count as f32 - df + 0.5) / (df + 0.5) + 1.0).ln();
-
-            let norm = 1.0 - self.b + self.b * (doc_len as f32 / self.avg_doc_length.max(1.0));
             let tf_norm = (tf * (self.k1 + 1.0)) / (tf + self.k1 * norm);
 
             score += idf * tf_norm;
         }
+        score
+    }
+
+    /// Sum over `query_tokens` of `count / doc_len` times `ln((N + 1) / (df + 1)) + 1`,
+    /// with N from the indexed content and df from `doc_freq`
+    fn tf_idf(
+        &self,
+        doc_tokens: &[String],
+        query_tokens: &[String],
+        doc_len: usize,
+        doc_freq: &HashMap<String, usize>,
+    ) -> f32 {
+        if doc_len == 0 {
+            return 0.0;
+        }
 
+        let mut score = 0.0;
+        for query_term in query_tokens {
+            let count = doc_tokens.iter().filter(|t| *t == query_term).count();
+            if count == 0 {
+                continue;
+            }
+
+            let df = *doc_freq.get(query_term).unwrap_or(&0) as f32;
+            let idf = ((self.doc_count as f32 + 1.0) / (df + 1.0)).ln() + 1.0;
+            score += count as f32 / doc_len as f32 * idf;
+        }
         score
     }
 
@@ -141,18 +539,176 @@ impl BM25SearchEngine {
         }
     }
 
-    pub fn reindex_all(&mut self, memories: &[Memory]) {
+    /// Like [`Self::remove_memory`], but also takes the memory's terms out of the
+    /// document frequencies so IDF no longer counts the deleted document. `content`
+    /// must be the content the memory was indexed with.
+    pub fn remove_memory_full(&mut self, memory_id: &str, content: &str) {
+        if !self.doc_lengths.contains_key(memory_id) {
+            return;
+        }
+        self.remove_memory(memory_id);
+
+        let tokens = self.tokenize(content);
+        let unique_ngrams: HashSet<String> = self.ngrams(&tokens).into_iter().collect();
+        let unique_terms: HashSet<String> = tokens.into_iter().collect();
+        decrement_doc_freq(&mut self.term_doc_freq, unique_terms);
+        decrement_doc_freq(&mut self.ngram_term_doc_freq, unique_ngrams);
+    }
+
+    /// Number of indexed documents containing `term` (an already tokenized term)
+    pub fn document_frequency(&self, term: &str) -> usize {
+        self.term_doc_freq.get(term).copied().unwrap_or(0)
+    }
+
+    /// Number of indexed documents
+    pub fn document_count(&self) -> usize {
+        self.doc_count
+    }
+
+    /// No documents indexed, e.g. an index not yet loaded or rebuilt after a restart
+    pub fn is_empty(&self) -> bool {
+        self.doc_count == 0
+    }
+
+    /// Engine whose statistics cover the documents of both `self` and `other`, e.g. to
+    /// search several scopes at once. Document frequencies of shared terms are added.
+    /// Tuning (k1, b, stop words, stemming, boosts) comes from `self`; the two indices
+    /// are expected to hold disjoint memory IDs.
+    pub fn merge(&self, other: &BM25SearchEngine) -> BM25SearchEngine {
+        let mut merged = self.clone();
+
+        for (id, len) in &other.doc_lengths {
+            merged.doc_lengths.insert(id.clone(), *len);
+        }
+        for (term, freq) in &other.term_doc_freq {
+            *merged.term_doc_freq.entry(term.clone()).or_insert(0) += freq;
+        }
+        for (ngram, freq) in &other.ngram_term_doc_freq {
+            *merged.ngram_term_doc_freq.entry(ngram.clone()).or_insert(0) += freq;
+        }
+
+        merged.doc_count = merged.doc_lengths.len();
+        merged.avg_doc_length = if merged.doc_count > 0 {
+            merged.doc_lengths.values().sum::<usize>() as f32 / merged.doc_count as f32
+        } else {
+            0.0
+        };
+
+        merged
+    }
+
+    /// Rebuild the index from `memories`, indexed one at a time: a slice, or owned
+    /// memories streamed from storage that are never held in memory as a whole
+    pub fn reindex_all<M: Borrow<Memory>>(&mut self, memories: impl IntoIterator<Item = M>) {
         self.doc_lengths.clear();
         self.term_doc_freq.clear();
+        self.ngram_term_doc_freq.clear();
         self.doc_count = 0;
         self.avg_doc_length = 0.0;
 
         for memory in memories {
-            self.index_memory(memory);
+            self.index_memory(memory.borrow());
         }
     }
 }
 
+/// Take one document out of the frequency of each of `terms`, dropping terms at zero
+fn decrement_doc_freq(doc_freq: &mut HashMap<String, usize>, terms: HashSet<String>) {
+    for term in terms {
+        if let Some(freq) = doc_freq.get_mut(&term) {
+            *freq = freq.saturating_sub(1);
+            if *freq == 0 {
+                doc_freq.remove(&term);
+            }
+        }
+    }
+}
+
+/// Token -> positions at which it occurs
+fn positional_index(tokens: &[String]) -> HashMap<&str, Vec<usize>> {
+    let mut positions: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (pos, token) in tokens.iter().enumerate() {
+        positions.entry(token.as_str()).or_default().push(pos);
+    }
+    positions
+}
+
+fn contains_phrase(positions: &HashMap<&str, Vec<usize>>, phrase: &[String]) -> bool {
+    let Some((first, rest)) = phrase.split_first() else {
+        return false;
+    };
+
+    positions.get(first.as_str()).is_some_and(|starts| {
+        starts.iter().any(|&start| {
+            rest.iter().enumerate().all(|(offset, term)| {
+                positions
+                    .get(term.as_str())
+                    .is_some_and(|p| p.contains(&(start + offset + 1)))
+            })
+        })
+    })
+}
+
+/// Snowball algorithm for an ISO 639-1 code or English language name
+fn stemming_algorithm(language: &str) -> Result<Algorithm> {
+    let algorithm = match language.to_lowercase().as_str() {
+        "ar" | "arabic" => Algorithm::Arabic,
+        "da" | "danish" => Algorithm::Danish,
+        "nl" | "dutch" => Algorithm::Dutch,
+        "en" | "english" => Algorithm::English,
+        "fi" | "finnish" => Algorithm::Finnish,
+        "fr" | "french" => Algorithm::French,
+        "de" | "german" => Algorithm::German,
+        "el" | "greek" => Algorithm::Greek,
+        "hu" | "hungarian" => Algorithm::Hungarian,
+        "it" | "italian" => Algorithm::Italian,
+        "no" | "norwegian" => Algorithm::Norwegian,
+        "pt" | "portuguese" => Algorithm::Portuguese,
+        "ro" | "romanian" => Algorithm::Romanian,
+        "ru" | "russian" => Algorithm::Russian,
+        "es" | "spanish" => Algorithm::Spanish,
+        "sv" | "swedish" => Algorithm::Swedish,
+        "ta" | "tamil" => Algorithm::Tamil,
+        "tr" | "turkish" => Algorithm::Turkish,
+        other => anyhow::bail!("Unsupported stemming language: {}", other),
+    };
+    Ok(algorithm)
+}
+
+/// Interpolates between no boost (factor 0.0) and the raw importance score (factor 1.0)
+fn importance_multiplier(importance_score: f32, factor: f32) -> f32 {
+    (1.0 + factor * (importance_score - 1.0)).max(0.0)
+}
+
+/// `content` with every whole-word, case-insensitive occurrence of `tokens` wrapped in
+/// `prefix` and `suffix`, e.g. "Rust" becomes "**Rust**" for the token "rust"
+pub fn highlight_tokens(content: &str, tokens: &[String], prefix: &str, suffix: &str) -> String {
+    if tokens.is_empty() {
+        return content.to_string();
+    }
+    let alternatives: Vec<String> = tokens.iter().map(|t| regex::escape(t)).collect();
+    let pattern = format!(r"(?i)\b(?:{})\b", alternatives.join("|"));
+    match Regex::new(&pattern) {
+        Ok(re) => re
+            .replace_all(content, |caps: &regex::Captures| {
+                format!("{}{}{}", prefix, &caps[0], suffix)
+            })
+            .into_owned(),
+        Err(_) => content.to_string(),
+    }
+}
+
+/// Halve `score` every `config.time_decay_half_life_days` of memory age.
+/// Returns `score` unchanged when decay is disabled or the half-life is not positive.
+pub fn score_with_decay(score: f32, memory: &Memory, config: &SearchConfig) -> f32 {
+    if !config.time_decay_enabled || config.time_decay_half_life_days <= 0.0 {
+        return score;
+    }
+
+    let age_days = (chrono::Utc::now() - memory.created_at).num_days().max(0) as f32;
+    score * (-std::f32::consts::LN_2 * age_days / config.time_decay_half_life_days).exp()
+}
+
 impl Default for BM25SearchEngine {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,365 @@
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+use rag_search::{highlight_tokens, score_with_decay, BM25SearchEngine, QueryClause};
+
+fn memory(content: &str, importance_score: f32) -> Memory {
+    Memory::new(
+        content.to_string(),
+        MemoryScope::session(),
+        MemoryMetadata {
+            importance_score,
+            ..Default::default()
+        },
+    )
+}
+
+fn indexed(memories: &[Memory]) -> BM25SearchEngine {
+    let mut engine = BM25SearchEngine::new();
+    engine.reindex_all(memories);
+    engine
+}
+
+#[test]
+fn importance_boost_outranks_higher_bm25_score() {
+    let memories = vec![
+        memory("cache cache cache invalidation", 1.0),
+        memory("cache notes about deployment scripts and rollout", 3.0),
+    ];
+    let engine = indexed(&memories);
+
+    let unboosted = engine.search_with_boost("cache", &memories, 2, 0.0, 0.0);
+    assert_eq!(unboosted[0].memory.id, memories[0].id);
+
+    let boosted = engine.search("cache", &memories, 2, 0.0);
+    assert_eq!(boosted[0].memory.id, memories[1].id);
+    assert_eq!(boosted[0].rank, 0);
+}
+
+#[test]
+fn importance_boost_factor_zero_disables_boost() {
+    let memories = vec![
+        memory("cache cache cache invalidation", 1.0),
+        memory("cache notes about deployment scripts and rollout", 3.0),
+    ];
+    let engine = indexed(&memories).with_importance_boost(0.0);
+
+    let results = engine.search("cache", &memories, 2, 0.0);
+    assert_eq!(results[0].memory.id, memories[0].id);
+}
+
+#[test]
+fn time_decay_ranks_newer_memory_first() {
+    let mut old = memory("deploy checklist for staging", 1.0);
+    old.created_at = chrono::Utc::now() - chrono::Duration::days(60);
+    let new = memory("deploy checklist for staging", 1.0);
+
+    let mut config = rag_core::config::Config::default().search;
+    config.time_decay_enabled = true;
+
+    let old_score = score_with_decay(2.0, &old, &config);
+    let new_score = score_with_decay(2.0, &new, &config);
+    assert!(new_score > old_score);
+    // Two half-lives old: a quarter of the original score
+    assert!((old_score - 0.5).abs() < 1e-4, "Got: {}", old_score);
+
+    config.time_decay_enabled = false;
+    assert_eq!(score_with_decay(2.0, &old, &config), 2.0);
+}
+
+#[test]
+fn custom_stop_word_does_not_contribute_to_scores() {
+    let memories = vec![memory("rust ownership rules", 1.0)];
+    let mut engine = indexed(&memories);
+    assert_eq!(engine.search("rust", &memories, 5, 0.0).len(), 1);
+
+    engine.add_stop_word("Rust".to_string());
+    engine.reindex_all(&memories);
+    assert!(engine.search("rust", &memories, 5, 0.0).is_empty());
+
+    engine.remove_stop_word("rust");
+    engine.reindex_all(&memories);
+    assert_eq!(engine.search("rust", &memories, 5, 0.0).len(), 1);
+}
+
+#[test]
+fn config_stop_words_merge_with_defaults() -> anyhow::Result<()> {
+    let memories = vec![memory("the rust borrow checker", 1.0)];
+
+    let mut config = rag_core::config::Config::default().search;
+    config.stop_words = Some(vec!["rust".to_string()]);
+    let mut engine = BM25SearchEngine::new_with_config(&config)?;
+    engine.reindex_all(&memories);
+    assert!(engine.search("rust", &memories, 5, 0.0).is_empty());
+    assert!(engine.search("the", &memories, 5, 0.0).is_empty());
+
+    config.replace_default_stop_words = true;
+    let mut engine = BM25SearchEngine::new_with_config(&config)?;
+    engine.reindex_all(&memories);
+    assert_eq!(engine.search("the", &memories, 5, 0.0).len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn stemming_matches_inflected_query_terms() -> anyhow::Result<()> {
+    let memories = vec![memory("how to program a microcontroller", 1.0)];
+
+    let mut config = rag_core::config::Config::default().search;
+    let mut engine = BM25SearchEngine::new_with_config(&config)?;
+    engine.reindex_all(&memories);
+    assert!(engine.search("programming", &memories, 5, 0.0).is_empty());
+
+    config.stemming_enabled = true;
+    let mut engine = BM25SearchEngine::new_with_config(&config)?;
+    engine.reindex_all(&memories);
+    assert_eq!(engine.search("programming", &memories, 5, 0.0).len(), 1);
+    assert_eq!(engine.search("programmed", &memories, 5, 0.0).len(), 1);
+
+    config.stemming_language = "klingon".to_string();
+    assert!(BM25SearchEngine::new_with_config(&config).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn parse_query_separates_phrases_from_terms() {
+    let engine = BM25SearchEngine::new();
+    let parsed = engine.parse_query(r#"rust "memory safety" borrow"#);
+
+    assert_eq!(
+        parsed.clauses,
+        vec![
+            QueryClause::Term("rust".to_string()),
+            QueryClause::Phrase(vec!["memory".to_string(), "safety".to_string()]),
+            QueryClause::Term("borrow".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn phrase_match_ranks_above_scattered_terms() {
+    let memories = vec![
+        memory("safety checks guard shared memory access", 1.0),
+        memory("rust guarantees memory safety without gc", 1.0),
+    ];
+    let engine = indexed(&memories);
+
+    let results = engine.search(r#""memory safety""#, &memories, 2, 0.0);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].memory.id, memories[1].id);
+    assert!(results[0].score > results[1].score * 1.5);
+}
+
+#[test]
+fn merged_engines_rank_like_one_engine_over_all_memories() {
+    let session = vec![
+        memory("rust borrow checker rules", 1.0),
+        memory("deploy rust service to staging", 1.0),
+    ];
+    let global = vec![
+        memory("rust ownership and borrowing", 1.0),
+        memory("staging database migration notes", 1.0),
+        memory("coffee brewing guide", 1.0),
+    ];
+    let combined: Vec<Memory> = session.iter().chain(&global).cloned().collect();
+
+    let merged = indexed(&session).merge(&indexed(&global));
+    let single = indexed(&combined);
+
+    for query in ["rust", "staging", "rust staging borrow"] {
+        let expected = single.search(query, &combined, 5, 0.0);
+        let actual = merged.search(query, &combined, 5, 0.0);
+        assert_eq!(actual.len(), expected.len(), "{}", query);
+        for (a, e) in actual.iter().zip(&expected) {
+            assert_eq!(a.memory.id, e.memory.id, "{}", query);
+            assert!((a.score - e.score).abs() < 1e-6, "{}", query);
+        }
+    }
+}
+
+#[test]
+fn remove_memory_full_drops_unique_terms() {
+    let memories = vec![
+        memory("xyzzy opens the shared cave", 1.0),
+        memory("the shared cave is dark", 1.0),
+    ];
+    let mut engine = indexed(&memories);
+    assert_eq!(engine.document_frequency("xyzzy"), 1);
+    assert_eq!(engine.document_frequency("cave"), 2);
+
+    engine.remove_memory_full(&memories[0].id, &memories[0].content);
+    assert_eq!(engine.document_frequency("xyzzy"), 0);
+    assert_eq!(engine.document_frequency("cave"), 1);
+
+    // Removing twice must not decrement the surviving memory's terms
+    engine.remove_memory_full(&memories[0].id, &memories[0].content);
+    assert_eq!(engine.document_frequency("cave"), 1);
+}
+
+#[test]
+fn min_score_drops_weak_matches() {
+    let memories = vec![
+        memory(
+            "borrow checker rejects dangling lifetimes in moved async closures",
+            1.0,
+        ),
+        memory("the checker script runs nightly", 1.0),
+        memory("grocery list: apples, bread", 1.0),
+    ];
+    let engine = indexed(&memories);
+    let query = "borrow checker dangling lifetimes moved async closures";
+
+    let all = engine.search(query, &memories, 5, 0.0);
+    assert_eq!(all.len(), 2);
+    assert!(all[0].score >= 5.0, "strong match scored {}", all[0].score);
+    assert!(all[1].score > 0.0 && all[1].score < 5.0);
+
+    let filtered = engine.search(query, &memories, 5, 5.0);
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].memory.id, memories[0].id);
+}
+
+#[test]
+fn ast_node_type_match_outranks_content_mentions() {
+    let mut declaration = memory("pub struct Config { path: PathBuf }", 1.0);
+    declaration.metadata.ast_node_type = Some("struct_item".to_string());
+    let memories = vec![
+        memory("notes: a struct groups fields, see the struct chapter", 1.0),
+        declaration,
+    ];
+    let engine = indexed(&memories);
+
+    let results = engine.search("struct", &memories, 2, 0.0);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].memory.id, memories[1].id);
+
+    // Without field boosting the content mentions win
+    let unboosted = engine
+        .with_field_boost(0.0)
+        .search("struct", &memories, 2, 0.0);
+    assert_eq!(unboosted[0].memory.id, memories[0].id);
+}
+
+#[test]
+fn tf_idf_scores_match_hand_computed_values() {
+    use rag_core::config::ScoringMode;
+
+    let memories = vec![memory("apple banana", 1.0), memory("apple apple", 1.0)];
+    let mut engine = BM25SearchEngine::new().with_scoring_mode(ScoringMode::TfIdf);
+    engine.reindex_all(&memories);
+    let score = |doc: &Memory, query: &str| engine.score_document(doc, &engine.parse_query(query));
+
+    // N = 2; idf = ln((N + 1) / (df + 1)) + 1
+    let idf_banana = (3.0f32 / 2.0).ln() + 1.0;
+    let idf_apple = 1.0;
+    assert!((score(&memories[0], "banana") - 0.5 * idf_banana).abs() < 1e-6);
+    assert_eq!(score(&memories[1], "banana"), 0.0);
+    assert!((score(&memories[0], "apple") - 0.5 * idf_apple).abs() < 1e-6);
+    assert!((score(&memories[1], "apple") - 1.0 * idf_apple).abs() < 1e-6);
+    assert!(
+        (score(&memories[0], "apple banana") - (0.5 * idf_apple + 0.5 * idf_banana)).abs() < 1e-6
+    );
+}
+
+#[test]
+fn ngrams_match_partial_words() {
+    let memories = vec![
+        memory("notes on functional programming", 1.0),
+        memory("grocery list: apples, bread", 1.0),
+    ];
+
+    let plain = indexed(&memories);
+    assert!(plain.search("prog", &memories, 5, 0.0).is_empty());
+
+    let mut engine = BM25SearchEngine::new().with_ngrams(3, 0.3);
+    engine.reindex_all(&memories);
+    let results = engine.search("prog", &memories, 5, 0.0);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].memory.id, memories[0].id);
+}
+
+#[test]
+fn filter_excludes_memories_before_scoring() {
+    let memories = vec![
+        memory("cache cache cache invalidation", 1.0),
+        memory("cache cache eviction", 1.0),
+        memory("cache warmup", 1.0),
+    ];
+    let engine = indexed(&memories);
+
+    let all = engine.search("cache", &memories, 10, 0.0);
+    assert_eq!(all.len(), 3);
+
+    // The best-scoring memory is filtered out, so it can't take the only slot
+    let filtered = engine.search_with_filter("cache", &memories, 1, |m| m.id != memories[0].id);
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].memory.id, memories[1].id);
+    assert_eq!(filtered[0].rank, 0);
+
+    let filtered = engine.search_with_filter("cache", &memories, 10, |m| m.id == memories[2].id);
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].memory.id, memories[2].id);
+}
+
+#[test]
+fn deserialized_engine_scores_like_the_original() {
+    let memories = vec![
+        memory("cache invalidation strategies for the session store", 1.0),
+        memory("deployment scripts and cache warmup", 2.0),
+        memory("grocery list: apples, bread", 1.0),
+    ];
+    let mut engine = BM25SearchEngine::new().with_ngrams(3, 0.3);
+    engine.reindex_all(&memories);
+    assert!(!engine.is_empty());
+    assert!(BM25SearchEngine::new().is_empty());
+
+    let json = serde_json::to_string(&engine).unwrap();
+    let restored: BM25SearchEngine = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.document_count(), memories.len());
+
+    let query = engine.parse_query("cache warm");
+    for m in &memories {
+        assert_eq!(
+            engine.score_document(m, &query),
+            restored.score_document(m, &restored.parse_query("cache warm"))
+        );
+    }
+    let original = engine.search("cache", &memories, 5, 0.0);
+    let results = restored.search("cache", &memories, 5, 0.0);
+    assert_eq!(original.len(), results.len());
+    for (a, b) in original.iter().zip(&results) {
+        assert_eq!(a.memory.id, b.memory.id);
+        assert_eq!(a.score, b.score);
+    }
+}
+
+#[test]
+fn highlight_tokens_wraps_whole_words_ignoring_case() {
+    let tokens = vec!["rust".to_string()];
+    let highlighted = highlight_tokens(
+        "Rust and rust-analyzer, not Rustacean; RUST.",
+        &tokens,
+        "**",
+        "**",
+    );
+    assert_eq!(
+        highlighted,
+        "**Rust** and **rust**-analyzer, not Rustacean; **RUST**."
+    );
+
+    assert_eq!(
+        highlight_tokens("borrow checker", &["borrow".to_string()], "<b>", "</b>"),
+        "<b>borrow</b> checker"
+    );
+    assert_eq!(highlight_tokens("unchanged", &[], "**", "**"), "unchanged");
+}
+
+#[test]
+fn query_words_are_unstemmed_and_skip_stop_words() {
+    let mut config = rag_core::config::Config::default().search;
+    config.stemming_enabled = true;
+    let engine = BM25SearchEngine::new_with_config(&config).unwrap();
+    assert_eq!(
+        engine.query_words("The Running \"running tests\""),
+        vec!["running".to_string(), "tests".to_string()]
+    );
+}
@@ -0,0 +1,96 @@
+use rag_core::storage::MemoryStore;
+use rag_core::{Memory, MemoryMetadata, MemoryScope};
+use rag_search::BM25SearchEngine;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tracks live and peak heap bytes so the test can compare memory spikes
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(current, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Peak heap growth while running `f`
+fn peak_growth<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let value = f();
+    (value, PEAK.load(Ordering::SeqCst) - baseline)
+}
+
+#[test]
+fn streamed_reindex_matches_listed_reindex_with_less_memory() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rag-search-reindex-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    let mut store = MemoryStore::new(dir.join("global.db"))?;
+    let scope = MemoryScope::Global;
+
+    // Large contents with few distinct terms: the index stays small, the memories don't
+    let padding = "padding ".repeat(128);
+    let memories: Vec<Memory> = (0..10_000)
+        .map(|i| {
+            Memory::new(
+                format!("note {} {}", i % 10, padding),
+                scope.clone(),
+                MemoryMetadata::default(),
+            )
+        })
+        .collect();
+    store.batch_store(memories)?;
+
+    let (listed, list_peak) = peak_growth(|| -> anyhow::Result<BM25SearchEngine> {
+        let mut engine = BM25SearchEngine::new();
+        engine.reindex_all(&store.list_all(&scope)?);
+        Ok(engine)
+    });
+    let listed = listed?;
+
+    let (streamed, stream_peak) = peak_growth(|| -> anyhow::Result<BM25SearchEngine> {
+        let mut engine = BM25SearchEngine::new();
+        engine.reindex_all(store.iter_all(&scope)?.map(Result::unwrap));
+        Ok(engine)
+    });
+    let streamed = streamed?;
+
+    assert_eq!(streamed.document_count(), 10_000);
+    let probe = Memory::new(
+        format!("note 3 {}", padding),
+        scope.clone(),
+        MemoryMetadata::default(),
+    );
+    let query = listed.parse_query("note 3");
+    assert_eq!(
+        listed.score_document(&probe, &query),
+        streamed.score_document(&probe, &streamed.parse_query("note 3"))
+    );
+    assert!(
+        stream_peak * 2 < list_peak,
+        "streamed peak {} bytes vs listed peak {} bytes",
+        stream_peak,
+        list_peak
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
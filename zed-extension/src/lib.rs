@@ -1,33 +1,100 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
 use std::fs;
-use zed_extension_api::{self as zed, Command, ContextServerId, Project, Result};
+use zed_extension_api::settings::ContextServerSettings;
+use zed_extension_api::{
+    self as zed, Command, ContextServerConfiguration, ContextServerId, Project, Result,
+};
 
 const REPO_NAME: &str = "Vany/totalrecall";
 const BINARY_NAME: &str = "rag-mcp";
+/// Oldest local `rag-mcp` (`binary_path`) used instead of downloading a release
+const MIN_LOCAL_VERSION: &str = "0.1.0";
+
+/// `context_servers.totalrecall.settings` in Zed's settings.json, passed to the server
+/// as `RAG_MCP_*` environment variables. Unset fields keep the server's config.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+struct TotalRecallSettings {
+    /// Directory holding the global database
+    db_path: Option<String>,
+    /// "error", "warn", "info", "debug" or "trace"
+    log_level: Option<String>,
+    /// BM25 term frequency saturation
+    bm25_k1: Option<f32>,
+    /// BM25 length normalization
+    bm25_b: Option<f32>,
+    /// Number of search results when the caller gives none
+    default_k: Option<usize>,
+    /// Absolute path of a local `rag-mcp` to run instead of downloading a release. It
+    /// is used when `rag-mcp version --json` reports at least the supported version.
+    /// The extension sandbox has no PATH to search, so the path must be spelled out.
+    binary_path: Option<String>,
+    /// Download the newest pre-release instead of the newest stable release
+    #[serde(default)]
+    pre_release: bool,
+}
+
+impl TotalRecallSettings {
+    fn for_server(context_server_id: &ContextServerId, project: &Project) -> Result<Self> {
+        let settings = ContextServerSettings::for_project(context_server_id.as_ref(), project)?;
+        match settings.settings {
+            Some(settings) => serde_json::from_value(settings)
+                .map_err(|e| format!("invalid totalrecall settings: {e}")),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn env(&self) -> Vec<(String, String)> {
+        [
+            ("RAG_MCP_DB_PATH", self.db_path.clone()),
+            ("RAG_MCP_LOG_LEVEL", self.log_level.clone()),
+            ("RAG_MCP_BM25_K1", self.bm25_k1.map(|v| v.to_string())),
+            ("RAG_MCP_BM25_B", self.bm25_b.map(|v| v.to_string())),
+            ("RAG_MCP_DEFAULT_K", self.default_k.map(|v| v.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value?)))
+        .collect()
+    }
+}
 
 struct TotalRecallExtension {
-    cached_binary_path: Option<String>,
+    /// Resolved binary and the `pre_release` setting it was resolved under
+    cached_binary_path: Option<(bool, String)>,
 }
 
 impl TotalRecallExtension {
+    /// `probe_version` runs `<binary_path> version --json` (`local_version` outside
+    /// tests); `fetch` looks up releases (`zed::latest_github_release` outside tests)
     fn context_server_binary_path(
         &mut self,
-        _context_server_id: &ContextServerId,
+        settings: &TotalRecallSettings,
+        probe_version: impl FnOnce(&str) -> Option<String>,
+        fetch: impl FnOnce(&str, zed::GithubReleaseOptions) -> Result<zed::GithubRelease>,
     ) -> Result<String> {
+        // A local install skips the download entirely. The sandbox cannot stat host
+        // paths, so the binary is checked by running it rather than cached.
+        if let Some(path) = &settings.binary_path {
+            if let Some(path) = local_binary_path(path, probe_version)? {
+                eprintln!("totalrecall: using local {BINARY_NAME} at {path}");
+                return Ok(path);
+            }
+        }
+
         // Check cache first
-        if let Some(path) = &self.cached_binary_path {
-            if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
+        if let Some((pre_release, path)) = &self.cached_binary_path {
+            if *pre_release == settings.pre_release
+                && fs::metadata(path).map_or(false, |stat| stat.is_file())
+            {
                 return Ok(path.clone());
             }
         }
 
         // Get latest release from GitHub
-        let release = zed::latest_github_release(
-            REPO_NAME,
-            zed::GithubReleaseOptions {
-                require_assets: true,
-                pre_release: false,
-            },
-        )?;
+        if settings.pre_release {
+            eprintln!("totalrecall: pre_release is set, resolving the newest pre-release build");
+        }
+        let release = latest_release(fetch, settings.pre_release)?;
 
         // Determine platform and architecture
         let (platform, arch) = zed::current_platform();
@@ -51,15 +118,10 @@ impl TotalRecallExtension {
             }
         );
 
-        // Find matching asset
-        let asset = release
-            .assets
-            .iter()
-            .find(|asset| asset.name == asset_name)
-            .ok_or_else(|| format!("no asset found matching {:?}", asset_name))?;
+        let asset = find_asset(&release, &asset_name)?;
 
         // Create version-specific directory
-        let version_dir = format!("{BINARY_NAME}-{}", release.version);
+        let version_dir = version_dir(&release.version, settings.pre_release);
         fs::create_dir_all(&version_dir)
             .map_err(|err| format!("failed to create directory '{version_dir}': {err}"))?;
 
@@ -95,11 +157,117 @@ impl TotalRecallExtension {
         }
 
         // Cache the path
-        self.cached_binary_path = Some(binary_path.clone());
+        eprintln!("totalrecall: using downloaded {BINARY_NAME} at {binary_path}");
+        self.cached_binary_path = Some((settings.pre_release, binary_path.clone()));
         Ok(binary_path)
     }
 }
 
+/// Newest release as returned by `fetch` (`zed::latest_github_release` outside tests),
+/// pre-releases included when `pre_release` is set
+fn latest_release(
+    fetch: impl FnOnce(&str, zed::GithubReleaseOptions) -> Result<zed::GithubRelease>,
+    pre_release: bool,
+) -> Result<zed::GithubRelease> {
+    fetch(
+        REPO_NAME,
+        zed::GithubReleaseOptions {
+            require_assets: true,
+            pre_release,
+        },
+    )
+}
+
+fn find_asset<'a>(
+    release: &'a zed::GithubRelease,
+    asset_name: &str,
+) -> Result<&'a zed::GithubReleaseAsset> {
+    release
+        .assets
+        .iter()
+        .find(|asset| asset.name == asset_name)
+        .ok_or_else(|| format!("no asset found matching {:?}", asset_name))
+}
+
+/// Download directory of a release; stable and pre-release builds never share one, so
+/// toggling `pre_release` cannot pick up the other channel's binary
+fn version_dir(version: &str, pre_release: bool) -> String {
+    let channel = if pre_release { "pre" } else { "stable" };
+    format!("{BINARY_NAME}-{version}-{channel}")
+}
+
+/// `path` when `probe_version` reports it at least `MIN_LOCAL_VERSION`; None (download
+/// instead) when it is older or does not answer. `path` must be absolute and name a
+/// `rag-mcp` binary: the `process:exec` capability cannot list the user's path, so
+/// this is what keeps the probe from running any other program.
+fn local_binary_path(
+    path: &str,
+    probe_version: impl FnOnce(&str) -> Option<String>,
+) -> Result<Option<String>> {
+    if !is_absolute(path) {
+        return Err(format!(
+            "binary_path must be an absolute path, got {path:?}"
+        ));
+    }
+    let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    if file_name != BINARY_NAME && file_name != format!("{BINARY_NAME}.exe") {
+        return Err(format!(
+            "binary_path must point at a {BINARY_NAME} binary, got {path:?}"
+        ));
+    }
+    let Some(version) = probe_version(path) else {
+        eprintln!("totalrecall: ignoring {path}: `{BINARY_NAME} version --json` failed");
+        return Ok(None);
+    };
+    if !version_at_least(&version, MIN_LOCAL_VERSION) {
+        eprintln!(
+            "totalrecall: ignoring {path}: version {version} is older than {MIN_LOCAL_VERSION}"
+        );
+        return Ok(None);
+    }
+    Ok(Some(path.to_string()))
+}
+
+/// Version reported by `<path> version --json`, run through Zed's process API
+fn local_version(path: &str) -> Option<String> {
+    let output = zed::process::Command::new(path)
+        .args(["version", "--json"])
+        .output()
+        .ok()?;
+    parse_version_output(&output.stdout)
+}
+
+/// Absolute on the host: `/...`, or a Windows drive or UNC path. `Path::is_absolute`
+/// answers for the WASI guest, not for the host that runs the binary.
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || path.starts_with("\\\\")
+        || (bytes.len() > 2
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && matches!(bytes[2], b'\\' | b'/'))
+}
+
+/// `version` field of `rag-mcp version --json` output
+fn parse_version_output(stdout: &[u8]) -> Option<String> {
+    let info: serde_json::Value = serde_json::from_slice(stdout).ok()?;
+    info["version"].as_str().map(String::from)
+}
+
+/// Compare dotted numeric versions; a pre-release suffix (`-rc.1`) is ignored
+fn version_at_least(version: &str, minimum: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        let release = version
+            .trim_start_matches('v')
+            .split('-')
+            .next()
+            .unwrap_or("");
+        release.split('.').map(|p| p.parse().unwrap_or(0)).collect()
+    }
+    parts(version) >= parts(minimum)
+}
+
 impl zed::Extension for TotalRecallExtension {
     fn new() -> Self {
         Self {
@@ -110,14 +278,171 @@ impl zed::Extension for TotalRecallExtension {
     fn context_server_command(
         &mut self,
         context_server_id: &ContextServerId,
-        _project: &Project,
+        project: &Project,
     ) -> Result<Command> {
+        let settings = TotalRecallSettings::for_server(context_server_id, project)?;
         Ok(Command {
-            command: self.context_server_binary_path(context_server_id)?,
+            command: self.context_server_binary_path(
+                &settings,
+                local_version,
+                zed::latest_github_release,
+            )?,
             args: vec!["serve".to_string()],
-            env: vec![],
+            env: settings.env(),
         })
     }
+
+    fn context_server_configuration(
+        &mut self,
+        _context_server_id: &ContextServerId,
+        _project: &Project,
+    ) -> Result<Option<ContextServerConfiguration>> {
+        let settings_schema = serde_json::to_string(&schemars::schema_for!(TotalRecallSettings))
+            .map_err(|e| e.to_string())?;
+        Ok(Some(ContextServerConfiguration {
+            installation_instructions: "Works without configuration. Settings override \
+                ~/.config/rag-mcp/config.toml."
+                .to_string(),
+            settings_schema,
+            default_settings: "{}".to_string(),
+        }))
+    }
 }
 
 zed::register_extension!(TotalRecallExtension);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_includes_only_set_settings() {
+        let settings = TotalRecallSettings {
+            db_path: Some("/tmp/recall".to_string()),
+            default_k: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.env(),
+            vec![
+                ("RAG_MCP_DB_PATH".to_string(), "/tmp/recall".to_string()),
+                ("RAG_MCP_DEFAULT_K".to_string(), "8".to_string()),
+            ]
+        );
+
+        assert!(TotalRecallSettings::default().env().is_empty());
+    }
+
+    #[test]
+    fn pre_release_setting_selects_pre_release_asset() {
+        let asset = |version: &str| zed::GithubReleaseAsset {
+            name: "rag-mcp_Linux_x86_64.tar.gz".to_string(),
+            download_url: format!("https://example.com/{version}/rag-mcp_Linux_x86_64.tar.gz"),
+        };
+        // Stands in for the GitHub API: pre-releases only when asked for
+        let mock_github = |repo: &str, options: zed::GithubReleaseOptions| {
+            assert_eq!(repo, REPO_NAME);
+            assert!(options.require_assets);
+            let version = if options.pre_release {
+                "0.2.0-rc.1"
+            } else {
+                "0.1.0"
+            };
+            Ok(zed::GithubRelease {
+                version: version.to_string(),
+                assets: vec![asset(version)],
+            })
+        };
+
+        let release = latest_release(mock_github, true).unwrap();
+        let selected = find_asset(&release, "rag-mcp_Linux_x86_64.tar.gz").unwrap();
+        assert!(selected.download_url.contains("0.2.0-rc.1"));
+        assert_eq!(
+            version_dir(&release.version, true),
+            "rag-mcp-0.2.0-rc.1-pre"
+        );
+
+        let release = latest_release(mock_github, false).unwrap();
+        let selected = find_asset(&release, "rag-mcp_Linux_x86_64.tar.gz").unwrap();
+        assert!(selected.download_url.contains("/0.1.0/"));
+        assert_eq!(version_dir(&release.version, false), "rag-mcp-0.1.0-stable");
+        assert!(find_asset(&release, "rag-mcp_Windows_i386.zip").is_err());
+    }
+
+    #[test]
+    fn local_binary_meeting_minimum_version_skips_download() {
+        let mut extension = TotalRecallExtension {
+            cached_binary_path: None,
+        };
+        let settings = TotalRecallSettings {
+            binary_path: Some("/opt/rag-mcp/bin/rag-mcp".to_string()),
+            ..Default::default()
+        };
+        let no_download = |_: &str, _: zed::GithubReleaseOptions| -> Result<zed::GithubRelease> {
+            panic!("a usable local binary must not trigger a release lookup")
+        };
+
+        let path = extension
+            .context_server_binary_path(
+                &settings,
+                |path| {
+                    assert_eq!(path, "/opt/rag-mcp/bin/rag-mcp");
+                    Some("0.2.0".to_string())
+                },
+                no_download,
+            )
+            .unwrap();
+        assert_eq!(path, "/opt/rag-mcp/bin/rag-mcp");
+
+        // Too old or not answering: fall back to downloading a release
+        let offline = |_: &str, _: zed::GithubReleaseOptions| -> Result<zed::GithubRelease> {
+            Err("offline".to_string())
+        };
+        for probed in [Some("0.0.9".to_string()), None] {
+            let result = extension.context_server_binary_path(&settings, |_| probed, offline);
+            assert_eq!(result, Err("offline".to_string()));
+        }
+
+        let relative = TotalRecallSettings {
+            binary_path: Some("bin/rag-mcp".to_string()),
+            ..Default::default()
+        };
+        assert!(extension
+            .context_server_binary_path(&relative, |_| unreachable!(), no_download)
+            .is_err());
+
+        // Only a rag-mcp binary is ever run to probe its version
+        for other in ["/usr/bin/python3", "C:\\tools\\rag-mcp-old.exe"] {
+            let settings = TotalRecallSettings {
+                binary_path: Some(other.to_string()),
+                ..Default::default()
+            };
+            assert!(extension
+                .context_server_binary_path(&settings, |_| unreachable!(), no_download)
+                .is_err());
+        }
+        assert_eq!(
+            local_binary_path("C:\\tools\\rag-mcp.exe", |_| Some("0.1.0".to_string())),
+            Ok(Some("C:\\tools\\rag-mcp.exe".to_string()))
+        );
+    }
+
+    #[test]
+    fn binary_path_must_be_absolute_on_host() {
+        assert!(is_absolute("/usr/local/bin/rag-mcp"));
+        assert!(is_absolute("C:\\tools\\rag-mcp.exe"));
+        assert!(is_absolute("\\\\server\\share\\rag-mcp.exe"));
+        assert!(!is_absolute("rag-mcp"));
+        assert!(!is_absolute("./bin/rag-mcp"));
+    }
+
+    #[test]
+    fn local_binary_must_meet_minimum_version() {
+        let version = parse_version_output(br#"{"version": "0.2.0", "git_sha": "abc"}"#).unwrap();
+        assert!(version_at_least(&version, MIN_LOCAL_VERSION));
+        assert!(version_at_least("0.1.0", "0.1.0"));
+        assert!(version_at_least("1.0.0-rc.1", "0.9.5"));
+        assert!(!version_at_least("0.0.9", "0.1.0"));
+        assert_eq!(parse_version_output(b"rag-mcp 0.1.0"), None);
+    }
+}